@@ -13,6 +13,8 @@ done
 
 echo "Test files: ${test_files[*]}"
 
+cargo build -q --bin as8086 || exit 1
+
 for a in tests/isa/*.asm; do
     fname=$(basename "$a" .asm)
     # Skip if test_files is not empty and the current file is not in the list
@@ -21,22 +23,37 @@ for a in tests/isa/*.asm; do
     fi
 
     b=`echo $a | sed 's/\.asm/.bin/'`
-    l=`echo $a | sed 's/\.asm/.dis/'`
-    rm -rf $b
-    nasm -f bin -DDEBUG -Itests -o$b $a
+    l=`echo $a | sed 's/\.asm/.lst/'`
+    m=`echo $a | sed 's/\.asm/.map/'`
+    rm -rf $b $l $m
+    target/debug/as8086 -f bin -DDEBUG -Itests -o$b -l$l -m$m $a
     if [ ! -f "$b" ]; then
         echo "Error: Failed to compile $a. Output file $b does not exist."
         exit 1
     fi
-    rm -rf $l
-    ndisasm -b 16 $b > $l
 done
 
 # Adjust the cargo command to use only the selected test files
 if [ ${#test_files[@]} -eq 0 ]; then
-    test_binaries="tests/isa/*.bin"
+    test_binaries=(tests/isa/*.bin)
 else
-    test_binaries=$(printf "tests/isa/%s.bin " "${test_files[@]}")
+    test_binaries=($(printf "tests/isa/%s.bin " "${test_files[@]}"))
 fi
 
-cargo r --bin emu8086 -- -test -hide-header $log -show-binary-name -dump-regs-on-halt $test_binaries
\ No newline at end of file
+# the tests named *-286 check the behavior of the 80286
+i8086=()
+i80286=()
+for b in "${test_binaries[@]}"; do
+    if [[ $b == *-286.bin ]]; then
+        i80286+=("$b")
+    else
+        i8086+=("$b")
+    fi
+done
+
+if [ ${#i8086[@]} -ne 0 ]; then
+    cargo r --bin emu8086 -- -test -hide-header $log -show-binary-name -load-map -dump-regs-on-halt -cpu-8086 "${i8086[@]}"
+fi
+if [ ${#i80286[@]} -ne 0 ]; then
+    cargo r --bin emu8086 -- -test -hide-header $log -show-binary-name -load-map -dump-regs-on-halt -cpu-80286 "${i80286[@]}"
+fi
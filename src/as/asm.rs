@@ -0,0 +1,576 @@
+// multi pass assembler: the passes pick the shortest forms with the values
+// known so far (forward references are taken as $ in the first one), and
+// are repeated until the labels and the sections stop moving. the final
+// pass then generates the code. if the labels keep moving, the operands
+// that depend on them get their widest encoding, so that the sizes settle
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use lib8086::{EncMode, Encoder, Rep, Width};
+
+use crate::error::{warning, AsmError, Loc, Result};
+use crate::expr::{Expr, Scope};
+use crate::insn::{self, Opnd, Val};
+use crate::parse::{self, Attr, Data, Line, OperandKind, Prefix, Stmt};
+use crate::pp;
+
+// passes before pinning the operands, then after
+const PASSES: usize = 16;
+const PINNED_PASSES: usize = 8;
+
+// the output of the assembler, with what the listing and the map need
+pub struct Program {
+    pub bin: Vec<u8>,
+    // the bytes of each preprocessed line
+    pub spans: Vec<Span>,
+    // the labels, with their values and their offsets in the binary
+    pub labels: Vec<(String, i64, usize)>,
+}
+
+pub struct Span {
+    pub addr: i64,
+    pub bytes: Vec<u8>,
+    pub repeat: Option<Repeat>,
+}
+
+// lines that are listed as one item and a count
+#[derive(Clone, Copy)]
+pub enum Repeat {
+    Times(i64),
+    Res,
+}
+
+struct Section {
+    name: String,
+    align: i64,
+    start: Option<i64>,
+    vstart: Option<i64>,
+    nobits: bool,
+    data: Vec<u8>,
+    // where the last layout put it: its offset in the binary, and the
+    // address of its first byte
+    pos: i64,
+    base: i64,
+}
+
+impl Section {
+    fn new(name: &str) -> Section {
+        Section {
+            name: name.to_string(),
+            align: 4,
+            start: None,
+            vstart: None,
+            nobits: name == ".bss",
+            data: Vec::new(),
+            pos: 0,
+            base: 0,
+        }
+    }
+
+    // the segment its addresses are offsets in, from the start of the
+    // binary
+    fn segment(&self) -> i64 {
+        (self.pos - self.base).div_euclid(16)
+    }
+}
+
+struct Assembler {
+    symbols: HashMap<String, i64>,
+    // the section of each label
+    label_sections: HashMap<String, usize>,
+    // operands pinned to their widest form, by statement
+    wide: HashMap<usize, Vec<bool>>,
+    pin: bool,
+    org: Option<i64>,
+    sections: Vec<Section>,
+    cur: usize,
+    final_pass: bool,
+    // first unknown symbol met by an evaluation
+    missing: RefCell<Option<String>>,
+    repeat: Option<Repeat>,
+    spans: Vec<Span>,
+    labels: Vec<(String, i64, usize, usize)>,
+}
+
+impl Scope for Assembler {
+    fn symbol(&self, name: &str) -> Option<i64> {
+        let mut v = self.symbols.get(name).copied();
+        // nasm's section.<name>.start and section.<name>.vstart
+        if let Some(rest) = name.strip_prefix("section.") {
+            let org = self.org.unwrap_or(0);
+            v = v.or_else(|| {
+                let (s, f) = rest.rsplit_once('.')?;
+                let s = self.sections.iter().find(|x| x.name == s)?;
+                match f {
+                    "start" => Some(org + s.pos),
+                    "vstart" => Some(s.base),
+                    _ => None,
+                }
+            });
+        }
+        if v.is_none() {
+            self.missing
+                .borrow_mut()
+                .get_or_insert_with(|| name.to_string());
+        }
+        v
+    }
+
+    fn segment(&self, name: &str) -> std::result::Result<Option<i64>, String> {
+        if let Some(&s) = self.label_sections.get(name) {
+            return Ok(Some(self.sections[s].segment()));
+        }
+        if let Some(s) = self.sections.iter().find(|s| s.name == name) {
+            return Ok(Some(s.segment()));
+        }
+        if self.symbols.contains_key(name) {
+            return Err(format!("`{}` is not a label, it has no segment", name));
+        }
+        self.missing
+            .borrow_mut()
+            .get_or_insert_with(|| name.to_string());
+        Ok(None)
+    }
+
+    fn here(&self) -> i64 {
+        self.start() + self.out_len() as i64
+    }
+
+    fn start(&self) -> i64 {
+        self.sections[self.cur].base
+    }
+}
+
+impl Assembler {
+    fn out(&mut self) -> &mut Vec<u8> {
+        &mut self.sections[self.cur].data
+    }
+
+    fn out_len(&self) -> usize {
+        self.sections[self.cur].data.len()
+    }
+
+    // value of an expression: None if a symbol is unknown before the last
+    // pass, an error in the last one
+    fn eval(&self, e: &Expr) -> std::result::Result<Option<i64>, String> {
+        self.missing.borrow_mut().take();
+        match e.eval(self)? {
+            Some(v) => Ok(Some(v)),
+            None if self.final_pass => {
+                let name = self.missing.borrow_mut().take().unwrap_or_default();
+                Err(format!("symbol `{}` not defined", name))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // value that must be known in the first pass (org, times ...)
+    fn constant(&self, e: &Expr, what: &str) -> std::result::Result<i64, String> {
+        self.eval(e)?
+            .ok_or_else(|| format!("{} must not depend on a forward reference", what))
+    }
+
+    // a repeat count. it can be negative until the layout settles
+    fn count(&self, e: &Expr, what: &str) -> std::result::Result<usize, String> {
+        let n = self.constant(e, what)?;
+        if n < 0 && self.final_pass {
+            return Err(format!("negative {}", what));
+        }
+        Ok(n.max(0) as usize)
+    }
+
+    fn pass(&mut self, lines: &[(Loc, Line)]) -> Result<()> {
+        for s in &mut self.sections {
+            s.data.clear();
+        }
+        self.cur = 0;
+        let mut defined = HashSet::new();
+        for (idx, (loc, line)) in lines.iter().enumerate() {
+            let mut warns = Vec::new();
+            let (sec, addr, start) = (self.cur, self.here(), self.out_len());
+            self.repeat = None;
+            self.line(idx, line, &mut defined, &mut warns)
+                .map_err(|e| AsmError::new(loc, e))?;
+            if self.final_pass {
+                for w in warns {
+                    warning(loc, &w);
+                }
+                let bytes = if sec == self.cur {
+                    self.sections[sec].data[start..].to_vec()
+                } else {
+                    Vec::new()
+                };
+                self.spans.push(Span {
+                    addr,
+                    bytes,
+                    repeat: self.repeat,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn line(
+        &mut self,
+        idx: usize,
+        line: &Line,
+        defined: &mut HashSet<String>,
+        warns: &mut Vec<String>,
+    ) -> std::result::Result<(), String> {
+        if let Some(label) = &line.label {
+            if !defined.insert(label.clone()) {
+                return Err(format!("label `{}` redefined", label));
+            }
+            let value = match &line.stmt {
+                Stmt::Equ(e) => self.eval(e)?,
+                _ => Some(self.here()),
+            };
+            if !matches!(line.stmt, Stmt::Equ(_)) {
+                self.label_sections.insert(label.clone(), self.cur);
+            }
+            match value {
+                // the layout is settled, the labels do not move
+                Some(v) if self.final_pass && !matches!(line.stmt, Stmt::Equ(_)) => {
+                    debug_assert_eq!(self.symbols.get(label), Some(&v), "label `{}` moved", label);
+                    let offset = self.out_len();
+                    self.labels.push((label.clone(), v, self.cur, offset));
+                }
+                Some(v) => {
+                    self.symbols.insert(label.clone(), v);
+                }
+                None => (),
+            }
+        }
+
+        let (sec, len) = (self.cur, self.out_len());
+        self.stmt(idx, &line.stmt, warns)?;
+        let s = &self.sections[sec];
+        if s.nobits && s.data.len() != len && !matches!(self.repeat, Some(Repeat::Res)) {
+            return Err(format!(
+                "only RESB and friends can go in the nobits section `{}`",
+                s.name
+            ));
+        }
+        Ok(())
+    }
+
+    fn stmt(
+        &mut self,
+        idx: usize,
+        stmt: &Stmt,
+        warns: &mut Vec<String>,
+    ) -> std::result::Result<(), String> {
+        match stmt {
+            Stmt::Empty | Stmt::Equ(_) => Ok(()),
+            Stmt::Org(e) => {
+                let org = self.constant(e, "ORG")?;
+                if self.sections.iter().any(|s| !s.data.is_empty()) {
+                    return Err("ORG must come before any code or data".into());
+                }
+                if self.org.is_some_and(|o| o != org) && !self.final_pass {
+                    return Err("program origin redefined".into());
+                }
+                self.org = Some(org);
+                Ok(())
+            }
+            Stmt::Section(name, attrs) => self.section(name, attrs),
+            Stmt::Data(size, items) => {
+                for item in items {
+                    self.data(*size, item, warns)?;
+                }
+                Ok(())
+            }
+            Stmt::Reserve(size, count) => {
+                let n = self.count(count, "reserved size")?;
+                let len = self.out_len() + n * size;
+                self.out().resize(len, 0);
+                self.repeat = Some(Repeat::Res);
+                Ok(())
+            }
+            Stmt::Times(count, inner) => {
+                let n = self.count(count, "TIMES count")?;
+                for _ in 0..n {
+                    self.stmt(idx, inner, warns)?;
+                }
+                if !matches!(self.repeat, Some(Repeat::Res)) {
+                    self.repeat = Some(Repeat::Times(n as i64));
+                }
+                Ok(())
+            }
+            Stmt::Inst {
+                prefixes,
+                mnemonic,
+                operands,
+            } => self.inst(idx, prefixes, mnemonic.as_deref(), operands, warns),
+        }
+    }
+
+    // switches to the section, its attributes can be given again
+    fn section(&mut self, name: &str, attrs: &[Attr]) -> std::result::Result<(), String> {
+        let idx = match self.sections.iter().position(|s| s.name == name) {
+            Some(i) => i,
+            None => {
+                self.sections.push(Section::new(name));
+                self.sections.len() - 1
+            }
+        };
+        for a in attrs {
+            match a {
+                Attr::Align(e) => {
+                    let n = self.constant(e, "the section alignment")?;
+                    if n <= 0 || n & (n - 1) != 0 {
+                        return Err("the section alignment must be a power of two".into());
+                    }
+                    self.sections[idx].align = n;
+                }
+                Attr::Start(e) => self.sections[idx].start = Some(self.constant(e, "START")?),
+                Attr::Vstart(e) => self.sections[idx].vstart = Some(self.constant(e, "VSTART")?),
+                Attr::Nobits(b) => self.sections[idx].nobits = *b,
+            }
+        }
+        self.cur = idx;
+        Ok(())
+    }
+
+    // places the sections one after the other, the nobits ones last. the
+    // next pass uses these positions
+    fn layout(&mut self) -> std::result::Result<(), String> {
+        let org = self.org.unwrap_or(0);
+        let mut order: Vec<usize> = (0..self.sections.len()).collect();
+        order.sort_by_key(|&i| self.sections[i].nobits);
+
+        let mut pos = 0;
+        for i in order {
+            let s = &mut self.sections[i];
+            let p = match s.start {
+                Some(start) => start - org,
+                None => (pos + s.align - 1) & !(s.align - 1),
+            };
+            if p < pos && self.final_pass {
+                return Err(format!("section `{}` overlaps the previous one", s.name));
+            }
+            s.pos = p;
+            s.base = s.vstart.unwrap_or(org + p);
+            pos = p + s.data.len() as i64;
+        }
+        Ok(())
+    }
+
+    fn data(
+        &mut self,
+        size: usize,
+        item: &Data,
+        warns: &mut Vec<String>,
+    ) -> std::result::Result<(), String> {
+        match item {
+            // strings are padded with zeros to a multiple of the item size
+            Data::Str(s) => {
+                let pad = (size - s.len() % size) % size;
+                let out = self.out();
+                out.extend_from_slice(s);
+                out.resize(out.len() + pad, 0);
+            }
+            Data::Expr(e) => {
+                let v = self.eval(e)?.unwrap_or(0);
+                if size < 8 {
+                    let bits = size * 8;
+                    if v < -(1 << (bits - 1)) || v >= 1 << bits {
+                        warns.push(format!("{} bit data exceeds bounds", bits));
+                    }
+                }
+                self.out().extend_from_slice(&v.to_le_bytes()[..size]);
+            }
+        }
+        Ok(())
+    }
+
+    fn inst(
+        &mut self,
+        idx: usize,
+        prefixes: &[Prefix],
+        mnemonic: Option<&str>,
+        operands: &[parse::Operand],
+        warns: &mut Vec<String>,
+    ) -> std::result::Result<(), String> {
+        let mut seg = None;
+        let mut lock = false;
+        let mut rep = None;
+        for p in prefixes {
+            match *p {
+                Prefix::Seg(s) if seg.replace(s).is_some() => {
+                    return Err("too many segment prefixes".into())
+                }
+                Prefix::Seg(_) => (),
+                Prefix::Lock => lock = true,
+                Prefix::Rep(r) if rep.replace(r).is_some() => {
+                    return Err("too many repeat prefixes".into())
+                }
+                Prefix::Rep(_) => (),
+            }
+        }
+
+        let Some(mnemonic) = mnemonic else {
+            // prefixes alone
+            if let Some(s) = seg {
+                self.out().push(0x26 | (s as u8) << 3);
+            }
+            if lock {
+                self.out().push(0xf0);
+            }
+            match rep {
+                Some(Rep::Rep) => self.out().push(0xf3),
+                Some(Rep::Repne) => self.out().push(0xf2),
+                None => (),
+            }
+            return Ok(());
+        };
+
+        // placeholder for unknown values: jumps to it are always in range
+        let here = self.here();
+        let mut relative = vec![false; operands.len()];
+        let mut opnds = Vec::with_capacity(operands.len());
+        for (k, o) in operands.iter().enumerate() {
+            let mut value = |e: &Expr| -> std::result::Result<i64, String> {
+                relative[k] |= e.is_relative();
+                Ok(self.eval(e)?.unwrap_or(here))
+            };
+            let val = match &o.kind {
+                OperandKind::Reg8(r) => Val::Reg8(*r),
+                OperandKind::Reg16(r) => Val::Reg16(*r),
+                OperandKind::Sreg(s) => Val::Sreg(*s),
+                OperandKind::Imm(e) => Val::Imm(value(e)?),
+                OperandKind::Far(s, e) => Val::Far(value(s)?, value(e)?),
+                OperandKind::Mem {
+                    seg: s,
+                    base,
+                    index,
+                    disp,
+                    disp_size,
+                } => {
+                    if let Some(s) = s {
+                        if seg.replace(*s).is_some() {
+                            return Err("too many segment prefixes".into());
+                        }
+                    }
+                    let disp = match disp {
+                        Some(e) => Some(value(e)?),
+                        None => None,
+                    };
+                    Val::Mem {
+                        base: *base,
+                        index: *index,
+                        disp,
+                        disp_size: *disp_size,
+                    }
+                }
+            };
+            // strict word immediates keep their 16 bits
+            let wide = o.strict && matches!(val, Val::Imm(_)) && o.size != Some(Width::Byte);
+            opnds.push(Opnd {
+                val,
+                size: o.size,
+                jump: o.jump,
+                wide,
+            });
+        }
+
+        // once pinned, an operand keeps its widest form
+        let wide = self
+            .wide
+            .entry(idx)
+            .or_insert_with(|| vec![false; operands.len()]);
+        if self.pin && !self.final_pass {
+            for (w, r) in wide.iter_mut().zip(&relative) {
+                *w |= *r;
+            }
+        }
+        for (o, w) in opnds.iter_mut().zip(wide.iter()) {
+            o.wide |= *w;
+        }
+
+        // the builder picks the forms, the encoder must keep them
+        let start = here + seg.is_some() as i64 + lock as i64 + rep.is_some() as i64;
+        let mut inst = insn::build(mnemonic, &opnds, start, self.final_pass, warns)?;
+        inst.seg = seg;
+        inst.lock = lock;
+        inst.rep = rep;
+        let bytes = Encoder::new(EncMode::Fixed)
+            .encode(&inst)
+            .map_err(|e| e.to_string())?;
+        self.out().extend_from_slice(&bytes);
+        Ok(())
+    }
+
+    // where the labels are, to know when they stop moving
+    fn state(&self) -> (HashMap<String, i64>, Vec<(i64, i64)>) {
+        let layout = self.sections.iter().map(|s| (s.pos, s.base)).collect();
+        (self.symbols.clone(), layout)
+    }
+
+    // the sections, in the binary. the nobits ones are not written
+    fn image(&self) -> Vec<u8> {
+        let mut bin = Vec::new();
+        for s in self.sections.iter().filter(|s| !s.nobits) {
+            if !s.data.is_empty() {
+                bin.resize(s.pos as usize, 0);
+                bin.extend_from_slice(&s.data);
+            }
+        }
+        bin
+    }
+}
+
+// assembles the preprocessed lines to a flat binary
+pub fn assemble(lines: &[pp::Line]) -> Result<Program> {
+    let mut global = String::new();
+    let mut parsed = Vec::with_capacity(lines.len());
+    for l in lines {
+        let line = parse::parse_line(&l.toks, &mut global).map_err(|e| AsmError::new(&l.loc, e))?;
+        parsed.push((l.loc.clone(), line));
+    }
+
+    let mut asm = Assembler {
+        symbols: HashMap::new(),
+        label_sections: HashMap::new(),
+        wide: HashMap::new(),
+        pin: false,
+        org: None,
+        sections: vec![Section::new(".text")],
+        cur: 0,
+        final_pass: false,
+        missing: RefCell::new(None),
+        repeat: None,
+        spans: Vec::new(),
+        labels: Vec::new(),
+    };
+
+    let mut state = None;
+    for n in 0.. {
+        if n == PASSES + PINNED_PASSES {
+            return Err(AsmError::bare("the labels do not settle, too many passes"));
+        }
+        asm.pin = n >= PASSES;
+        asm.pass(&parsed)?;
+        asm.layout().map_err(AsmError::bare)?;
+        let s = asm.state();
+        if state.as_ref() == Some(&s) {
+            break;
+        }
+        state = Some(s);
+    }
+
+    asm.final_pass = true;
+    asm.pass(&parsed)?;
+    asm.layout().map_err(AsmError::bare)?;
+    let labels = asm
+        .labels
+        .iter()
+        .map(|(name, v, s, off)| (name.clone(), *v, asm.sections[*s].pos as usize + off))
+        .collect();
+    Ok(Program {
+        bin: asm.image(),
+        spans: asm.spans,
+        labels,
+    })
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::rc::Rc;
+
+// where a line comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loc {
+    pub file: Rc<str>,
+    pub line: usize,
+    // the %include or the macro call that brought the line
+    pub parent: Option<Rc<Origin>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Via {
+    Include,
+    Macro(Rc<str>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub via: Via,
+    pub loc: Loc,
+}
+
+impl Loc {
+    // the line a macro expansion comes from, where __FILE__ and __LINE__
+    // point to
+    pub fn caller(&self) -> &Loc {
+        match &self.parent {
+            Some(o) if matches!(o.via, Via::Macro(_)) => o.loc.caller(),
+            _ => self,
+        }
+    }
+
+    // the include and macro call chain, one line each
+    fn fmt_chain(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut loc = self;
+        while let Some(o) = &loc.parent {
+            match &o.via {
+                Via::Include => write!(f, "\n{}: note: in file included from here", o.loc)?,
+                Via::Macro(name) => {
+                    write!(f, "\n{}: note: in expansion of macro `{}`", o.loc, name)?
+                }
+            }
+            loc = &o.loc;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Loc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+// a diagnostic for a line, followed by where the line comes from
+struct Diag<'a>(&'a Loc, &'a str, &'a str);
+
+impl fmt::Display for Diag<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.0, self.1, self.2)?;
+        self.0.fmt_chain(f)
+    }
+}
+
+#[derive(Debug)]
+pub struct AsmError {
+    pub loc: Option<Loc>,
+    pub msg: String,
+}
+
+impl AsmError {
+    pub fn new(loc: &Loc, msg: impl Into<String>) -> AsmError {
+        AsmError {
+            loc: Some(loc.clone()),
+            msg: msg.into(),
+        }
+    }
+
+    // errors that are not about a line (command line, files)
+    pub fn bare(msg: impl Into<String>) -> AsmError {
+        AsmError {
+            loc: None,
+            msg: msg.into(),
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.loc {
+            Some(loc) => write!(f, "{}", Diag(loc, "error", &self.msg)),
+            None => write!(f, "error: {}", self.msg),
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+pub type Result<T> = std::result::Result<T, AsmError>;
+
+pub fn warning(loc: &Loc, msg: &str) {
+    eprintln!("{}", Diag(loc, "warning", msg));
+}
@@ -0,0 +1,240 @@
+// constant expressions: numbers, character constants, symbols, $, $$,
+// SEG, WRT and the nasm operators
+
+use crate::lex::{Cursor, Tok};
+use crate::parse::is_register;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Or,
+    Xor,
+    And,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,  // unsigned
+    SDiv, // signed, //
+    Mod,  // unsigned
+    SMod, // signed, %%
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnOp {
+    Neg,
+    Not,  // ~
+    LNot, // !
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(i64),
+    Sym(String),
+    Here,  // $
+    Start, // $$, the start of the section
+    Seg(String),
+    Un(UnOp, Box<Expr>),
+    Bin(BinOp, Box<Expr>, Box<Expr>),
+    Wrt(Box<Expr>, String), // the offset from another segment
+}
+
+// where expressions find the values of symbols and of $
+pub trait Scope {
+    // None if the symbol is not known (yet)
+    fn symbol(&self, name: &str) -> Option<i64>;
+    // the segment of a label or of a section, None if not known (yet)
+    fn segment(&self, name: &str) -> Result<Option<i64>, String>;
+    fn here(&self) -> i64;
+    fn start(&self) -> i64;
+}
+
+impl Expr {
+    // None when a symbol is not known yet
+    pub fn eval(&self, scope: &dyn Scope) -> Result<Option<i64>, String> {
+        Ok(match self {
+            Expr::Num(n) => Some(*n),
+            Expr::Sym(name) => scope.symbol(name),
+            Expr::Here => Some(scope.here()),
+            Expr::Start => Some(scope.start()),
+            Expr::Seg(name) => scope.segment(name)?,
+            Expr::Un(op, e) => e.eval(scope)?.map(|v| match op {
+                UnOp::Neg => v.wrapping_neg(),
+                UnOp::Not => !v,
+                UnOp::LNot => (v == 0) as i64,
+            }),
+            Expr::Bin(op, l, r) => {
+                let (Some(l), Some(r)) = (l.eval(scope)?, r.eval(scope)?) else {
+                    return Ok(None);
+                };
+                Some(binary(*op, l, r)?)
+            }
+            Expr::Wrt(e, seg) => {
+                // the address of the symbol of e, from the other segment
+                let sym = e.symbol().ok_or("WRT expects an address")?;
+                let (Some(v), Some(from), Some(to)) =
+                    (e.eval(scope)?, scope.segment(sym)?, scope.segment(seg)?)
+                else {
+                    return Ok(None);
+                };
+                Some(v.wrapping_add((from - to) * 16))
+            }
+        })
+    }
+
+    // the first symbol of the expression
+    fn symbol(&self) -> Option<&str> {
+        match self {
+            Expr::Sym(name) => Some(name),
+            Expr::Un(_, e) | Expr::Wrt(e, _) => e.symbol(),
+            Expr::Bin(_, l, r) => l.symbol().or_else(|| r.symbol()),
+            _ => None,
+        }
+    }
+
+    // false for the expressions whose value does not depend on the layout
+    pub fn is_relative(&self) -> bool {
+        match self {
+            Expr::Num(_) => false,
+            Expr::Un(_, e) => e.is_relative(),
+            Expr::Bin(_, l, r) => l.is_relative() || r.is_relative(),
+            _ => true,
+        }
+    }
+}
+
+fn binary(op: BinOp, l: i64, r: i64) -> Result<i64, String> {
+    if r == 0 && matches!(op, BinOp::Div | BinOp::SDiv | BinOp::Mod | BinOp::SMod) {
+        return Err("division by zero".into());
+    }
+    // like nasm, / and % are unsigned, >> is a logical shift
+    Ok(match op {
+        BinOp::Or => l | r,
+        BinOp::Xor => l ^ r,
+        BinOp::And => l & r,
+        BinOp::Shl => (l as u64).checked_shl(r as u32).unwrap_or(0) as i64,
+        BinOp::Shr => (l as u64).checked_shr(r as u32).unwrap_or(0) as i64,
+        BinOp::Add => l.wrapping_add(r),
+        BinOp::Sub => l.wrapping_sub(r),
+        BinOp::Mul => l.wrapping_mul(r),
+        BinOp::Div => (l as u64 / r as u64) as i64,
+        BinOp::SDiv => l.wrapping_div(r),
+        BinOp::Mod => (l as u64 % r as u64) as i64,
+        BinOp::SMod => l.wrapping_rem(r),
+    })
+}
+
+// local labels (.name) belong to the last global label
+pub fn qualify(name: &str, global: &str) -> String {
+    if name.starts_with('.') && !name.starts_with("..") {
+        format!("{}{}", global, name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn keyword(cur: &Cursor, k: &str) -> bool {
+    cur.ident().is_some_and(|s| s.eq_ignore_ascii_case(k))
+}
+
+// parses an expression, `global` is the label local ones attach to
+pub fn parse(cur: &mut Cursor, global: &str) -> Result<Expr, String> {
+    let e = binary_level(cur, global, 0)?;
+    if !keyword(cur, "WRT") {
+        return Ok(e);
+    }
+    cur.next();
+    // a section or a global label, not qualified
+    let Some(seg) = cur.ident() else {
+        return Err(cur.unexpected("a segment after WRT"));
+    };
+    cur.next();
+    Ok(Expr::Wrt(Box::new(e), seg.to_string()))
+}
+
+// the binary operators, from the lowest precedence
+const LEVELS: &[&[(&str, BinOp)]] = &[
+    &[("|", BinOp::Or)],
+    &[("^", BinOp::Xor)],
+    &[("&", BinOp::And)],
+    &[("<<", BinOp::Shl), (">>", BinOp::Shr)],
+    &[("+", BinOp::Add), ("-", BinOp::Sub)],
+    &[
+        ("*", BinOp::Mul),
+        ("/", BinOp::Div),
+        ("//", BinOp::SDiv),
+        ("%", BinOp::Mod),
+        ("%%", BinOp::SMod),
+    ],
+];
+
+fn binary_level(cur: &mut Cursor, global: &str, level: usize) -> Result<Expr, String> {
+    let Some(ops) = LEVELS.get(level) else {
+        return unary(cur, global);
+    };
+    let mut e = binary_level(cur, global, level + 1)?;
+    loop {
+        let Some((_, op)) = ops.iter().find(|(p, _)| cur.is(p)) else {
+            return Ok(e);
+        };
+        cur.next();
+        let r = binary_level(cur, global, level + 1)?;
+        e = Expr::Bin(*op, Box::new(e), Box::new(r));
+    }
+}
+
+fn unary(cur: &mut Cursor, global: &str) -> Result<Expr, String> {
+    for (p, op) in [("-", UnOp::Neg), ("~", UnOp::Not), ("!", UnOp::LNot)] {
+        if cur.eat(p) {
+            return Ok(Expr::Un(op, Box::new(unary(cur, global)?)));
+        }
+    }
+    if cur.eat("+") {
+        return unary(cur, global);
+    }
+    if keyword(cur, "SEG") {
+        cur.next();
+        return match cur.next() {
+            Some(Tok::Ident(name)) if !is_register(name) => Ok(Expr::Seg(qualify(name, global))),
+            _ => Err("SEG expects a label".into()),
+        };
+    }
+    primary(cur, global)
+}
+
+fn primary(cur: &mut Cursor, global: &str) -> Result<Expr, String> {
+    if cur.eat("(") {
+        let e = parse(cur, global)?;
+        cur.expect(")")?;
+        return Ok(e);
+    }
+    if cur.eat("$") {
+        return Ok(Expr::Here);
+    }
+    if cur.eat("$$") {
+        return Ok(Expr::Start);
+    }
+    match cur.peek() {
+        Some(Tok::Num(n)) => {
+            cur.next();
+            Ok(Expr::Num(*n))
+        }
+        // a character constant, little endian: 'ab' is 0x6261
+        Some(Tok::Str(s)) => {
+            if s.len() > 8 {
+                return Err("character constant too long".into());
+            }
+            cur.next();
+            let v = s.iter().rev().fold(0u64, |v, &b| v << 8 | b as u64);
+            Ok(Expr::Num(v as i64))
+        }
+        Some(Tok::Ident(name)) if is_register(name) => {
+            Err(format!("invalid use of register `{}`", name))
+        }
+        Some(Tok::Ident(name)) => {
+            cur.next();
+            Ok(Expr::Sym(qualify(name, global)))
+        }
+        _ => Err(cur.unexpected("an expression")),
+    }
+}
@@ -0,0 +1,581 @@
+// builds lib8086 instructions from a mnemonic and its evaluated operands
+
+use lib8086::{Arg, Cc, Inst, Mem, Op, Reg16, Reg8, Sreg, Width};
+
+use crate::parse::Jump;
+
+// operand once its expressions are evaluated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Val {
+    Reg8(Reg8),
+    Reg16(Reg16),
+    Sreg(Sreg),
+    Imm(i64),
+    Far(i64, i64),
+    Mem {
+        base: Option<Reg16>,
+        index: Option<Reg16>,
+        disp: Option<i64>,
+        disp_size: Option<Width>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Opnd {
+    pub val: Val,
+    pub size: Option<Width>,
+    pub jump: Option<Jump>,
+    // the widest form is used whatever the value, so that the size of the
+    // instruction does not change from a pass to the next
+    pub wide: bool,
+}
+
+const JCC: &[(&str, Cc)] = &[
+    ("JO", Cc::O),
+    ("JNO", Cc::NO),
+    ("JB", Cc::B),
+    ("JC", Cc::B),
+    ("JNAE", Cc::B),
+    ("JNB", Cc::NB),
+    ("JNC", Cc::NB),
+    ("JAE", Cc::NB),
+    ("JE", Cc::E),
+    ("JZ", Cc::E),
+    ("JNE", Cc::NE),
+    ("JNZ", Cc::NE),
+    ("JBE", Cc::BE),
+    ("JNA", Cc::BE),
+    ("JNBE", Cc::NBE),
+    ("JA", Cc::NBE),
+    ("JS", Cc::S),
+    ("JNS", Cc::NS),
+    ("JP", Cc::P),
+    ("JPE", Cc::P),
+    ("JNP", Cc::NP),
+    ("JPO", Cc::NP),
+    ("JL", Cc::L),
+    ("JNGE", Cc::L),
+    ("JNL", Cc::NL),
+    ("JGE", Cc::NL),
+    ("JLE", Cc::LE),
+    ("JNG", Cc::LE),
+    ("JNLE", Cc::NLE),
+    ("JG", Cc::NLE),
+];
+
+const MNEMONICS: &[&str] = &[
+    "AAA", "AAD", "AAM", "AAS", "ADC", "ADD", "AND", "CALL", "CBW", "CLC", "CLD", "CLI", "CMC",
+    "CMP", "CMPSB", "CMPSW", "CWD", "DAA", "DAS", "DEC", "DIV", "FWAIT", "HLT", "IDIV", "IMUL",
+    "IN", "INC", "INT", "INT3", "INTO", "IRET", "JCXZ", "JMP", "LAHF", "LDS", "LEA", "LES",
+    "LODSB", "LODSW", "LOOP", "LOOPE", "LOOPNE", "LOOPNZ", "LOOPZ", "MOV", "MOVSB", "MOVSW", "MUL",
+    "NEG", "NOP", "NOT", "OR", "OUT", "POP", "POPF", "PUSH", "PUSHF", "RCL", "RCR", "RET", "RETF",
+    "RETN", "ROL", "ROR", "SAHF", "SAL", "SAR", "SBB", "SCASB", "SCASW", "SHL", "SHR", "STC",
+    "STD", "STI", "STOSB", "STOSW", "SUB", "TEST", "WAIT", "XCHG", "XLAT", "XLATB", "XOR",
+];
+
+pub fn is_mnemonic(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    MNEMONICS.contains(&name.as_str()) || JCC.iter().any(|(j, _)| *j == name)
+}
+
+// memory operand in its shortest form, or with a 16 bit displacement when
+// the operand is wide or when word is asked for. byte keeps a zero
+// displacement
+fn mem(
+    base: Option<Reg16>,
+    index: Option<Reg16>,
+    disp: Option<i64>,
+    size: Option<Width>,
+    wide: bool,
+    warns: &mut Vec<String>,
+) -> Mem {
+    if let Some(d) = disp {
+        check_range(d, Width::Word, warns);
+    }
+    let d = disp.unwrap_or(0) as i16;
+    let wide = wide || size == Some(Width::Word);
+    let short = i8::try_from(d).ok().filter(|_| !wide);
+    let no_disp = size != Some(Width::Byte);
+    match (base, index) {
+        (None, _) => Mem::Direct(d as u16),
+        (Some(b), None) => match short {
+            Some(0) if b != Reg16::BP && no_disp => Mem::Reg(b),
+            _ if disp.is_none() && b != Reg16::BP && size.is_none() => Mem::Reg(b),
+            Some(d8) => Mem::RegOff8(b, d8),
+            None => Mem::RegOff16(b, d),
+        },
+        (Some(b), Some(i)) => match short {
+            Some(0) if no_disp => Mem::RegReg(b, i),
+            _ if disp.is_none() && size.is_none() => Mem::RegReg(b, i),
+            Some(d8) => Mem::RegRegOff8(b, i, d8),
+            None => Mem::RegRegOff16(b, i, d),
+        },
+    }
+}
+
+fn check_range(v: i64, w: Width, warns: &mut Vec<String>) {
+    let ok = match w {
+        Width::Byte => (-0x80..=0xff).contains(&v),
+        Width::Word => (-0x8000..=0xffff).contains(&v),
+    };
+    if !ok {
+        let what = if w == Width::Byte { "byte" } else { "word" };
+        warns.push(format!("{} value {:#x} exceeds bounds", what, v));
+    }
+}
+
+// immediate operand of the given width. word immediates that fit in a sign
+// extended byte are given as Imm8, which selects the short alu forms
+fn imm(o: &Opnd, v: i64, w: Width, warns: &mut Vec<String>) -> Arg {
+    check_range(v, w, warns);
+    match w {
+        Width::Byte => Arg::Uimm8(v as u8),
+        Width::Word => match i8::try_from(v as u16 as i16) {
+            Ok(b) if !o.wide => Arg::Imm8(b),
+            _ => Arg::Uimm16(v as u16),
+        },
+    }
+}
+
+fn byte_value(o: &Opnd, what: &str, warns: &mut Vec<String>) -> Result<u8, String> {
+    match o.val {
+        Val::Imm(v) => {
+            check_range(v, Width::Byte, warns);
+            Ok(v as u8)
+        }
+        _ => Err(format!("{} expects an immediate", what)),
+    }
+}
+
+// operand size given by registers or by byte/word specifiers
+fn width(ops: &[Opnd]) -> Result<Option<Width>, String> {
+    let mut w = None;
+    for o in ops {
+        let ow = match o.val {
+            Val::Reg8(_) => Some(Width::Byte),
+            Val::Reg16(_) | Val::Sreg(_) => Some(Width::Word),
+            Val::Mem { .. } => o.size,
+            Val::Imm(_) | Val::Far(..) => None,
+        };
+        if let Some(ow) = ow {
+            if w.is_some_and(|w| w != ow) {
+                return Err("mismatch in operand sizes".into());
+            }
+            w = Some(ow);
+        }
+    }
+    Ok(w)
+}
+
+struct Builder<'a> {
+    ops: &'a [Opnd],
+    start: i64, // address of the opcode, after the prefixes
+    // out of range short jumps are errors. they are not while the labels
+    // can still move
+    strict: bool,
+    warns: &'a mut Vec<String>,
+}
+
+impl Builder<'_> {
+    fn count(&self, n: usize) -> Result<(), String> {
+        if self.ops.len() == n {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected {} operand{}, got {}",
+                n,
+                if n == 1 { "" } else { "s" },
+                self.ops.len()
+            ))
+        }
+    }
+
+    // a register or memory operand, or an immediate of width w
+    fn arg(&mut self, o: &Opnd, w: Option<Width>) -> Result<Arg, String> {
+        Ok(match o.val {
+            Val::Reg8(r) => Arg::Reg8(r),
+            Val::Reg16(r) => Arg::Reg16(r),
+            Val::Sreg(s) => Arg::Sreg(s),
+            Val::Mem {
+                base,
+                index,
+                disp,
+                disp_size,
+            } => Arg::Mem(mem(base, index, disp, disp_size, o.wide, self.warns)),
+            Val::Imm(v) => {
+                let w = w.ok_or("operation size not specified")?;
+                imm(o, v, w, self.warns)
+            }
+            Val::Far(..) => return Err("invalid far address".into()),
+        })
+    }
+
+    fn rm(&mut self, o: &Opnd) -> Result<Arg, String> {
+        match o.val {
+            Val::Imm(_) | Val::Far(..) => Err("invalid combination of operands".into()),
+            _ => self.arg(o, None),
+        }
+    }
+
+    // two operands of the same size
+    fn binary(&mut self, f: fn(Arg, Arg) -> Op) -> Result<(Op, Option<Width>), String> {
+        self.count(2)?;
+        let w = width(self.ops)?;
+        let (a, b) = (self.ops[0], self.ops[1]);
+        if matches!(b.val, Val::Imm(_)) && w.is_none() {
+            return Err("operation size not specified".into());
+        }
+        Ok((f(self.rm(&a)?, self.arg(&b, w)?), w))
+    }
+
+    fn unary(&mut self, f: fn(Arg) -> Op) -> Result<(Op, Option<Width>), String> {
+        self.count(1)?;
+        let w = width(self.ops)?.ok_or("operation size not specified")?;
+        let a = self.ops[0];
+        Ok((f(self.rm(&a)?), Some(w)))
+    }
+
+    fn shift(&mut self, f: fn(Arg, Arg) -> Op) -> Result<(Op, Option<Width>), String> {
+        self.count(2)?;
+        let w = width(&self.ops[..1])?.ok_or("operation size not specified")?;
+        let (a, b) = (self.ops[0], self.ops[1]);
+        let cnt = match b.val {
+            Val::Reg8(Reg8::CL) => Arg::Reg8(Reg8::CL),
+            Val::Imm(1) => Arg::Uimm8(1),
+            _ => return Err("the 8086 only shifts by 1 or by CL".into()),
+        };
+        Ok((f(self.rm(&a)?, cnt), Some(w)))
+    }
+
+    fn target(&self, o: &Opnd) -> Option<i64> {
+        match o.val {
+            Val::Imm(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // 8 bit displacement to t, from the end of a 2 bytes instruction
+    fn short(&self, t: i64) -> Result<i8, String> {
+        let rel = t - (self.start + 2);
+        match i8::try_from(rel) {
+            Ok(r) => Ok(r),
+            Err(_) if !self.strict => Ok(0),
+            Err(_) => Err(format!("short jump out of range ({})", rel)),
+        }
+    }
+
+    fn rel8(&self) -> Result<i8, String> {
+        self.count(1)?;
+        let o = self.ops[0];
+        if o.jump.is_some_and(|j| j != Jump::Short) {
+            return Err("only short jumps are possible".into());
+        }
+        let t = self.target(&o).ok_or("invalid jump target")?;
+        self.short(t)
+    }
+
+    // near relative call or jump, or indirect through a register or memory
+    fn near(&mut self, jmp: bool) -> Result<(Op, Option<Width>), String> {
+        self.count(1)?;
+        let o = self.ops[0];
+        match (o.val, o.jump) {
+            (Val::Far(seg, off), None | Some(Jump::Far)) => {
+                check_range(seg, Width::Word, self.warns);
+                check_range(off, Width::Word, self.warns);
+                let (seg, off) = (Arg::Uimm16(seg as u16), Arg::Uimm16(off as u16));
+                Ok((
+                    if jmp {
+                        Op::JmpFar(seg, off)
+                    } else {
+                        Op::CallFar(seg, off)
+                    },
+                    None,
+                ))
+            }
+            (Val::Mem { .. }, Some(Jump::Far)) => {
+                let a = self.rm(&o)?;
+                Ok((
+                    if jmp {
+                        Op::JmpFarMem(a)
+                    } else {
+                        Op::CallFarMem(a)
+                    },
+                    Some(Width::Word),
+                ))
+            }
+            (Val::Imm(t), jump) if jump != Some(Jump::Far) => {
+                // a short jump is chosen when the target is known to be
+                // close enough, unless near is asked for
+                let rel8 = i8::try_from(t - (self.start + 2)).ok();
+                let rel16 = (t - (self.start + 3)) as i16;
+                match jump {
+                    _ if !jmp => {
+                        if jump == Some(Jump::Short) {
+                            return Err("a call cannot be short".into());
+                        }
+                        Ok((Op::Call(Arg::Imm16(rel16)), None))
+                    }
+                    Some(Jump::Short) => Ok((Op::Jmp(Arg::Imm8(self.short(t)?)), None)),
+                    None if !o.wide && rel8.is_some() => {
+                        Ok((Op::Jmp(Arg::Imm8(rel8.unwrap())), None))
+                    }
+                    _ => Ok((Op::Jmp(Arg::Imm16(rel16)), None)),
+                }
+            }
+            (Val::Reg16(_) | Val::Mem { .. }, None | Some(Jump::Near)) => {
+                let a = self.rm(&o)?;
+                Ok((
+                    if jmp { Op::Jmp(a) } else { Op::Call(a) },
+                    Some(Width::Word),
+                ))
+            }
+            _ => Err("invalid combination of operands".into()),
+        }
+    }
+
+    // in al/ax, port and out port, al/ax
+    fn io(&mut self, is_in: bool) -> Result<(Op, Option<Width>), String> {
+        self.count(2)?;
+        let (acc, port) = if is_in {
+            (self.ops[0], self.ops[1])
+        } else {
+            (self.ops[1], self.ops[0])
+        };
+        let (acc, w) = match acc.val {
+            Val::Reg8(Reg8::AL) => (Arg::Reg8(Reg8::AL), Width::Byte),
+            Val::Reg16(Reg16::AX) => (Arg::Reg16(Reg16::AX), Width::Word),
+            _ => return Err("invalid combination of operands".into()),
+        };
+        let port = match port.val {
+            Val::Reg16(Reg16::DX) => Arg::Reg16(Reg16::DX),
+            Val::Imm(p) => {
+                check_range(p, Width::Byte, self.warns);
+                Arg::Uimm8(p as u8)
+            }
+            _ => return Err("invalid combination of operands".into()),
+        };
+        Ok((
+            if is_in {
+                Op::In(acc, port)
+            } else {
+                Op::Out(port, acc)
+            },
+            Some(w),
+        ))
+    }
+
+    // lea, lds and les: a word register and a memory operand
+    fn load(&mut self, f: fn(Arg, Arg) -> Op) -> Result<(Op, Option<Width>), String> {
+        self.count(2)?;
+        let (a, b) = (self.ops[0], self.ops[1]);
+        match (a.val, b.val) {
+            (Val::Reg16(r), Val::Mem { .. }) => {
+                Ok((f(Arg::Reg16(r), self.rm(&b)?), Some(Width::Word)))
+            }
+            _ => Err("invalid combination of operands".into()),
+        }
+    }
+
+    // optional immediate operand (ret, aam, aad)
+    fn opt_imm(&mut self, what: &str) -> Result<Option<i64>, String> {
+        match self.ops {
+            [] => Ok(None),
+            [o] => match o.val {
+                Val::Imm(v) => Ok(Some(v)),
+                _ => Err(format!("{} expects an immediate", what)),
+            },
+            _ => Err(format!("{} takes at most one operand", what)),
+        }
+    }
+
+    fn none(&self, op: Op) -> Result<(Op, Option<Width>), String> {
+        self.count(0)?;
+        Ok((op, None))
+    }
+
+    fn string(&self, op: Op, w: Width) -> Result<(Op, Option<Width>), String> {
+        self.count(0)?;
+        Ok((op, Some(w)))
+    }
+
+    fn build(&mut self, mnemonic: &str) -> Result<(Op, Option<Width>), String> {
+        if let Some((_, cc)) = JCC.iter().find(|(j, _)| *j == mnemonic) {
+            return Ok((Op::Jcc(*cc, self.rel8()?), None));
+        }
+
+        match mnemonic {
+            "ADD" => self.binary(Op::Add),
+            "OR" => self.binary(Op::Or),
+            "ADC" => self.binary(Op::Adc),
+            "SBB" => self.binary(Op::Sbb),
+            "AND" => self.binary(Op::And),
+            "SUB" => self.binary(Op::Sub),
+            "XOR" => self.binary(Op::Xor),
+            "CMP" => self.binary(Op::Cmp),
+            "TEST" => self.binary(Op::Test),
+            "MOV" => self.binary(Op::Mov),
+            "XCHG" => {
+                let (op, w) = self.binary(Op::Xchg)?;
+                // ax goes first for the 90+r form, like nasm xchg ax, ax
+                // is nop. the encoder expects memory second
+                match op {
+                    Op::Xchg(Arg::Reg16(Reg16::AX), Arg::Reg16(Reg16::AX)) => Ok((Op::Nop, None)),
+                    Op::Xchg(a @ Arg::Mem(_), b) | Op::Xchg(a, b @ Arg::Reg16(Reg16::AX)) => {
+                        Ok((Op::Xchg(b, a), w))
+                    }
+                    op => Ok((op, w)),
+                }
+            }
+
+            "NOT" => self.unary(Op::Not),
+            "NEG" => self.unary(Op::Neg),
+            "MUL" => self.unary(Op::Mul),
+            "IMUL" => self.unary(Op::Imul),
+            "DIV" => self.unary(Op::Div),
+            "IDIV" => self.unary(Op::Idiv),
+            "INC" => self.unary(Op::Inc),
+            "DEC" => self.unary(Op::Dec),
+
+            "ROL" => self.shift(Op::Rol),
+            "ROR" => self.shift(Op::Ror),
+            "RCL" => self.shift(Op::Rcl),
+            "RCR" => self.shift(Op::Rcr),
+            "SHL" | "SAL" => self.shift(Op::Shl),
+            "SHR" => self.shift(Op::Shr),
+            "SAR" => self.shift(Op::Sar),
+
+            "PUSH" | "POP" => {
+                self.count(1)?;
+                let o = self.ops[0];
+                if matches!(o.val, Val::Imm(_) | Val::Far(..) | Val::Reg8(_)) {
+                    return Err("invalid combination of operands".into());
+                }
+                let a = self.rm(&o)?;
+                Ok((
+                    if mnemonic == "PUSH" {
+                        Op::Push(a)
+                    } else {
+                        Op::Pop(a)
+                    },
+                    Some(Width::Word),
+                ))
+            }
+            "PUSHF" => self.none(Op::Pushf),
+            "POPF" => self.none(Op::Popf),
+
+            "AAA" => self.none(Op::Aaa),
+            "AAS" => self.none(Op::Aas),
+            "DAA" => self.none(Op::Daa),
+            "DAS" => self.none(Op::Das),
+            "AAD" | "AAM" => {
+                let base = self.opt_imm(mnemonic)?.unwrap_or(10);
+                check_range(base, Width::Byte, self.warns);
+                Ok((
+                    if mnemonic == "AAD" {
+                        Op::Aad(base as u8)
+                    } else {
+                        Op::Aam(base as u8)
+                    },
+                    None,
+                ))
+            }
+
+            "JCXZ" => Ok((Op::Jcxz(self.rel8()?), None)),
+            "LOOP" => Ok((Op::Loop(self.rel8()?), None)),
+            "LOOPE" | "LOOPZ" => Ok((Op::Loopz(self.rel8()?), None)),
+            "LOOPNE" | "LOOPNZ" => Ok((Op::Loopnz(self.rel8()?), None)),
+
+            "CALL" => self.near(false),
+            "JMP" => self.near(true),
+            "RET" | "RETN" | "RETF" => {
+                let far = mnemonic == "RETF";
+                Ok(match self.opt_imm(mnemonic)? {
+                    None if far => (Op::Retf, None),
+                    None => (Op::Ret, None),
+                    Some(n) => {
+                        check_range(n, Width::Word, self.warns);
+                        (
+                            if far {
+                                Op::RetfImm(n as u16)
+                            } else {
+                                Op::RetImm(n as u16)
+                            },
+                            None,
+                        )
+                    }
+                })
+            }
+
+            "INT" => {
+                self.count(1)?;
+                let o = self.ops[0];
+                Ok((Op::Int(byte_value(&o, "INT", self.warns)?), None))
+            }
+            "INT3" => self.none(Op::Int3),
+            "INTO" => self.none(Op::Into),
+            "IRET" => self.none(Op::Iret),
+
+            "LEA" => self.load(Op::Lea),
+            "LDS" => self.load(Op::Lds),
+            "LES" => self.load(Op::Les),
+            "XLAT" | "XLATB" => self.none(Op::Xlat),
+            "SAHF" => self.none(Op::Sahf),
+            "LAHF" => self.none(Op::Lahf),
+
+            "MOVSB" => self.string(Op::Movs, Width::Byte),
+            "MOVSW" => self.string(Op::Movs, Width::Word),
+            "CMPSB" => self.string(Op::Cmps, Width::Byte),
+            "CMPSW" => self.string(Op::Cmps, Width::Word),
+            "STOSB" => self.string(Op::Stos, Width::Byte),
+            "STOSW" => self.string(Op::Stos, Width::Word),
+            "LODSB" => self.string(Op::Lods, Width::Byte),
+            "LODSW" => self.string(Op::Lods, Width::Word),
+            "SCASB" => self.string(Op::Scas, Width::Byte),
+            "SCASW" => self.string(Op::Scas, Width::Word),
+
+            "IN" => self.io(true),
+            "OUT" => self.io(false),
+
+            "CBW" => self.none(Op::Cbw),
+            "CWD" => self.none(Op::Cwd),
+            "WAIT" | "FWAIT" => self.none(Op::Wait),
+
+            "NOP" => self.none(Op::Nop),
+            "HLT" => self.none(Op::Hlt),
+            "CMC" => self.none(Op::Cmc),
+            "CLC" => self.none(Op::Clc),
+            "STC" => self.none(Op::Stc),
+            "CLI" => self.none(Op::Cli),
+            "STI" => self.none(Op::Sti),
+            "CLD" => self.none(Op::Cld),
+            "STD" => self.none(Op::Std),
+
+            _ => Err(format!("unknown instruction `{}`", mnemonic)),
+        }
+    }
+}
+
+// builds the instruction (without its prefixes). `start` is the address of
+// its first byte after the prefixes, relative jumps are computed from it.
+// warnings about truncated values are added to `warns`
+pub fn build(
+    mnemonic: &str,
+    ops: &[Opnd],
+    start: i64,
+    strict: bool,
+    warns: &mut Vec<String>,
+) -> Result<Inst, String> {
+    let mnemonic = mnemonic.to_ascii_uppercase();
+    let mut b = Builder {
+        ops,
+        start,
+        strict,
+        warns,
+    };
+    let (op, width) = b.build(&mnemonic)?;
+    Ok(Inst {
+        op,
+        width: width.unwrap_or(Width::Byte),
+        ..Default::default()
+    })
+}
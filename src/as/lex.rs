@@ -0,0 +1,255 @@
+// splits a source line into tokens. comments (from ';') are dropped
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tok {
+    Ident(String),
+    Num(i64),
+    Str(Vec<u8>),
+    Punct(&'static str),
+}
+
+impl std::fmt::Display for Tok {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tok::Ident(s) => write!(f, "{}", s),
+            Tok::Num(n) => write!(f, "{}", n),
+            Tok::Str(s) => write!(f, "\"{}\"", String::from_utf8_lossy(s)),
+            Tok::Punct(p) => write!(f, "{}", p),
+        }
+    }
+}
+
+// longest first, so that "<<" is not read as two "<"
+const PUNCTS: &[&str] = &[
+    "<<", ">>", "//", "%%", "$$", "+", "-", "*", "/", "%", "(", ")", "[", "]", ",", ":", "$", "|",
+    "&", "^", "~", "!", "=",
+];
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '_' | '.' | '?' | '@')
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '?' | '@' | '$' | '#' | '~')
+}
+
+// nasm numbers: 0x1f, 1fh, 0b101, 101b, 17o, 17q, 0q17, 12d ...
+fn number(s: &str) -> Result<i64, String> {
+    let t: String = s
+        .chars()
+        .filter(|&c| c != '_')
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let (digits, radix) = if let Some(d) = t.strip_prefix("0x") {
+        (d, 16)
+    } else if let Some(d) = t.strip_suffix('h') {
+        (d, 16)
+    } else if let Some(d) = t
+        .strip_prefix("0b")
+        .or(t.strip_prefix("0y"))
+        .filter(|d| !d.is_empty())
+    {
+        (d, 2)
+    } else if let Some(d) = t
+        .strip_prefix("0o")
+        .or(t.strip_prefix("0q"))
+        .filter(|d| !d.is_empty())
+    {
+        (d, 8)
+    } else if let Some(d) = t
+        .strip_prefix("0d")
+        .or(t.strip_prefix("0t"))
+        .filter(|d| !d.is_empty())
+    {
+        (d, 10)
+    } else if let Some(d) = t.strip_suffix('b').or(t.strip_suffix('y')) {
+        (d, 2)
+    } else if let Some(d) = t.strip_suffix('o').or(t.strip_suffix('q')) {
+        (d, 8)
+    } else if let Some(d) = t.strip_suffix('d').or(t.strip_suffix('t')) {
+        (d, 10)
+    } else {
+        (t.as_str(), 10)
+    };
+    u64::from_str_radix(digits, radix)
+        .map(|v| v as i64)
+        .map_err(|_| format!("invalid number `{}`", s))
+}
+
+// backquoted strings accept c-like escapes
+fn unescape(s: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut it = s.chars().peekable();
+    while let Some(c) = it.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        let b = match it.next() {
+            Some('n') => b'\n',
+            Some('r') => b'\r',
+            Some('t') => b'\t',
+            Some('0') => 0,
+            Some('e') => 0x1b,
+            Some('\\') => b'\\',
+            Some('`') => b'`',
+            Some('\'') => b'\'',
+            Some('"') => b'"',
+            Some('x') => {
+                let mut v = 0u8;
+                for _ in 0..2 {
+                    match it.peek().and_then(|c| c.to_digit(16)) {
+                        Some(d) => {
+                            v = v << 4 | d as u8;
+                            it.next();
+                        }
+                        None => break,
+                    }
+                }
+                v
+            }
+            Some(c) => return Err(format!("unknown escape `\\{}`", c)),
+            None => return Err("unterminated escape".into()),
+        };
+        out.push(b);
+    }
+    Ok(out)
+}
+
+pub fn tokenize(line: &str) -> Result<Vec<Tok>, String> {
+    let mut toks = Vec::new();
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == ';' {
+            break;
+        }
+
+        let start = i;
+        if c == '"' || c == '\'' || c == '`' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                // a backslash protects the quote in backquoted strings
+                i += if c == '`' && chars[i] == '\\' { 2 } else { 1 };
+            }
+            if i >= chars.len() {
+                return Err("unterminated string".into());
+            }
+            let s: String = chars[start + 1..i].iter().collect();
+            i += 1;
+            toks.push(Tok::Str(if c == '`' {
+                unescape(&s)?
+            } else {
+                s.into_bytes()
+            }));
+        } else if c.is_ascii_digit()
+            || (c == '$' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            // $ followed by a digit is an hexadecimal number
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let s: String = chars[start..i].iter().collect();
+            toks.push(Tok::Num(match s.strip_prefix('$') {
+                Some(hex) => number(&format!("0x{}", hex))?,
+                None => number(&s)?,
+            }));
+        } else if is_ident_start(c)
+            || (c == '$' && chars.get(i + 1).is_some_and(|&c| is_ident_start(c)))
+        {
+            // $name is the identifier name, even if name is a keyword
+            i += 1;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            let s: String = chars[start..i].iter().collect();
+            toks.push(Tok::Ident(s.trim_start_matches('$').to_string()));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let Some(p) = PUNCTS.iter().find(|p| rest.starts_with(*p)) else {
+                return Err(format!("unexpected character `{}`", c));
+            };
+            i += p.len();
+            toks.push(Tok::Punct(p));
+        }
+    }
+    Ok(toks)
+}
+
+// walks through the tokens of a line
+pub struct Cursor<'a> {
+    toks: &'a [Tok],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(toks: &'a [Tok]) -> Cursor<'a> {
+        Cursor { toks, pos: 0 }
+    }
+
+    pub fn peek(&self) -> Option<&'a Tok> {
+        self.toks.get(self.pos)
+    }
+
+    pub fn peek_at(&self, n: usize) -> Option<&'a Tok> {
+        self.toks.get(self.pos + n)
+    }
+
+    pub fn next(&mut self) -> Option<&'a Tok> {
+        let t = self.toks.get(self.pos);
+        if t.is_some() {
+            self.pos += 1;
+        }
+        t
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.pos >= self.toks.len()
+    }
+
+    pub fn rest(&self) -> &'a [Tok] {
+        &self.toks[self.pos..]
+    }
+
+    pub fn is(&self, p: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Punct(q)) if *q == p)
+    }
+
+    pub fn eat(&mut self, p: &str) -> bool {
+        let ok = self.is(p);
+        if ok {
+            self.pos += 1;
+        }
+        ok
+    }
+
+    pub fn expect(&mut self, p: &str) -> Result<(), String> {
+        if self.eat(p) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", p)))
+        }
+    }
+
+    // identifier at the cursor, if any
+    pub fn ident(&self) -> Option<&'a str> {
+        match self.peek() {
+            Some(Tok::Ident(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn unexpected(&self, what: &str) -> String {
+        match self.peek() {
+            Some(t) => format!("expected {}, found `{}`", what, t),
+            None => format!("expected {}, found end of line", what),
+        }
+    }
+}
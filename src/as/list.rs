@@ -0,0 +1,88 @@
+// the listing (-l) and the symbol map (-m) of a program
+
+use std::fmt::Write;
+
+use crate::asm::{Program, Repeat};
+use crate::pp;
+
+// bytes shown on a listing row
+const ROW: usize = 9;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+// like nasm -l: the line number, the address and the bytes of each line,
+// then the source line, marked with its include or macro depth
+pub fn listing(lines: &[pp::Line], prog: &Program) -> String {
+    let mut out = String::new();
+    for (n, (line, span)) in lines.iter().zip(&prog.spans).enumerate() {
+        let mut depth = 0;
+        let mut loc = &line.loc;
+        while let Some(o) = &loc.parent {
+            depth += 1;
+            loc = &o.loc;
+        }
+        let marker = match depth {
+            0 => String::new(),
+            d => format!("<{}>", d),
+        };
+
+        let bytes = &span.bytes;
+        let addr = if bytes.is_empty() {
+            String::new()
+        } else {
+            format!("{:08X}", span.addr)
+        };
+        let mut rows = match span.repeat {
+            Some(Repeat::Times(t)) if t > 1 && !bytes.is_empty() => {
+                let item = &bytes[..bytes.len() / t as usize];
+                vec![format!(
+                    "{}<rep {:X}h>",
+                    hex(&item[..item.len().min(ROW)]),
+                    t
+                )]
+            }
+            Some(Repeat::Res) if !bytes.is_empty() => vec![format!("<res {:X}h>", bytes.len())],
+            _ => bytes.chunks(ROW).map(hex).collect(),
+        };
+        if rows.is_empty() {
+            rows.push(String::new());
+        }
+
+        // the long lines continue on the next rows, without the source
+        let last = rows.len() - 1;
+        for (k, row) in rows.iter().enumerate() {
+            let row = if k < last {
+                format!("{}-", row)
+            } else {
+                row.clone()
+            };
+            if k == 0 {
+                let s = format!("{:6} {:8} {:20}{:4}{}", n + 1, addr, row, marker, line.text);
+                writeln!(out, "{}", s.trim_end()).unwrap();
+            } else {
+                let addr = span.addr + (k * ROW) as i64;
+                writeln!(out, "{:6} {:08X} {}", n + 1, addr, row).unwrap();
+            }
+        }
+    }
+    out
+}
+
+// one label per line, as segment:offset. the segments are relative to the
+// paragraph where the binary is loaded
+pub fn map(file: &str, prog: &Program) -> String {
+    let mut out = format!("; symbols of {}\n", file);
+    for (name, value, offset) in &prog.labels {
+        // the labels local to a macro expansion
+        if name.starts_with("..@") {
+            continue;
+        }
+        let d = *offset as i64 - value;
+        let seg = d.div_euclid(16);
+        let off = value + d.rem_euclid(16);
+        writeln!(out, "{:04X}:{:04X} {}", seg & 0xffff, off & 0xffff, name).unwrap();
+    }
+    out
+}
@@ -1,60 +1,147 @@
 use std::env::args;
+use std::path::Path;
+use std::process::exit;
+
+mod asm;
+mod error;
+mod expr;
+mod insn;
+mod lex;
+mod list;
+mod parse;
+mod pp;
+
+use error::AsmError;
+use pp::Preprocessor;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-fn main() -> Result<()> {
-    let mut incls = Vec::new();
+struct Options {
+    incls: Vec<String>,
+    defines: Vec<String>,
+    output: Option<String>,
+    listing: Option<String>,
+    map: Option<String>,
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{}", err);
+        exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let mut opts = Options {
+        incls: Vec::new(),
+        defines: Vec::new(),
+        output: None,
+        listing: None,
+        map: None,
+    };
 
-    let mut opt = None;
+    // options take their value attached (-Idir) or as the next argument
+    let mut opt: Option<String> = None;
     let mut files = Vec::new();
     for arg in args().skip(1) {
+        if let Some(o) = opt.take() {
+            set_option(&mut opts, &o, arg)?;
+            continue;
+        }
+
         match arg.as_str() {
             "-h" | "-?" => {
                 usage();
                 return Ok(());
             }
 
-            "-I" => opt = Some("I"),
-            "-D" => opt = Some("D"),
+            "-I" | "-D" | "-o" | "-f" | "-l" | "-m" => opt = Some(arg),
 
-            _ => {
-                if arg.starts_with('-') {
-                    println!("Unknown option: {}", arg)
-                }
+            _ if arg.len() > 2 && ["-I", "-D", "-o", "-f", "-l", "-m"].contains(&&arg[..2]) => {
+                set_option(&mut opts, &arg[..2], arg[2..].to_string())?;
             }
-        }
 
-        match opt {
-            Some("I") => {
-                opt = None;
-                incls.push(arg);
-                continue;
+            _ if arg.starts_with('-') => {
+                return Err(AsmError::bare(format!("unknown option: {}", arg)).into());
             }
 
-            Some("D") => {
-                opt = None;
-                println!("Defining {}", arg);
-                continue;
-            }
-
-            _ => (),
+            _ => files.push(arg),
         }
+    }
+    if let Some(o) = opt {
+        return Err(AsmError::bare(format!("missing value for {}", o)).into());
+    }
 
-        files.push(arg);
+    if files.is_empty() {
+        usage();
+        return Ok(());
+    }
+    if files.len() > 1 {
+        for (o, set) in [
+            ("-o", &opts.output),
+            ("-l", &opts.listing),
+            ("-m", &opts.map),
+        ] {
+            if set.is_some() {
+                return Err(
+                    AsmError::bare(format!("{} cannot be used with several files", o)).into(),
+                );
+            }
+        }
     }
 
     for file in files {
-        assemble(&file)?;
+        assemble(&file, &opts)?;
     }
 
     Ok(())
 }
 
+fn set_option(opts: &mut Options, opt: &str, value: String) -> Result<()> {
+    match opt {
+        "-I" => opts.incls.push(value),
+        "-D" => opts.defines.push(value),
+        "-o" => opts.output = Some(value),
+        "-l" => opts.listing = Some(value),
+        "-m" => opts.map = Some(value),
+        "-f" if value == "bin" => (),
+        "-f" => return Err(AsmError::bare(format!("unsupported output format: {}", value)).into()),
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
 fn usage() {
-    println!("Usage: as8086 [-h|-?] [-I dir] [-D def] file ...");
+    println!("Usage: as8086 [-h|-?] [-I dir] [-D def[=value]] [-f bin] [-o out.bin] [-l out.lst] [-m out.map] file ...");
+}
+
+fn assemble(file: &str, opts: &Options) -> Result<()> {
+    let mut pp = Preprocessor::new(&opts.incls);
+    for def in &opts.defines {
+        pp.define(def)?;
+    }
+    let lines = pp.run(file)?;
+    let prog = asm::assemble(&lines)?;
+
+    let output = match &opts.output {
+        Some(o) => o.clone(),
+        None => Path::new(file)
+            .with_extension("bin")
+            .to_string_lossy()
+            .to_string(),
+    };
+    write(&output, &prog.bin)?;
+    if let Some(l) = &opts.listing {
+        write(l, list::listing(&lines, &prog).as_bytes())?;
+    }
+    if let Some(m) = &opts.map {
+        write(m, list::map(file, &prog).as_bytes())?;
+    }
+    Ok(())
 }
 
-fn assemble(_file: &str) -> Result<()> {
-    // let mut f = File::open
+fn write(file: &str, data: &[u8]) -> Result<()> {
+    std::fs::write(file, data)
+        .map_err(|e| AsmError::bare(format!("cannot write `{}`: {}", file, e)))?;
     Ok(())
 }
@@ -0,0 +1,480 @@
+// parses the tokens of a preprocessed line into a statement
+
+use lib8086::{Reg16, Reg8, Rep, Sreg, Width};
+
+use crate::expr::{self, qualify, Expr};
+use crate::insn::is_mnemonic;
+use crate::lex::{Cursor, Tok};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Jump {
+    Short,
+    Near,
+    Far,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OperandKind {
+    Reg8(Reg8),
+    Reg16(Reg16),
+    Sreg(Sreg),
+    Imm(Expr),
+    Far(Expr, Expr), // seg:off
+    Mem {
+        seg: Option<Sreg>,
+        base: Option<Reg16>,
+        index: Option<Reg16>,
+        disp: Option<Expr>,
+        disp_size: Option<Width>, // [word bx+1], [byte bx+0]
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operand {
+    pub kind: OperandKind,
+    pub size: Option<Width>, // byte or word
+    pub jump: Option<Jump>,  // short, near or far
+    pub strict: bool,        // the size of the immediate is kept
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prefix {
+    Lock,
+    Rep(Rep),
+    Seg(Sreg),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Data {
+    Expr(Expr),
+    Str(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Empty,
+    // prefixes can be alone on a line
+    Inst {
+        prefixes: Vec<Prefix>,
+        mnemonic: Option<String>,
+        operands: Vec<Operand>,
+    },
+    Data(usize, Vec<Data>), // item size, items
+    Reserve(usize, Expr),   // item size, count
+    Times(Expr, Box<Stmt>),
+    Equ(Expr),
+    Org(Expr),
+    Section(String, Vec<Attr>),
+}
+
+// the attributes of a section
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attr {
+    Align(Expr),
+    Start(Expr),
+    Vstart(Expr),
+    Nobits(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub label: Option<String>,
+    pub stmt: Stmt,
+}
+
+pub fn reg8(name: &str) -> Option<Reg8> {
+    let regs = ["AL", "CL", "DL", "BL", "AH", "CH", "DH", "BH"];
+    let n = regs.iter().position(|r| r.eq_ignore_ascii_case(name))?;
+    Some(Reg8::from(n as u8))
+}
+
+pub fn reg16(name: &str) -> Option<Reg16> {
+    let regs = ["AX", "CX", "DX", "BX", "SP", "BP", "SI", "DI"];
+    let n = regs.iter().position(|r| r.eq_ignore_ascii_case(name))?;
+    Some(Reg16::from(n as u8))
+}
+
+pub fn sreg(name: &str) -> Option<Sreg> {
+    let regs = ["ES", "CS", "SS", "DS"];
+    let n = regs.iter().position(|r| r.eq_ignore_ascii_case(name))?;
+    Some(Sreg::from(n as u8))
+}
+
+pub fn is_register(name: &str) -> bool {
+    reg8(name).is_some() || reg16(name).is_some() || sreg(name).is_some()
+}
+
+fn prefix(name: &str) -> Option<Prefix> {
+    match name.to_ascii_uppercase().as_str() {
+        "LOCK" => Some(Prefix::Lock),
+        "REP" | "REPE" | "REPZ" => Some(Prefix::Rep(Rep::Rep)),
+        "REPNE" | "REPNZ" => Some(Prefix::Rep(Rep::Repne)),
+        _ => sreg(name).map(Prefix::Seg),
+    }
+}
+
+// data directives and their item size
+fn data_size(name: &str) -> Option<usize> {
+    match name.to_ascii_uppercase().as_str() {
+        "DB" => Some(1),
+        "DW" => Some(2),
+        "DD" => Some(4),
+        "DQ" => Some(8),
+        _ => None,
+    }
+}
+
+fn reserve_size(name: &str) -> Option<usize> {
+    match name.to_ascii_uppercase().as_str() {
+        "RESB" => Some(1),
+        "RESW" => Some(2),
+        "RESD" => Some(4),
+        "RESQ" => Some(8),
+        _ => None,
+    }
+}
+
+const DIRECTIVES: &[&str] = &["ORG", "CPU", "BITS", "TIMES", "EQU", "SECTION", "SEGMENT"];
+
+// words that cannot start a label
+fn is_keyword(name: &str) -> bool {
+    DIRECTIVES.iter().any(|d| d.eq_ignore_ascii_case(name))
+        || data_size(name).is_some()
+        || reserve_size(name).is_some()
+        || prefix(name).is_some()
+        || is_mnemonic(name)
+}
+
+// parses a line. `global` is the last non local label: local ones
+// (.name) are attached to it, and it is updated by global labels
+pub fn parse_line(toks: &[Tok], global: &mut String) -> Result<Line, String> {
+    // [bits 16], [org 0] ...: the bracketed form of the directives
+    let toks = match toks {
+        [Tok::Punct("["), Tok::Ident(d), .., Tok::Punct("]")]
+            if DIRECTIVES.iter().any(|k| k.eq_ignore_ascii_case(d)) =>
+        {
+            &toks[1..toks.len() - 1]
+        }
+        _ => toks,
+    };
+
+    let mut cur = Cursor::new(toks);
+    let mut label = None;
+    if let Some(name) = cur.ident() {
+        let colon = matches!(cur.peek_at(1), Some(Tok::Punct(":")));
+        if colon || !is_keyword(name) {
+            cur.next();
+            if colon {
+                cur.next();
+            }
+            let local = name.starts_with('.');
+            let name = qualify(name, global);
+            if !local {
+                *global = name.clone();
+            }
+            label = Some(name);
+        }
+    }
+
+    let stmt = statement(&mut cur, global)?;
+    if !cur.at_end() {
+        return Err(cur.unexpected("end of line"));
+    }
+    if matches!(stmt, Stmt::Equ(_)) && label.is_none() {
+        return Err("EQU without a label".into());
+    }
+    Ok(Line { label, stmt })
+}
+
+fn statement(cur: &mut Cursor, global: &str) -> Result<Stmt, String> {
+    let Some(word) = cur.ident() else {
+        return match cur.peek() {
+            None => Ok(Stmt::Empty),
+            Some(_) => Err(cur.unexpected("an instruction")),
+        };
+    };
+
+    if let Some(size) = data_size(word) {
+        cur.next();
+        let mut items = Vec::new();
+        loop {
+            match cur.peek() {
+                Some(Tok::Str(s)) if matches!(cur.peek_at(1), None | Some(Tok::Punct(","))) => {
+                    cur.next();
+                    items.push(Data::Str(s.clone()));
+                }
+                _ => items.push(Data::Expr(expr::parse(cur, global)?)),
+            }
+            if !cur.eat(",") {
+                return Ok(Stmt::Data(size, items));
+            }
+        }
+    }
+    if let Some(size) = reserve_size(word) {
+        cur.next();
+        return Ok(Stmt::Reserve(size, expr::parse(cur, global)?));
+    }
+
+    match word.to_ascii_uppercase().as_str() {
+        "ORG" => {
+            cur.next();
+            return Ok(Stmt::Org(expr::parse(cur, global)?));
+        }
+        "EQU" => {
+            cur.next();
+            return Ok(Stmt::Equ(expr::parse(cur, global)?));
+        }
+        "TIMES" => {
+            cur.next();
+            let count = expr::parse(cur, global)?;
+            let stmt = statement(cur, global)?;
+            if matches!(
+                stmt,
+                Stmt::Empty | Stmt::Times(..) | Stmt::Equ(_) | Stmt::Org(_) | Stmt::Section(..)
+            ) {
+                return Err("TIMES expects an instruction or data".into());
+            }
+            return Ok(Stmt::Times(count, Box::new(stmt)));
+        }
+        "CPU" => {
+            cur.next();
+            return match cur.next() {
+                Some(Tok::Num(8086)) => Ok(Stmt::Empty),
+                Some(Tok::Ident(cpu)) if cpu.eq_ignore_ascii_case("8086") => Ok(Stmt::Empty),
+                Some(t) => Err(format!("unsupported cpu `{}`, only 8086 is", t)),
+                None => Err("CPU expects a processor name".into()),
+            };
+        }
+        "SECTION" | "SEGMENT" => {
+            cur.next();
+            return section(cur, global);
+        }
+        "BITS" => {
+            cur.next();
+            return match cur.next() {
+                Some(Tok::Num(16)) => Ok(Stmt::Empty),
+                Some(t) => Err(format!("unsupported `BITS {}`, only 16 is", t)),
+                None => Err("BITS expects 16".into()),
+            };
+        }
+        _ => (),
+    }
+
+    let mut prefixes = Vec::new();
+    while let Some(p) = cur.ident().and_then(prefix) {
+        // es:[bx] is an operand, not a prefix
+        if matches!(cur.peek_at(1), Some(Tok::Punct(":"))) {
+            break;
+        }
+        cur.next();
+        prefixes.push(p);
+    }
+
+    let mnemonic = match cur.ident() {
+        Some(m) if is_mnemonic(m) => {
+            cur.next();
+            Some(m.to_ascii_uppercase())
+        }
+        Some(m) => return Err(format!("unknown instruction `{}`", m)),
+        None if prefixes.is_empty() => return Err(cur.unexpected("an instruction")),
+        None => None,
+    };
+
+    let mut operands = Vec::new();
+    if mnemonic.is_some() && !cur.at_end() {
+        loop {
+            operands.push(operand(cur, global)?);
+            if !cur.eat(",") {
+                break;
+            }
+        }
+    }
+    Ok(Stmt::Inst {
+        prefixes,
+        mnemonic,
+        operands,
+    })
+}
+
+// SECTION name [align=n] [start=n] [vstart=n] [progbits|nobits]
+fn section(cur: &mut Cursor, global: &str) -> Result<Stmt, String> {
+    let Some(name) = cur.ident() else {
+        return Err(cur.unexpected("a section name"));
+    };
+    cur.next();
+    let mut attrs = Vec::new();
+    while let Some(a) = cur.ident() {
+        cur.next();
+        let attr = match a.to_ascii_lowercase().as_str() {
+            "progbits" => Attr::Nobits(false),
+            "nobits" => Attr::Nobits(true),
+            k @ ("align" | "start" | "vstart") => {
+                cur.expect("=")?;
+                let e = expr::parse(cur, global)?;
+                match k {
+                    "align" => Attr::Align(e),
+                    "start" => Attr::Start(e),
+                    _ => Attr::Vstart(e),
+                }
+            }
+            _ => return Err(format!("unknown section attribute `{}`", a)),
+        };
+        attrs.push(attr);
+    }
+    Ok(Stmt::Section(name.to_string(), attrs))
+}
+
+fn operand(cur: &mut Cursor, global: &str) -> Result<Operand, String> {
+    let mut size = None;
+    let mut jump = None;
+    let mut strict = false;
+    loop {
+        match cur.ident().map(|s| s.to_ascii_uppercase()).as_deref() {
+            Some("STRICT") => strict = true,
+            Some("BYTE") => size = Some(Width::Byte),
+            Some("WORD") => size = Some(Width::Word),
+            Some("SHORT") => jump = Some(Jump::Short),
+            Some("NEAR") => jump = Some(Jump::Near),
+            Some("FAR") => jump = Some(Jump::Far),
+            Some(s @ ("DWORD" | "QWORD")) => {
+                return Err(format!("{} operands are not supported", s))
+            }
+            _ => break,
+        }
+        cur.next();
+    }
+
+    let kind = match cur.peek() {
+        Some(Tok::Punct("[")) => memory(cur, None, global)?,
+        Some(Tok::Ident(name)) if is_register(name) => {
+            cur.next();
+            if let Some(s) = sreg(name).filter(|_| cur.is(":")) {
+                // es:[bx]
+                cur.next();
+                memory(cur, Some(s), global)?
+            } else if let Some(r) = reg8(name) {
+                OperandKind::Reg8(r)
+            } else if let Some(r) = reg16(name) {
+                OperandKind::Reg16(r)
+            } else {
+                OperandKind::Sreg(sreg(name).unwrap())
+            }
+        }
+        _ => {
+            let e = expr::parse(cur, global)?;
+            if cur.eat(":") {
+                OperandKind::Far(e, expr::parse(cur, global)?)
+            } else {
+                OperandKind::Imm(e)
+            }
+        }
+    };
+    Ok(Operand {
+        kind,
+        size,
+        jump,
+        strict,
+    })
+}
+
+// [seg:base+index+disp], the registers and the displacement terms can come
+// in any order
+fn memory(cur: &mut Cursor, mut seg: Option<Sreg>, global: &str) -> Result<OperandKind, String> {
+    cur.expect("[")?;
+    let start = cur.rest();
+    let mut len = 0;
+    while !cur.is("]") {
+        if cur.next().is_none() {
+            return Err("missing `]`".into());
+        }
+        len += 1;
+    }
+    cur.next();
+
+    // the size of the displacement and the segment, in any order
+    let mut inner = &start[..len];
+    let mut disp_size = None;
+    loop {
+        match inner {
+            [Tok::Ident(s), Tok::Punct(":"), rest @ ..] if sreg(s).is_some() => {
+                if seg.replace(sreg(s).unwrap()).is_some() {
+                    return Err("too many segment overrides".into());
+                }
+                inner = rest;
+            }
+            [Tok::Ident(s), rest @ ..] if s.eq_ignore_ascii_case("BYTE") && disp_size.is_none() => {
+                disp_size = Some(Width::Byte);
+                inner = rest;
+            }
+            [Tok::Ident(s), rest @ ..] if s.eq_ignore_ascii_case("WORD") && disp_size.is_none() => {
+                disp_size = Some(Width::Word);
+                inner = rest;
+            }
+            _ => break,
+        }
+    }
+
+    // split at the + and - outside of parentheses: registers are the terms
+    // made of a single register, added. the other terms are the displacement
+    let mut regs = Vec::new();
+    let mut disp: Vec<Tok> = Vec::new();
+    let mut depth = 0;
+    let mut term_start = 0;
+    for i in 0..=inner.len() {
+        let at_split = match inner.get(i) {
+            None => true,
+            Some(Tok::Punct("(")) => {
+                depth += 1;
+                false
+            }
+            Some(Tok::Punct(")")) => {
+                depth -= 1;
+                false
+            }
+            Some(Tok::Punct("+" | "-")) => depth == 0 && i > term_start,
+            _ => false,
+        };
+        if !at_split {
+            continue;
+        }
+        let term = &inner[term_start..i];
+        let reg = match term {
+            [Tok::Ident(r)] | [Tok::Punct("+"), Tok::Ident(r)] => reg16(r),
+            _ => None,
+        };
+        match reg {
+            Some(r) => regs.push(r),
+            None => disp.extend_from_slice(term),
+        }
+        term_start = i;
+    }
+
+    let (base, index) = match regs[..] {
+        [] => (None, None),
+        [r] if matches!(r, Reg16::BX | Reg16::BP | Reg16::SI | Reg16::DI) => (Some(r), None),
+        [b @ (Reg16::BX | Reg16::BP), i @ (Reg16::SI | Reg16::DI)]
+        | [i @ (Reg16::SI | Reg16::DI), b @ (Reg16::BX | Reg16::BP)] => (Some(b), Some(i)),
+        _ => return Err("invalid effective address".into()),
+    };
+
+    let disp = if disp.is_empty() {
+        None
+    } else {
+        let mut dc = Cursor::new(&disp);
+        let e = expr::parse(&mut dc, global)?;
+        if !dc.at_end() {
+            return Err(dc.unexpected("end of address"));
+        }
+        Some(e)
+    };
+    if base.is_none() && disp.is_none() {
+        return Err("invalid effective address".into());
+    }
+    Ok(OperandKind::Mem {
+        seg,
+        base,
+        index,
+        disp,
+        disp_size,
+    })
+}
@@ -0,0 +1,652 @@
+// preprocessor: reads the sources, follows %include, expands %define
+// symbols and %macro calls, and keeps the lines of the taken %if branches.
+// the result is the list of lines to assemble, as tokens. the directives
+// are kept without tokens, for the listing
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::error::{warning, AsmError, Loc, Origin, Result, Via};
+use crate::expr::{self, Scope};
+use crate::lex::{tokenize, Cursor, Tok};
+
+const MAX_INCLUDE_DEPTH: usize = 32;
+const MAX_MACRO_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub loc: Loc,
+    pub toks: Vec<Tok>,
+    pub text: String, // the source line, after the macro parameters
+}
+
+// a multi-line macro: %macro name min[-max|-*][+] [defaults]
+struct Macro {
+    name: Rc<str>,
+    min: usize,
+    max: Option<usize>, // None: no limit
+    greedy: bool,       // the last parameter takes the remaining commas
+    defaults: Vec<String>,
+    file: Rc<str>,
+    body: Vec<(usize, String)>, // line numbers and texts
+}
+
+// the macro whose body is being read
+struct Defining {
+    mac: Macro,
+    loc: Loc,
+    depth: usize, // nested %macro in the body
+}
+
+// an open %if block
+struct Cond {
+    loc: Loc,
+    outer: bool,  // the enclosing block is kept
+    taken: bool,  // a branch was already kept
+    active: bool, // the current branch is kept
+    in_else: bool,
+}
+
+pub struct Preprocessor {
+    incls: Vec<PathBuf>,
+    defines: HashMap<String, Vec<Tok>>,
+    macros: HashMap<String, Rc<Macro>>,
+    defining: Option<Defining>,
+    conds: Vec<Cond>,
+    lines: Vec<Line>,
+    depth: usize,
+    expansions: usize,
+    unique: usize, // numbers %% labels
+}
+
+fn ident_len(s: &str) -> usize {
+    s.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '?' | '@' | '$')))
+        .unwrap_or(s.len())
+}
+
+// the line without its comment
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '"' || c == '\'' || c == '`' => quote = Some(c),
+            None if c == ';' => return &text[..i],
+            None => (),
+        }
+    }
+    text
+}
+
+// the directive name and the rest of a %line
+fn directive(text: &str) -> Option<(&str, &str)> {
+    let d = text.trim_start().strip_prefix('%')?;
+    let n = ident_len(d);
+    (n > 0).then(|| (&d[..n], &d[n..]))
+}
+
+// splits macro arguments at the commas outside of quotes, brackets and
+// braces. braces group an argument and are removed
+fn split_args(text: &str) -> Vec<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let mut args = Vec::new();
+    let mut cur = String::new();
+    let mut quote = None;
+    let mut depth = 0;
+    for c in text.chars() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                cur.push(c);
+                continue;
+            }
+            None if c == '"' || c == '\'' || c == '`' => quote = Some(c),
+            None => (),
+        }
+        match c {
+            ',' if depth == 0 => {
+                args.push(cur.trim().to_string());
+                cur.clear();
+                continue;
+            }
+            '{' if depth == 0 => {
+                depth += 1;
+                continue;
+            }
+            '}' if depth == 1 => {
+                depth -= 1;
+                continue;
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => (),
+        }
+        cur.push(c);
+    }
+    args.push(cur.trim().to_string());
+    args
+}
+
+// the %if expressions only know numbers
+struct NoSymbols;
+
+impl Scope for NoSymbols {
+    fn symbol(&self, _: &str) -> Option<i64> {
+        None
+    }
+
+    fn segment(&self, _: &str) -> std::result::Result<Option<i64>, String> {
+        Ok(None)
+    }
+
+    fn here(&self) -> i64 {
+        0
+    }
+
+    fn start(&self) -> i64 {
+        0
+    }
+}
+
+impl Preprocessor {
+    pub fn new(incls: &[String]) -> Preprocessor {
+        Preprocessor {
+            incls: incls.iter().map(PathBuf::from).collect(),
+            defines: HashMap::new(),
+            macros: HashMap::new(),
+            defining: None,
+            conds: Vec::new(),
+            lines: Vec::new(),
+            depth: 0,
+            expansions: 0,
+            unique: 0,
+        }
+    }
+
+    // a -D definition: NAME or NAME=value
+    pub fn define(&mut self, def: &str) -> Result<()> {
+        let (name, value) = def.split_once('=').unwrap_or((def, ""));
+        if name.is_empty() || ident_len(name) != name.len() {
+            return Err(AsmError::bare(format!("invalid definition `{}`", def)));
+        }
+        let toks = tokenize(value).map_err(|e| AsmError::bare(format!("-D{}: {}", def, e)))?;
+        self.defines.insert(name.to_string(), toks);
+        Ok(())
+    }
+
+    pub fn run(mut self, file: &str) -> Result<Vec<Line>> {
+        self.file(Path::new(file), None)?;
+        if let Some(d) = &self.defining {
+            return Err(AsmError::new(&d.loc, "%macro without %endmacro"));
+        }
+        if let Some(c) = self.conds.last() {
+            return Err(AsmError::new(&c.loc, "%if without %endif"));
+        }
+        Ok(self.lines)
+    }
+
+    fn file(&mut self, path: &Path, from: Option<&Loc>) -> Result<()> {
+        let src = fs::read_to_string(path).map_err(|e| {
+            let msg = format!("cannot read `{}`: {}", path.display(), e);
+            match from {
+                Some(loc) => AsmError::new(loc, msg),
+                None => AsmError::bare(msg),
+            }
+        })?;
+
+        let name: Rc<str> = Rc::from(path.to_string_lossy().as_ref());
+        let parent = from.map(|loc| {
+            Rc::new(Origin {
+                via: Via::Include,
+                loc: loc.clone(),
+            })
+        });
+        for (n, text) in src.lines().enumerate() {
+            let loc = Loc {
+                file: name.clone(),
+                line: n + 1,
+                parent: parent.clone(),
+            };
+            self.line(loc, text)?;
+        }
+        Ok(())
+    }
+
+    fn active(&self) -> bool {
+        self.conds.last().is_none_or(|c| c.active)
+    }
+
+    fn line(&mut self, loc: Loc, text: &str) -> Result<()> {
+        // the body of a macro is kept as is, up to its %endmacro
+        if let Some(def) = &mut self.defining {
+            match directive(text)
+                .map(|(d, _)| d.to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("macro") => def.depth += 1,
+                Some("endmacro") if def.depth == 0 => {
+                    let mac = self.defining.take().unwrap().mac;
+                    self.macros.insert(mac.name.to_string(), Rc::new(mac));
+                    self.list(loc, text);
+                    return Ok(());
+                }
+                Some("endmacro") => def.depth -= 1,
+                _ => (),
+            }
+            def.mac.body.push((loc.line, text.to_string()));
+            self.list(loc, text);
+            return Ok(());
+        }
+
+        let code = strip_comment(text);
+        if let Some((name, rest)) = directive(code) {
+            let name = name.to_ascii_lowercase();
+            // a %else or an %endif is listed if one of its sides is
+            let active = self.active();
+            if self.conditional(&loc, &name, rest)? {
+                if active || self.active() {
+                    self.list(loc, text);
+                }
+                return Ok(());
+            }
+            if !active {
+                return Ok(());
+            }
+            self.list(loc.clone(), text);
+            return self.directive(&loc, &name, rest);
+        }
+        if !self.active() {
+            return Ok(());
+        }
+
+        let toks = tokenize(code).map_err(|e| AsmError::new(&loc, e))?;
+        if self.macro_call(&loc, text, &toks)? {
+            return Ok(());
+        }
+        let toks = self.expand(&loc, &toks);
+        self.lines.push(Line {
+            loc,
+            toks,
+            text: text.to_string(),
+        });
+        Ok(())
+    }
+
+    // a line that is only listed
+    fn list(&mut self, loc: Loc, text: &str) {
+        self.lines.push(Line {
+            loc,
+            toks: Vec::new(),
+            text: text.to_string(),
+        });
+    }
+
+    // %if and its friends, they are followed even in the skipped blocks.
+    // returns false for the other directives
+    fn conditional(&mut self, loc: &Loc, name: &str, rest: &str) -> Result<bool> {
+        let err = |what: &str| AsmError::new(loc, format!("%{} without %if", what));
+        match name {
+            "else" => {
+                let c = self.conds.last_mut().ok_or_else(|| err(name))?;
+                if c.in_else {
+                    return Err(AsmError::new(loc, "%else after %else"));
+                }
+                c.active = c.outer && !c.taken;
+                c.taken = true;
+                c.in_else = true;
+            }
+            "endif" => {
+                self.conds.pop().ok_or_else(|| err(name))?;
+            }
+            _ => {
+                if let Some(test) = name.strip_prefix("elif") {
+                    let c = self.conds.last().ok_or_else(|| err(name))?;
+                    if c.in_else {
+                        return Err(AsmError::new(loc, format!("%{} after %else", name)));
+                    }
+                    // the test is only evaluated when it can be taken
+                    let keep = c.outer && !c.taken && self.test(loc, test, rest)?;
+                    let c = self.conds.last_mut().unwrap();
+                    c.active = keep;
+                    c.taken |= keep;
+                } else if let Some(test) = name.strip_prefix("if") {
+                    let outer = self.active();
+                    let keep = outer && self.test(loc, test, rest)?;
+                    self.conds.push(Cond {
+                        loc: loc.clone(),
+                        outer,
+                        taken: keep,
+                        active: keep,
+                        in_else: false,
+                    });
+                } else {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    // the condition of %if<test> and %elif<test>, n negates the test
+    fn test(&self, loc: &Loc, test: &str, rest: &str) -> Result<bool> {
+        let err = |msg: String| AsmError::new(loc, msg);
+        let (negate, test) = match test.strip_prefix('n') {
+            Some(t) if !t.is_empty() => (true, t),
+            _ => (false, test),
+        };
+        let toks = tokenize(rest).map_err(err)?;
+        let res = match test {
+            "" => {
+                let toks = self.expand(loc, &toks);
+                let mut cur = Cursor::new(&toks);
+                let e = expr::parse(&mut cur, "").map_err(err)?;
+                if !cur.at_end() {
+                    return Err(err(cur.unexpected("end of line")));
+                }
+                let v = e.eval(&NoSymbols).map_err(err)?;
+                v.ok_or_else(|| err("%if expects a constant expression".into()))? != 0
+            }
+            "def" => match &toks[..] {
+                [Tok::Ident(name)] => self.defines.contains_key(name),
+                _ => return Err(err("%ifdef expects a name".into())),
+            },
+            "macro" => match toks.first() {
+                Some(Tok::Ident(name)) => self.macros.contains_key(name),
+                _ => return Err(err("%ifmacro expects a name".into())),
+            },
+            "idn" | "idni" => {
+                let toks = self.expand(loc, &toks);
+                let mut parts = toks.split(|t| *t == Tok::Punct(","));
+                let (Some(a), Some(b), None) = (parts.next(), parts.next(), parts.next()) else {
+                    return Err(err(format!("%if{} expects two arguments", test)));
+                };
+                let text = |t: &[Tok]| {
+                    let s = t
+                        .iter()
+                        .map(|t| t.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    if test == "idni" {
+                        s.to_ascii_lowercase()
+                    } else {
+                        s
+                    }
+                };
+                text(a) == text(b)
+            }
+            _ => return Err(err(format!("unknown preprocessor directive `%if{}`", test))),
+        };
+        Ok(res != negate)
+    }
+
+    fn directive(&mut self, loc: &Loc, name: &str, rest: &str) -> Result<()> {
+        let err = |msg: String| AsmError::new(loc, msg);
+        match name {
+            "include" => {
+                let toks = tokenize(rest).map_err(err)?;
+                let [Tok::Str(file)] = &toks[..] else {
+                    return Err(err("%include expects a file name".into()));
+                };
+                let file = String::from_utf8_lossy(file).to_string();
+                let path = self
+                    .resolve(&file, loc)
+                    .ok_or_else(|| err(format!("unable to find `{}`", file)))?;
+                if self.depth >= MAX_INCLUDE_DEPTH {
+                    return Err(err("too many nested %include".into()));
+                }
+                self.depth += 1;
+                let r = self.file(&path, Some(loc));
+                self.depth -= 1;
+                r
+            }
+            "define" | "undef" => {
+                let rest = rest.trim_start();
+                let n = ident_len(rest);
+                if n == 0 {
+                    return Err(err(format!("%{} expects a name", name)));
+                }
+                let (sym, body) = rest.split_at(n);
+                if name == "undef" {
+                    self.defines.remove(sym);
+                } else {
+                    let toks = tokenize(body).map_err(err)?;
+                    let toks = self.expand(loc, &toks);
+                    self.defines.insert(sym.to_string(), toks);
+                }
+                Ok(())
+            }
+            "macro" => {
+                let mac = self.macro_header(loc, rest)?;
+                self.defining = Some(Defining {
+                    mac,
+                    loc: loc.clone(),
+                    depth: 0,
+                });
+                Ok(())
+            }
+            "endmacro" => Err(err("%endmacro without %macro".into())),
+            "error" | "warning" => {
+                let msg = rest.trim();
+                let msg = match tokenize(msg).as_deref() {
+                    Ok([Tok::Str(s)]) => String::from_utf8_lossy(s).to_string(),
+                    _ => msg.to_string(),
+                };
+                if name == "error" {
+                    return Err(err(msg));
+                }
+                warning(loc, &msg);
+                Ok(())
+            }
+            _ => Err(err(format!("unknown preprocessor directive `%{}`", name))),
+        }
+    }
+
+    // name min[-max|-*][+] [defaults, ...]
+    fn macro_header(&self, loc: &Loc, rest: &str) -> Result<Macro> {
+        let err = |msg: &str| AsmError::new(loc, msg);
+        let rest = rest.trim_start();
+        let n = ident_len(rest);
+        if n == 0 {
+            return Err(err("%macro expects a name"));
+        }
+        let (name, rest) = rest.split_at(n);
+        let rest = rest.trim_start();
+
+        let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let d = digits(rest);
+        let min = rest[..d]
+            .parse()
+            .map_err(|_| err("%macro expects a number of parameters"))?;
+        let mut rest = &rest[d..];
+        let mut max = Some(min);
+        if let Some(r) = rest.strip_prefix("-*") {
+            max = None;
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix('-') {
+            let d = digits(r);
+            let m = r[..d].parse().map_err(|_| err("invalid parameter range"))?;
+            if m < min {
+                return Err(err("invalid parameter range"));
+            }
+            max = Some(m);
+            rest = &r[d..];
+        }
+        let greedy = rest.starts_with('+');
+        let rest = rest.trim_start_matches('+').trim_start();
+        let rest = rest.strip_prefix(".nolist").unwrap_or(rest);
+
+        Ok(Macro {
+            name: Rc::from(name),
+            min,
+            max,
+            greedy,
+            defaults: split_args(rest),
+            file: loc.file.clone(),
+            body: Vec::new(),
+        })
+    }
+
+    // [label:] name args. returns false if the line is not a macro call
+    fn macro_call(&mut self, loc: &Loc, text: &str, toks: &[Tok]) -> Result<bool> {
+        let (label, name) = match toks {
+            [Tok::Ident(l), Tok::Punct(":"), Tok::Ident(m), ..] => (Some(l), m),
+            [Tok::Ident(m), ..] => (None, m),
+            _ => return Ok(false),
+        };
+        let Some(mac) = self.macros.get(name).cloned() else {
+            return Ok(false);
+        };
+
+        // the call line only keeps the label
+        let toks = match label {
+            Some(l) => self.expand(loc, &[Tok::Ident(l.clone()), Tok::Punct(":")]),
+            None => Vec::new(),
+        };
+        self.lines.push(Line {
+            loc: loc.clone(),
+            toks,
+            text: text.to_string(),
+        });
+        let code = strip_comment(text);
+        let after = match label {
+            Some(_) => code.split_once(':').unwrap().1,
+            None => code,
+        };
+        let after = after.trim_start();
+        let mut args = split_args(&after[name.len()..]);
+
+        let n = args.len();
+        let too_many = mac.max.is_some_and(|max| n > max);
+        if n < mac.min || too_many && !mac.greedy {
+            let msg = format!("macro `{}` exists, but not taking {} parameters", name, n);
+            return Err(AsmError::new(loc, msg));
+        }
+        if too_many {
+            let last = args.split_off(mac.max.unwrap() - 1).join(", ");
+            args.push(last);
+        }
+        // defaults are for the parameters after the mandatory ones
+        let given = args.len();
+        for d in mac.defaults.iter().skip(given - mac.min) {
+            if mac.max.is_some_and(|max| args.len() >= max) {
+                break;
+            }
+            args.push(d.clone());
+        }
+
+        if self.expansions >= MAX_MACRO_DEPTH {
+            return Err(AsmError::new(loc, "macros nested too deeply"));
+        }
+        self.expansions += 1;
+        self.unique += 1;
+        let unique = self.unique;
+        let parent = Rc::new(Origin {
+            via: Via::Macro(mac.name.clone()),
+            loc: loc.clone(),
+        });
+        let depth = self.conds.len();
+        for (line, text) in &mac.body {
+            let text = substitute(text, &args, n, unique);
+            let loc = Loc {
+                file: mac.file.clone(),
+                line: *line,
+                parent: Some(parent.clone()),
+            };
+            self.line(loc, &text)?;
+        }
+        self.expansions -= 1;
+        if self.conds.len() != depth {
+            return Err(AsmError::new(
+                loc,
+                format!("unbalanced %if in macro `{}`", name),
+            ));
+        }
+        Ok(true)
+    }
+
+    // like nasm: the current directory, then the directory of the including
+    // file, then the -I directories
+    fn resolve(&self, file: &str, from: &Loc) -> Option<PathBuf> {
+        let dir = Path::new(&*from.file).parent().map(Path::to_path_buf);
+        std::iter::once(PathBuf::from(file))
+            .chain(dir.map(|d| d.join(file)))
+            .chain(self.incls.iter().map(|i| i.join(file)))
+            .find(|p| p.is_file())
+    }
+
+    // replaces the defined symbols, __FILE__ and __LINE__
+    fn expand(&self, loc: &Loc, toks: &[Tok]) -> Vec<Tok> {
+        let mut out = Vec::with_capacity(toks.len());
+        self.expand_into(loc, toks, &mut Vec::new(), &mut out);
+        out
+    }
+
+    // a symbol is not expanded in its own definition
+    fn expand_into(&self, loc: &Loc, toks: &[Tok], active: &mut Vec<String>, out: &mut Vec<Tok>) {
+        for t in toks {
+            match t {
+                Tok::Ident(name) if name == "__FILE__" => {
+                    out.push(Tok::Str(loc.caller().file.as_bytes().to_vec()));
+                }
+                Tok::Ident(name) if name == "__LINE__" => {
+                    out.push(Tok::Num(loc.caller().line as i64))
+                }
+                Tok::Ident(name) if self.defines.contains_key(name) && !active.contains(name) => {
+                    active.push(name.clone());
+                    self.expand_into(loc, &self.defines[name], active, out);
+                    active.pop();
+                }
+                _ => out.push(t.clone()),
+            }
+        }
+    }
+}
+
+// replaces the macro parameters in a body line: %1.. the arguments, %0
+// their number and %%name a label unique to this expansion
+fn substitute(text: &str, args: &[String], n: usize, unique: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut quote = None;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '"' || c == '\'' || c == '`' => quote = Some(c),
+            None if c == '%' => {
+                if let Some(name) = rest.strip_prefix('%') {
+                    let len = ident_len(name);
+                    if len > 0 {
+                        out.push_str(&format!("..@{}.{}", unique, &name[..len]));
+                        rest = &name[len..];
+                        continue;
+                    }
+                }
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                if len > 0 {
+                    match rest[..len].parse::<usize>() {
+                        Ok(0) => out.push_str(&n.to_string()),
+                        Ok(k) => out.push_str(args.get(k - 1).map_or("", |a| a.as_str())),
+                        Err(_) => (),
+                    }
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+            None => (),
+        }
+        out.push(c);
+    }
+    out
+}
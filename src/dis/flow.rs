@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+use lib8086::{Decoded, MemAddrT, Op, SliceDecoder};
+
+// recursive descent: the code is what the control flow reaches from the
+// entry points, the other bytes are data
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Label {
+    Loc, // jumped to
+    Sub, // called, or an entry point
+}
+
+impl Label {
+    pub fn name(self, addr: MemAddrT) -> String {
+        match self {
+            Label::Loc => format!("loc_{:04X}", addr),
+            Label::Sub => format!("sub_{:04X}", addr),
+        }
+    }
+}
+
+pub struct Flow<'a> {
+    buf: &'a [u8],
+    origin: MemAddrT,
+    code: BTreeMap<usize, Decoded<'a>>, // by offset in the slice
+    labels: BTreeMap<MemAddrT, Label>,
+}
+
+// what the flow finds, in the order of the file
+pub enum Item<'f, 'a> {
+    Label(String),
+    Code(&'f Decoded<'a>),
+    Data(MemAddrT, &'a [u8]),
+}
+
+impl<'a> Flow<'a> {
+    pub fn label(&self, addr: MemAddrT) -> Option<String> {
+        self.labels.get(&addr).map(|l| l.name(addr))
+    }
+
+    // the labels, the instructions and the data, at most `row` bytes by
+    // item. data stops at labels
+    pub fn items(&self, row: usize) -> Vec<Item<'_, 'a>> {
+        let mut items = vec![];
+        let mut off = 0;
+        while off < self.buf.len() {
+            let addr = self.origin + off as MemAddrT;
+            if let Some(label) = self.label(addr) {
+                items.push(Item::Label(label));
+            }
+            if let Some(d) = self.code.get(&off) {
+                items.push(Item::Code(d));
+                off += d.bytes.len();
+                continue;
+            }
+            let mut n = 1;
+            while n < row
+                && off + n < self.buf.len()
+                && !self.code.contains_key(&(off + n))
+                && !self.labels.contains_key(&(addr + n as MemAddrT))
+            {
+                n += 1;
+            }
+            items.push(Item::Data(addr, &self.buf[off..off + n]));
+            off += n;
+        }
+        items
+    }
+}
+
+// the instructions after which the control does not fall through
+fn ends_flow(op: &Op) -> bool {
+    matches!(
+        op,
+        Op::Jmp(_)
+            | Op::JmpFar(..)
+            | Op::JmpFarMem(_)
+            | Op::Ret
+            | Op::RetImm(_)
+            | Op::Retf
+            | Op::RetfImm(_)
+            | Op::Iret
+            | Op::Hlt
+    )
+}
+
+// cs is the code segment, in which the relative branches wrap around
+pub fn explore<'a>(buf: &'a [u8], origin: MemAddrT, cs: u16, entries: &[MemAddrT]) -> Flow<'a> {
+    let mut dec = SliceDecoder::new(buf, origin);
+    let mut code = BTreeMap::new();
+    let mut owned = vec![false; buf.len()]; // the bytes of the instructions
+    let mut refs: BTreeMap<MemAddrT, Label> = entries.iter().map(|&e| (e, Label::Sub)).collect();
+    let mut todo = entries.to_vec();
+
+    while let Some(mut addr) = todo.pop() {
+        loop {
+            // outside of the file, or already explored
+            let off = addr.wrapping_sub(origin) as usize;
+            if off >= buf.len() || owned[off] {
+                break;
+            }
+            dec.seek(off);
+            // an incomplete or invalid instruction is data
+            let Ok(d) = dec.decode() else {
+                break;
+            };
+            if matches!(d.inst.op, Op::Invalid(_)) {
+                break;
+            }
+            // overlaps an instruction decoded from another path
+            let bytes = off..off + d.bytes.len();
+            if owned[bytes.clone()].iter().any(|&o| o) {
+                break;
+            }
+            owned[bytes].fill(true);
+
+            if let Some(t) = d.inst.target(cs, d.addr) {
+                let label = match d.inst.op {
+                    Op::Call(_) | Op::CallFar(..) => Label::Sub,
+                    _ => Label::Loc,
+                };
+                let l = refs.entry(t).or_insert(label);
+                *l = (*l).max(label);
+                todo.push(t);
+            }
+
+            let stop = ends_flow(&d.inst.op);
+            addr = d.addr + d.inst.size as MemAddrT;
+            code.insert(off, d);
+            if stop {
+                break;
+            }
+        }
+    }
+
+    // a label goes on an instruction, or on data, not inside an instruction
+    let labels = refs
+        .into_iter()
+        .filter(|(addr, _)| {
+            let off = addr.wrapping_sub(origin) as usize;
+            off < buf.len() && (code.contains_key(&off) || !owned[off])
+        })
+        .collect();
+    Flow {
+        buf,
+        origin,
+        code,
+        labels,
+    }
+}
@@ -1,42 +1,400 @@
-use std::{env::args, io::Read};
+use std::{env::args, io::Read, str::FromStr};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-use lib8086::Decoder;
+use lib8086::{
+    Arg, DecodeError, Decoded, EncMode, Encoder, FmtOptions, Formatter, MemAddrT, Names, Nasm, Op,
+    Reg16, SliceDecoder, Syntax, Text,
+};
 
-mod dis;
-use dis::inst_to_string;
+mod flow;
+use flow::{explore, Flow, Item};
+
+// the reset vector of the 8086, F000:FFF0
+const RESET: MemAddrT = 0xffff0;
+
+// bytes on a data line, and on a db of the source
+const DATA_ROW: usize = 4;
+const SOURCE_ROW: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Output {
+    Listing, // address, bytes and text
+    Raw,     // the text only
+    Hex,     // address and bytes only
+    Json,    // an object by line
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "listing" => Ok(Output::Listing),
+            "raw" => Ok(Output::Raw),
+            "hex" => Ok(Output::Hex),
+            "json" => Ok(Output::Json),
+            _ => Err(format!("unknown output `{}`", s)),
+        }
+    }
+}
+
+struct Opts {
+    recursive: bool,
+    reassemble: bool,
+    entries: Vec<MemAddrT>,
+    origin: MemAddrT,     // the address of the first byte after the header
+    segment: Option<u16>, // the addresses are shown as segment:offset
+    skip: usize,          // the size of the header
+    start: usize,         // the range to disassemble, after the header
+    end: Option<usize>,
+    output: Output,
+}
+
+impl Opts {
+    // the code segment: the one given with the origin, else the 64K the
+    // origin is in
+    fn cs(&self) -> u16 {
+        self.segment.unwrap_or((self.origin >> 4) as u16 & 0xf000)
+    }
+}
 
 fn main() -> Result<()> {
+    let mut files = vec![];
+    let mut syntax = Syntax::default();
+    let mut fmt_opts = FmtOptions::default();
+    let mut opts = Opts {
+        recursive: false,
+        reassemble: false,
+        entries: vec![],
+        origin: 0,
+        segment: None,
+        skip: 0,
+        start: 0,
+        end: None,
+        output: Output::Listing,
+    };
+
     for arg in args().skip(1) {
-        disasm(&arg)?;
+        if arg == "-h" || arg == "-?" {
+            usage();
+            return Ok(());
+        } else if let Some(s) = arg.strip_prefix("-syntax=") {
+            syntax = s.parse()?;
+        } else if arg == "-uppercase" {
+            fmt_opts.uppercase = true;
+        } else if let Some(hex) = arg.strip_prefix("-hex=") {
+            fmt_opts.hex = Some(hex.parse()?);
+        } else if arg == "-recursive" {
+            opts.recursive = true;
+        } else if arg == "--reassemble" || arg == "-reassemble" {
+            opts.reassemble = true;
+        } else if let Some(addr) = arg.strip_prefix("-entry=") {
+            opts.recursive = true;
+            opts.entries.push(parse_addr(addr)?.0);
+        } else if let Some(addr) = arg.strip_prefix("-origin=") {
+            (opts.origin, opts.segment) = parse_addr(addr)?;
+        } else if let Some(n) = arg.strip_prefix("-skip=") {
+            opts.skip = parse_offset(n)?;
+        } else if let Some(n) = arg.strip_prefix("-start=") {
+            opts.start = parse_offset(n)?;
+        } else if let Some(n) = arg.strip_prefix("-end=") {
+            opts.end = Some(parse_offset(n)?);
+        } else if let Some(s) = arg.strip_prefix("-output=") {
+            opts.output = s.parse()?;
+        } else if arg.starts_with('-') && arg != "-" {
+            return Err(format!("unknown option `{}`", arg).into());
+        } else {
+            files.push(arg);
+        }
+    }
+    if files.is_empty() {
+        files.push("-".to_string());
+    }
+
+    let fmt = syntax.formatter(fmt_opts);
+    for file in files {
+        let (buf, origin) = load(&file, &opts)?;
+        if opts.reassemble {
+            reassemble(&file, &buf, origin, &opts, fmt_opts);
+        } else {
+            disasm(&buf, origin, &opts, fmt.as_ref());
+        }
     }
 
     Ok(())
 }
 
-fn disasm(file: &str) -> Result<()> {
-    let mut f = std::fs::File::open(file)?;
+fn usage() {
+    println!("Usage: dis8086 [-h|-?] [options] [file ...]");
+    println!();
+    println!("Reads the standard input without a file, or with -. The numbers are in hexadecimal.");
+    println!();
+    println!("  -origin=addr       load address, linear or segment:offset (default 0)");
+    println!("  -skip=n            skip a header of n bytes");
+    println!("  -start=n           disassemble from offset n, after the header");
+    println!("  -end=n             disassemble up to offset n, after the header");
+    println!("  -output=fmt        listing (default), raw, hex or json");
+    println!("  -syntax=name       nasm (default), masm, tasm, att or gas");
+    println!("  -uppercase         mnemonics, registers and keywords in uppercase");
+    println!("  -hex=style         numbers as 0x1F or 1Fh: 0x or h");
+    println!("  -recursive         follow the control flow instead of decoding every byte");
+    println!("  -entry=addr        an entry point, linear or segment:offset (implies -recursive)");
+    println!("  --reassemble       nasm source that assembles back to the same bytes");
+    println!("                     (-reassemble is the same)");
+}
+
+fn hex(s: &str, max: usize) -> Option<usize> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    usize::from_str_radix(s, 16).ok().filter(|&v| v <= max)
+}
+
+// a linear address, or segment:offset, in hexadecimal
+fn parse_addr(s: &str) -> Result<(MemAddrT, Option<u16>)> {
+    let err = || format!("invalid address `{}`", s);
+    Ok(match s.split_once(':') {
+        Some((seg, off)) => {
+            let seg = hex(seg, 0xffff).ok_or_else(err)?;
+            let off = hex(off, 0xffff).ok_or_else(err)?;
+            // only 20 address lines
+            (((seg << 4) + off) as MemAddrT & 0xf_ffff, Some(seg as u16))
+        }
+        None => (hex(s, 0xf_ffff).ok_or_else(err)? as MemAddrT, None),
+    })
+}
+
+// an offset in the file, in hexadecimal
+fn parse_offset(s: &str) -> Result<usize> {
+    Ok(hex(s, usize::MAX).ok_or_else(|| format!("invalid offset `{}`", s))?)
+}
+
+fn read(file: &str) -> Result<Vec<u8>> {
     let mut buf = Vec::new();
-    f.read_to_end(&mut buf)?;
-
-    let mut pc = 0;
-    let mut it = buf.iter().cloned();
-    let mut dec = Decoder::new(&mut it);
-    while let Some(inst) = dec.next_i() {
-        let size = inst.size as usize;
-        let npc = pc + size;
-        let bytes = &buf[pc..npc];
-        let bytes = bytes
+    if file == "-" {
+        std::io::stdin().read_to_end(&mut buf)?;
+    } else {
+        std::fs::File::open(file)?.read_to_end(&mut buf)?;
+    }
+    Ok(buf)
+}
+
+// the bytes to disassemble, without the header and out of the range, and
+// the address of the first one
+fn load(file: &str, opts: &Opts) -> Result<(Vec<u8>, MemAddrT)> {
+    let mut buf = read(file)?;
+    if opts.skip > buf.len() {
+        return Err(format!("{}: the header is larger than the file", file).into());
+    }
+    buf.drain(..opts.skip);
+    let end = opts.end.unwrap_or(buf.len());
+    if opts.start > end || end > buf.len() {
+        return Err(format!("{}: invalid range {:X}..{:X}", file, opts.start, end).into());
+    }
+    buf.truncate(end);
+    buf.drain(..opts.start);
+    Ok((buf, opts.origin + opts.start as MemAddrT))
+}
+
+// prints the lines in the output format. in json, the label goes with the
+// next instruction
+struct Printer<'o> {
+    opts: &'o Opts,
+    label: Option<String>,
+}
+
+impl Printer<'_> {
+    fn address(&self, addr: MemAddrT) -> String {
+        match self.opts.segment {
+            Some(seg) if (addr.wrapping_sub((seg as MemAddrT) << 4)) <= 0xffff => {
+                format!("{:04X}:{:04X}", seg, addr - ((seg as MemAddrT) << 4))
+            }
+            _ => format!("{:05X}", addr), // ! 05x -> 1MB max
+        }
+    }
+
+    fn label(&mut self, label: String) {
+        match self.opts.output {
+            Output::Listing | Output::Raw => println!("{}:", label),
+            Output::Hex => {}
+            Output::Json => self.label = Some(label),
+        }
+    }
+
+    fn line(&mut self, addr: MemAddrT, bytes: &[u8], text: &Text) {
+        let hex = bytes
             .iter()
             .map(|b| format!("{:02x}", *b))
             .collect::<Vec<String>>();
-        println!("{:05X} {:16} {}", // ! 05x -> 1MB max
-            pc,
-            bytes.join(" "),
-            inst_to_string(pc as u32, &inst));
-        pc = npc;
+        match self.opts.output {
+            Output::Listing => println!("{} {:16} {}", self.address(addr), hex.join(" "), text),
+            Output::Raw => println!("{}", text),
+            Output::Hex => println!("{} {}", self.address(addr), hex.join(" ")),
+            Output::Json => {
+                let label = match self.label.take() {
+                    Some(label) => format!("\"label\":{},", json(&label)),
+                    None => String::new(),
+                };
+                let operands: Vec<String> = text.operands.iter().map(|o| json(o)).collect();
+                println!(
+                    "{{{}\"address\":{},\"bytes\":\"{}\",\"mnemonic\":{},\"operands\":[{}],\"size\":{}}}",
+                    label,
+                    addr,
+                    hex.concat(),
+                    json(&text.mnemonic),
+                    operands.join(","),
+                    bytes.len()
+                );
+            }
+        }
     }
+}
 
-    Ok(())
-}
\ No newline at end of file
+// a json string
+fn json(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn disasm(buf: &[u8], origin: MemAddrT, opts: &Opts, fmt: &dyn Formatter) {
+    let mut out = Printer { opts, label: None };
+
+    if opts.recursive {
+        let flow = flow(buf, origin, opts);
+        let names = |addr| flow.label(addr);
+        for item in flow.items(DATA_ROW) {
+            match item {
+                Item::Label(label) => out.label(label),
+                Item::Code(d) => out.line(d.addr, d.bytes, &text(fmt, opts.cs(), d, &names)),
+                Item::Data(addr, bytes) => out.line(addr, bytes, &fmt.text_data(bytes)),
+            }
+        }
+        return;
+    }
+
+    for r in SliceDecoder::new(buf, origin) {
+        let d = match r {
+            Ok(d) => d,
+            Err(err) => {
+                // the last instruction is incomplete: dump its bytes as data
+                let n = match err {
+                    DecodeError::Truncated(n) => n,
+                    DecodeError::EndOfInput => 0,
+                };
+                let pc = buf.len() - n;
+                for (i, b) in buf[pc..].iter().enumerate() {
+                    let addr = origin + (pc + i) as MemAddrT;
+                    out.line(addr, &[*b], &fmt.text_data(&[*b]));
+                }
+                break;
+            }
+        };
+        out.line(d.addr, d.bytes, &text(fmt, opts.cs(), &d, &|_| None));
+    }
+}
+
+// the text of an instruction. like ndisasm, the bytes that do not decode
+// are given as data
+fn text(fmt: &dyn Formatter, cs: u16, d: &Decoded, names: &Names) -> Text {
+    match d.inst.op {
+        Op::Invalid(_) => fmt.text_data(d.bytes),
+        _ => fmt.text(cs, d.addr, &d.inst, names),
+    }
+}
+
+// follows the control flow from the entry points given, and from the reset
+// vector when the file holds it, else from the start of the file
+fn flow<'a>(buf: &'a [u8], origin: MemAddrT, opts: &Opts) -> Flow<'a> {
+    let end = origin + buf.len() as MemAddrT;
+    let mut entries = opts.entries.clone();
+    entries.push(if (origin..end).contains(&RESET) {
+        RESET
+    } else {
+        origin
+    });
+    explore(buf, origin, opts.cs(), &entries)
+}
+
+// whether an assembler picks the encoding of d from its text: as8086 uses
+// the fixed mode of the encoder, given pinned operands
+fn reassembles(d: &Decoded, cs: u16) -> bool {
+    let mode = match d.inst.op {
+        // like nasm, the short form of xchg with ax
+        Op::Xchg(Arg::Reg16(Reg16::AX), Arg::Reg16(_))
+        | Op::Xchg(Arg::Reg16(_), Arg::Reg16(Reg16::AX)) => EncMode::Shortest,
+        // no mnemonics for them
+        Op::Esc(..) | Op::Setmo(..) => return false,
+        _ => EncMode::Fixed,
+    };
+    // the relative branches do not wrap around the segment. far ones hold
+    // their target
+    let relative = matches!(
+        d.inst.op,
+        Op::Jcc(..)
+            | Op::Jcxz(_)
+            | Op::Loop(_)
+            | Op::Loopz(_)
+            | Op::Loopnz(_)
+            | Op::Jmp(Arg::Imm8(_) | Arg::Imm16(_))
+            | Op::Call(Arg::Imm16(_))
+    );
+    let base = (cs as MemAddrT) << 4;
+    let wraps = relative
+        && d.inst.target(cs, d.addr).is_some_and(|t| {
+            let rel = t.wrapping_sub(base) as u16 as i64 - d.addr.wrapping_sub(base) as u16 as i64;
+            rel.abs() > 0x8000
+        });
+    !wraps
+        && Encoder::new(mode)
+            .encode(&d.inst)
+            .is_ok_and(|b| b == d.bytes)
+}
+
+// nasm source that assembles back to the same bytes, with as8086 or nasm.
+// the instructions that cannot be written so that an assembler picks their
+// encoding are given as db
+fn reassemble(file: &str, buf: &[u8], origin: MemAddrT, opts: &Opts, fmt_opts: FmtOptions) {
+    let flow = flow(buf, origin, opts);
+    let cs = opts.cs();
+    let base = (cs as MemAddrT) << 4;
+    let fmt = Nasm(FmtOptions {
+        strict: true,
+        ..fmt_opts
+    });
+    // the targets without a label are out of the file, or inside an
+    // instruction: their offsets in cs, from the org
+    let names = |addr: MemAddrT| {
+        let label = flow.label(addr);
+        Some(label.unwrap_or_else(|| format!("0x{:04X}", addr.wrapping_sub(base) as u16)))
+    };
+
+    println!("; {}", file);
+    println!("cpu 8086");
+    println!("bits 16");
+    println!("org 0x{:04X}", origin.wrapping_sub(base) as u16);
+    println!();
+    for item in flow.items(SOURCE_ROW) {
+        match item {
+            Item::Label(label) => println!("{}:", label),
+            Item::Code(d) => {
+                let text = fmt.format_with(cs, d.addr, &d.inst, &names);
+                if reassembles(d, cs) {
+                    println!("        {}", text);
+                } else {
+                    println!("        {} ; {}", fmt.format_data(d.bytes), text);
+                }
+            }
+            Item::Data(_, bytes) => println!("        {}", fmt.format_data(bytes)),
+        }
+    }
+}
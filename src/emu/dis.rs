@@ -1 +0,0 @@
-include!("../dis/dis.rs");
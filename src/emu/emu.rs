@@ -1,9 +1,10 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use tracing::{debug, trace};
 
 use super::{
-    inst_to_string, Config, Cpu, Flags, Inst, Op, OpSize, OpSizeT, Reg16, Reg8, Result, Sreg,
+    Config, Cpu, CpuModel, Flags, FmtOptions, Inst, MemAddrT, Op, OpSize, OpSizeT, Reg16, Reg8, Result, Sreg,
+    SymbolMap, Syntax,
 };
 
 #[derive(Default)]
@@ -12,6 +13,10 @@ pub struct EmuOpts {
     pub wait_for_enter: bool,
     pub dump_regs_each_step: bool,
     pub dump_regs_on_halt: bool,
+    pub load_map: bool, // the symbols of foo.bin are in foo.map
+    pub model: CpuModel,
+    pub syntax: Syntax, // of the trace
+    pub fmt: FmtOptions,
 }
 
 pub fn emulate(file: &str, opts: &EmuOpts) -> Result<()> {
@@ -19,10 +24,17 @@ pub fn emulate(file: &str, opts: &EmuOpts) -> Result<()> {
         bios_file: PathBuf::from(file),
         ram_size: 0xf0000,
         bios_addr: 0xf0000,
+        model: opts.model,
     };
 
     let mut cpu = Cpu::new(&cfg)?;
 
+    let symbols = if opts.load_map {
+        SymbolMap::load(&Path::new(file).with_extension("map"), (cfg.bios_addr >> 4) as u16)?
+    } else {
+        SymbolMap::default()
+    };
+
     // initialize registers
     cpu.write_sreg(Sreg::CS, ((cfg.bios_addr & 0xffff_0000) >> 4) as OpSizeT); // todo
     cpu.write_ip((cfg.bios_addr & 0x0000_ffff) as OpSizeT); // todo
@@ -42,6 +54,8 @@ pub fn emulate(file: &str, opts: &EmuOpts) -> Result<()> {
     cpu.write_reg16(Reg16::BP, 0);
     cpu.write_reg16(Reg16::SP, 0);
 
+    let fmt = opts.syntax.formatter(opts.fmt);
+
     let mut prev_op = Inst::default();
     let mut prev_ip: u32 = 0;
 
@@ -49,7 +63,8 @@ pub fn emulate(file: &str, opts: &EmuOpts) -> Result<()> {
         let mut found_hlt_at = None;
 
         if opts.test_mode && cpu.is_halted() {
-            let ip = cpu.read_ip();
+            // ip is past the hlt
+            let ip = cpu.read_ip().wrapping_sub(1);
             let cs = Sreg::CS;
             let ea = cpu.calc_ea(cs, ip);
             found_hlt_at = Some(ea);
@@ -203,8 +218,29 @@ pub fn emulate(file: &str, opts: &EmuOpts) -> Result<()> {
                         trace!("{}: {} [OK]", name, f);
                         ea += 3;
                     }
+                    "MB" | "MW" => {
+                        // DD address, then DB or DW expected value
+                        let lo = cpu.read_mem_ea(ea + 2, OpSize::Word).unwrap() as MemAddrT;
+                        let hi = cpu.read_mem_ea(ea + 4, OpSize::Word).unwrap() as MemAddrT;
+                        let addr = hi << 16 | lo;
+                        let sz = if name == "MB" { OpSize::Byte } else { OpSize::Word };
+                        let v = cpu.read_mem_ea(addr, sz).unwrap_or(0xffff);
+                        let ex = cpu.read_mem_ea(ea + 6, sz).unwrap();
+                        if v != ex {
+                            return Err(format!(
+                                "{}:{}: {} [0x{:05X}]: got 0x{:04X}, expected 0x{:04X}",
+                                file, line, name, addr, v, ex
+                            )
+                            .into());
+                        }
+                        trace!("{} [{:05X}]: {:04X} [OK]", name, addr, v);
+                        ea += match sz {
+                            OpSize::Byte => 7,
+                            OpSize::Word => 8,
+                        };
+                    }
                     _ => {
-                        if file == "" {
+                        if file.is_empty() {
                             break 'debug_loop;
                         }
                         return Err(format!(
@@ -236,16 +272,19 @@ pub fn emulate(file: &str, opts: &EmuOpts) -> Result<()> {
         }
 
         let (inst, pc, bytes) = cpu.next_inst();
-        let bytes = bytes
+        let bytes = bytes[..inst.size as usize]
             .iter()
             .map(|b| format!("{:02x}", *b))
             .collect::<Vec<String>>();
 
+        for label in symbols.labels_at(pc) {
+            println!("{}:", label);
+        }
         println!(
             "{:06X} {:16} {}",
             pc,
             bytes.join(" "),
-            inst_to_string(pc, &inst)
+            fmt.format(cpu.read_sreg(Sreg::CS), pc, &inst)
         );
 
         prev_ip = pc;
@@ -7,13 +7,13 @@ pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 mod emu;
 use emu::{emulate, EmuOpts};
 
-mod dis;
-pub use dis::inst_to_string;
+mod map;
+pub use map::SymbolMap;
 
 mod x86;
-pub use x86::{Config, Cpu, Flags, OpSize};
+pub use x86::{Config, Cpu, CpuModel, OpSize};
 
-pub use lib8086::{Arg, Cc, Decoder, Inst, Op, OpSizeT, Reg16, Reg8, Rep, Sreg};
+pub use lib8086::{Arg, Cc, Decoder, Flags, FmtOptions, Inst, MemAddrT, Op, OpSizeT, Reg16, Reg8, Rep, Sreg, Syntax};
 
 fn main() -> Result<()> {
     let mut binaries = vec![];
@@ -69,6 +69,36 @@ fn main() -> Result<()> {
                 continue;
             }
 
+            if arg == "-cpu-8086" {
+                opts.model = CpuModel::I8086;
+                continue;
+            }
+
+            if arg == "-cpu-80286" {
+                opts.model = CpuModel::I80286;
+                continue;
+            }
+
+            if arg == "-load-map" {
+                opts.load_map = true;
+                continue;
+            }
+
+            if let Some(syntax) = arg.strip_prefix("-syntax=") {
+                opts.syntax = syntax.parse()?;
+                continue;
+            }
+
+            if arg == "-uppercase" {
+                opts.fmt.uppercase = true;
+                continue;
+            }
+
+            if let Some(hex) = arg.strip_prefix("-hex=") {
+                opts.fmt.hex = Some(hex.parse()?);
+                continue;
+            }
+
             if arg == "-hide-header" {
                 hide_header = true;
                 continue;
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::{MemAddrT, Result};
+
+// the labels of a binary, as written by `as8086 -m`
+#[derive(Default)]
+pub struct SymbolMap {
+    labels: HashMap<MemAddrT, Vec<String>>,
+}
+
+impl SymbolMap {
+    // the segments of the map are relative to `base`, the segment where
+    // the binary is loaded
+    pub fn load(path: &Path, base: u16) -> Result<SymbolMap> {
+        let src = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+
+        let mut map = SymbolMap::default();
+        for (n, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let err = || format!("{}:{}: invalid symbol", path.display(), n + 1);
+            let (addr, name) = line.split_once(' ').ok_or_else(err)?;
+            let (seg, off) = addr.split_once(':').ok_or_else(err)?;
+            let seg = u16::from_str_radix(seg, 16).map_err(|_| err())?;
+            let off = u16::from_str_radix(off, 16).map_err(|_| err())?;
+            // only 20 address lines, as in Cpu::calc_ea
+            let ea = (((base.wrapping_add(seg) as MemAddrT) << 4) + off as MemAddrT) & 0xf_ffff;
+            map.labels.entry(ea).or_default().push(name.trim().to_string());
+        }
+        Ok(map)
+    }
+
+    pub fn labels_at(&self, ea: MemAddrT) -> &[String] {
+        self.labels.get(&ea).map_or(&[], |l| l.as_slice())
+    }
+}
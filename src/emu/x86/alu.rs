@@ -0,0 +1,161 @@
+// flags computation shared by all the arithmetic and logical operations:
+// operands are given zero extended in a u16 and the result is truncated to
+// the operation size before being returned
+
+use super::{Cpu, CpuModel, Flags, OpSize};
+
+impl OpSize {
+    pub fn mask(self) -> u32 {
+        match self {
+            OpSize::Byte => 0xff,
+            OpSize::Word => 0xffff,
+        }
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            OpSize::Byte => 8,
+            OpSize::Word => 16,
+        }
+    }
+
+    pub fn sign_extend(self, v: u16) -> i32 {
+        match self {
+            OpSize::Byte => v as u8 as i8 as i32,
+            OpSize::Word => v as i16 as i32,
+        }
+    }
+
+    pub fn sign_bit(self) -> u32 {
+        match self {
+            OpSize::Byte => 0x80,
+            OpSize::Word => 0x8000,
+        }
+    }
+}
+
+// PF only looks at the low byte of the result, whatever the operand size
+fn parity(v: u32) -> bool {
+    (v as u8).count_ones().is_multiple_of(2)
+}
+
+impl Cpu {
+    pub fn update_flag(&mut self, f: Flags, on: bool) {
+        if on {
+            self.set_flag(f);
+        } else {
+            self.clear_flag(f);
+        }
+    }
+
+    // ZF, SF and PF only depend on the result
+    pub fn set_szp_flags(&mut self, sz: OpSize, res: u16) {
+        let res = res as u32 & sz.mask();
+        self.update_flag(Flags::Z, res == 0);
+        self.update_flag(Flags::S, res & sz.sign_bit() != 0);
+        self.update_flag(Flags::P, parity(res));
+    }
+
+    // add and adc
+    pub fn alu_add(&mut self, sz: OpSize, a: u16, b: u16, carry: bool) -> u16 {
+        let (a, b) = (a as u32 & sz.mask(), b as u32 & sz.mask());
+        let res = a + b + carry as u32;
+        self.update_flag(Flags::C, res > sz.mask());
+        self.update_flag(Flags::A, (a ^ b ^ res) & 0x10 != 0);
+        // both operands have the same sign, and the result has not
+        self.update_flag(Flags::O, (a ^ res) & (b ^ res) & sz.sign_bit() != 0);
+        let res = (res & sz.mask()) as u16;
+        self.set_szp_flags(sz, res);
+        res
+    }
+
+    // sub, sbb and cmp
+    pub fn alu_sub(&mut self, sz: OpSize, a: u16, b: u16, borrow: bool) -> u16 {
+        let (a, b) = (a as u32 & sz.mask(), b as u32 & sz.mask());
+        let res = a.wrapping_sub(b).wrapping_sub(borrow as u32);
+        self.update_flag(Flags::C, b + borrow as u32 > a);
+        self.update_flag(Flags::A, (a ^ b ^ res) & 0x10 != 0);
+        // operands have different signs, and the result has not the sign of a
+        self.update_flag(Flags::O, (a ^ b) & (a ^ res) & sz.sign_bit() != 0);
+        let res = (res & sz.mask()) as u16;
+        self.set_szp_flags(sz, res);
+        res
+    }
+
+    // and, or, xor and test: CF and OF are always cleared, AF is undefined
+    // (we clear it too)
+    pub fn alu_logic(&mut self, sz: OpSize, res: u16) -> u16 {
+        let res = (res as u32 & sz.mask()) as u16;
+        self.clear_flag(Flags::C);
+        self.clear_flag(Flags::O);
+        self.clear_flag(Flags::A);
+        self.set_szp_flags(sz, res);
+        res
+    }
+
+    // mul: returns the double size product (ax, or dx:ax). CF and OF tell
+    // whether the high half is used. the other flags are undefined, the 8086
+    // leaves them as computed on the high half by its last microcode step
+    pub fn alu_mul(&mut self, sz: OpSize, a: u16, b: u16) -> u32 {
+        let (a, b) = (a as u32 & sz.mask(), b as u32 & sz.mask());
+        let res = a * b;
+        let high = res >> sz.bits();
+        self.update_flag(Flags::C, high != 0);
+        self.update_flag(Flags::O, high != 0);
+        self.clear_flag(Flags::A);
+        self.set_szp_flags(sz, high as u16);
+        res
+    }
+
+    // imul: CF and OF are set when the high half is not just the sign
+    // extension of the low half
+    pub fn alu_imul(&mut self, sz: OpSize, a: u16, b: u16) -> u32 {
+        let res = sz.sign_extend(a) * sz.sign_extend(b);
+        let fits = sz.sign_extend(res as u16 & sz.mask() as u16) == res;
+        self.update_flag(Flags::C, !fits);
+        self.update_flag(Flags::O, !fits);
+        self.clear_flag(Flags::A);
+        let res = res as u32 & (sz.mask() << sz.bits() | sz.mask());
+        self.set_szp_flags(sz, (res >> sz.bits()) as u16);
+        res
+    }
+
+    // div: the dividend is twice the operand size, returns the quotient and
+    // the remainder, or None when the divisor is zero or the quotient does
+    // not fit (divide error). flags are undefined and left untouched
+    pub fn alu_div(&mut self, sz: OpSize, n: u32, d: u16) -> Option<(u16, u16)> {
+        let d = d as u32 & sz.mask();
+        if d == 0 {
+            return None;
+        }
+        let (q, r) = (n / d, n % d);
+        if q > sz.mask() {
+            return None;
+        }
+        Some((q as u16, r as u16))
+    }
+
+    // idiv: the 8086 faults on the most negative quotient (80h, 8000h), the
+    // 80286 accepts it
+    pub fn alu_idiv(&mut self, sz: OpSize, n: u32, d: u16) -> Option<(u16, u16)> {
+        let n = match sz {
+            OpSize::Byte => n as u16 as i16 as i32,
+            OpSize::Word => n as i32,
+        };
+        let d = sz.sign_extend(d);
+        if d == 0 {
+            return None;
+        }
+        // i32::MIN / -1 can not happen, the dividend is at most 32 bits wide
+        let (q, r) = (n as i64 / d as i64, n as i64 % d as i64);
+        let max = sz.sign_bit() as i64 - 1;
+        let min = match self.model {
+            CpuModel::I8086 => -max,
+            CpuModel::I80286 => -max - 1,
+        };
+        if q < min || q > max {
+            return None;
+        }
+        Some((q as u16 & sz.mask() as u16, r as u16 & sz.mask() as u16))
+    }
+}
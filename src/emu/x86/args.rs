@@ -1,11 +1,20 @@
-use lib8086::Arg;
+use lib8086::{Arg, Inst, Mem, Width};
 
 use tracing::debug;
 
-use super::{Cpu, OpSize};
+use super::{Cpu, OpSize, Sreg};
+
+impl From<Width> for OpSize {
+    fn from(w: Width) -> Self {
+        match w {
+            Width::Byte => OpSize::Byte,
+            Width::Word => OpSize::Word,
+        }
+    }
+}
 
 impl Cpu {
-    pub fn arg_size(&self, arg: &Arg) -> OpSize {
+    pub fn arg_size(&self, inst: &Inst, arg: &Arg) -> OpSize {
         match arg {
             Arg::Reg8(_) => OpSize::Byte,
             Arg::Reg16(_) => OpSize::Word,
@@ -14,11 +23,30 @@ impl Cpu {
             Arg::Imm16(_) => OpSize::Word,
             Arg::Uimm16(_) => OpSize::Word,
             Arg::Sreg(_) => OpSize::Word,
-            _ => unimplemented!(),
+            // memory operands have no size of their own, the opcode gives it
+            Arg::Mem(_) => inst.width.into(),
         }
     }
 
-    pub fn read_arg(&self, arg: &Arg) -> u16 {
+    // returns the segment and the offset of a memory operand: bp based forms
+    // default to SS, all others to DS, unless a segment prefix is present
+    pub fn mem_addr(&self, inst: &Inst, mem: &Mem) -> (Sreg, u16) {
+        let mut off = mem.disp();
+        if let Some(base) = mem.base() {
+            off = off.wrapping_add(self.read_reg16(base));
+        }
+        if let Some(index) = mem.index() {
+            off = off.wrapping_add(self.read_reg16(index));
+        }
+        let seg = match inst.seg {
+            Some(seg) => seg,
+            None if mem.uses_bp() => Sreg::SS,
+            None => Sreg::DS,
+        };
+        (seg, off)
+    }
+
+    pub fn read_arg(&self, inst: &Inst, arg: &Arg) -> u16 {
         match arg {
             Arg::Reg8(reg) => {
                 let b = self.read_reg8(*reg);
@@ -33,18 +61,25 @@ impl Cpu {
             Arg::Imm8(imm) => *imm as u16,
             Arg::Uimm8(imm) => *imm as u16,
             Arg::Imm16(imm) => *imm as u16,
-            Arg::Uimm16(imm) => *imm as u16,
+            Arg::Uimm16(imm) => *imm,
             Arg::Sreg(sreg) => self.read_sreg(*sreg),
-            Arg::Mem(_mem) => unimplemented!(),
+            Arg::Mem(mem) => {
+                let (seg, off) = self.mem_addr(inst, mem);
+                let sz = self.arg_size(inst, arg);
+                // nothing mapped there: the bus floats high
+                let v = self.read_mem(seg, off, sz).unwrap_or(0xffff);
+                debug!("read-arg: mem {:?}:{:04X} = {:04X}", seg, off, v);
+                v
+            }
         }
     }
 
-    pub fn write_arg(&mut self, arg: &Arg, val: u16) {
+    pub fn write_arg(&mut self, inst: &Inst, arg: &Arg, val: u16) {
         match arg {
             Arg::Reg8(reg) => {
                 let val = val as u8;
                 debug!("write-arg: reg8 {:?} = {:02X}", reg, val);
-                self.write_reg8(*reg, (val & 0xff) as u8);
+                self.write_reg8(*reg, val);
             }
             Arg::Reg16(reg) => {
                 debug!("write-arg: reg16 {:?} = {:04X}", reg, val);
@@ -55,7 +90,27 @@ impl Cpu {
             Arg::Imm16(_) => panic!("Cannot write to imm16"),
             Arg::Uimm16(_) => panic!("Cannot write to uimm16"),
             Arg::Sreg(sreg) => self.write_sreg(*sreg, val),
-            Arg::Mem(_mem) => unimplemented!(),
+            Arg::Mem(mem) => {
+                let (seg, off) = self.mem_addr(inst, mem);
+                let sz = self.arg_size(inst, arg);
+                debug!("write-arg: mem {:?}:{:04X} = {:04X}", seg, off, val);
+                self.write_mem(seg, off, val, sz);
+            }
         }
     }
-}
\ No newline at end of file
+
+    // reads a far pointer from a memory operand: the offset comes first, the
+    // segment is in the next word. returns (segment, offset)
+    pub fn read_far_ptr(&self, inst: &Inst, arg: &Arg) -> (u16, u16) {
+        let Arg::Mem(mem) = arg else {
+            panic!("far pointer must be in memory")
+        };
+        let (seg, off) = self.mem_addr(inst, mem);
+        let o = self.read_mem(seg, off, OpSize::Word).unwrap_or(0xffff);
+        let s = self
+            .read_mem(seg, off.wrapping_add(2), OpSize::Word)
+            .unwrap_or(0xffff);
+        debug!("read-far-ptr: mem {:?}:{:04X} = {:04X}:{:04X}", seg, off, s, o);
+        (s, o)
+    }
+}
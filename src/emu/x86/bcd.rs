@@ -0,0 +1,100 @@
+// decimal adjust instructions. the adjustments go through the alu, so the
+// flags the manuals leave undefined (OF, and SF/ZF/PF for aaa and aas) are
+// the ones the 8086 computes for the underlying add or sub
+
+use lib8086::{Reg16, Reg8};
+
+use super::{Cpu, CpuModel, Flags, OpSize};
+
+impl Cpu {
+    pub fn daa(&mut self) {
+        let al = self.read_reg8(Reg8::AL) as u16;
+        let af = self.is_flag_set(Flags::A);
+        let cf = self.is_flag_set(Flags::C);
+        let mut adj = 0;
+        if al & 0x0f > 9 || af {
+            adj |= 0x06;
+        }
+        if al > 0x99 || cf {
+            adj |= 0x60;
+        }
+        let res = self.alu_add(OpSize::Byte, al, adj, false);
+        self.update_flag(Flags::A, adj & 0x06 != 0);
+        self.update_flag(Flags::C, adj & 0x60 != 0);
+        self.write_reg8(Reg8::AL, res as u8);
+    }
+
+    pub fn das(&mut self) {
+        let al = self.read_reg8(Reg8::AL) as u16;
+        let af = self.is_flag_set(Flags::A);
+        let cf = self.is_flag_set(Flags::C);
+        let mut adj = 0;
+        if al & 0x0f > 9 || af {
+            adj |= 0x06;
+        }
+        if al > 0x99 || cf {
+            adj |= 0x60;
+        }
+        let res = self.alu_sub(OpSize::Byte, al, adj, false);
+        self.update_flag(Flags::A, adj & 0x06 != 0);
+        self.update_flag(Flags::C, adj & 0x60 != 0);
+        self.write_reg8(Reg8::AL, res as u8);
+    }
+
+    // the 8086 adds 6 to al and 1 to ah separately, later cpus add 106h to
+    // ax, so a carry out of al reaches ah
+    pub fn aaa(&mut self) {
+        let ax = self.read_reg16(Reg16::AX);
+        let (al, ah) = (ax & 0xff, ax >> 8);
+        let adjust = al & 0x0f > 9 || self.is_flag_set(Flags::A);
+        let adj = if adjust { 0x06 } else { 0x00 };
+        let res = self.alu_add(OpSize::Byte, al, adj, false);
+        let ax = match (adjust, self.model) {
+            (false, _) => ax,
+            (true, CpuModel::I8086) => (ah + 1) << 8 | res,
+            (true, CpuModel::I80286) => ax.wrapping_add(0x106),
+        };
+        self.update_flag(Flags::A, adjust);
+        self.update_flag(Flags::C, adjust);
+        self.write_reg16(Reg16::AX, ax & 0xff0f);
+    }
+
+    // same as aaa, the 80286 borrows from ah when al is below 6
+    pub fn aas(&mut self) {
+        let ax = self.read_reg16(Reg16::AX);
+        let (al, ah) = (ax & 0xff, ax >> 8);
+        let adjust = al & 0x0f > 9 || self.is_flag_set(Flags::A);
+        let adj = if adjust { 0x06 } else { 0x00 };
+        let res = self.alu_sub(OpSize::Byte, al, adj, false);
+        let ax = match (adjust, self.model) {
+            (false, _) => ax,
+            (true, CpuModel::I8086) => ah.wrapping_sub(1) << 8 | res,
+            (true, CpuModel::I80286) => ax.wrapping_sub(6).wrapping_sub(0x100),
+        };
+        self.update_flag(Flags::A, adjust);
+        self.update_flag(Flags::C, adjust);
+        self.write_reg16(Reg16::AX, ax & 0xff0f);
+    }
+
+    // al = al + ah * base, ah = 0. the flags are the ones of the final add
+    pub fn aad(&mut self, base: u8) {
+        let al = self.read_reg8(Reg8::AL) as u16;
+        let ah = self.read_reg8(Reg8::AH);
+        let prod = ah.wrapping_mul(base) as u16;
+        let res = self.alu_add(OpSize::Byte, al, prod, false);
+        self.write_reg16(Reg16::AX, res);
+    }
+
+    // ah = al / base, al = al % base. returns false on a zero base, which
+    // is a divide error
+    pub fn aam(&mut self, base: u8) -> bool {
+        let al = self.read_reg8(Reg8::AL);
+        let (Some(q), Some(r)) = (al.checked_div(base), al.checked_rem(base)) else {
+            return false;
+        };
+        self.write_reg8(Reg8::AH, q);
+        let r = self.alu_logic(OpSize::Byte, r as u16);
+        self.write_reg8(Reg8::AL, r as u8);
+        true
+    }
+}
@@ -2,8 +2,18 @@ use std::path::PathBuf;
 
 use super::MemAddrT;
 
+// cpu whose behaviour is emulated where the 8086 and its successors differ
+// (only the 8086 instruction set is available in both cases)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CpuModel {
+    #[default]
+    I8086,
+    I80286,
+}
+
 pub struct Config {
     pub bios_file: PathBuf,
     pub ram_size: MemAddrT,
     pub bios_addr: MemAddrT,
+    pub model: CpuModel,
 }
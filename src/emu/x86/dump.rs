@@ -11,20 +11,20 @@ pub fn dump(buf: &[u8], origin: usize, len: usize) {
             if i + j < len {
                 line.push(format!("{:02X} ", buf[i + j]));
             } else {
-                line.push(format!("   "));
+                line.push("   ".to_string());
             }
         }
-        line.push(format!(" "));
+        line.push(" ".to_string());
         for j in 0..16 {
             if i + j < len {
                 let c = buf[i + j];
-                if c >= 32 && c < 127 {
+                if (32..127).contains(&c) {
                     line.push(format!("{}", c as char));
                 } else {
-                    line.push(format!("."));
+                    line.push(".".to_string());
                 }
             } else {
-                line.push(format!(" "));
+                line.push(" ".to_string());
             }
         }
         info!("{}", line.join(""));
@@ -1,265 +1,254 @@
-use std::cmp::Ordering;
+use tracing::{debug, info, trace, warn};
 
-use tracing::{debug, info, trace};
-
-use lib8086::{Cc, Inst, Op, Reg16, Reg8, Sreg};
+use lib8086::{Cc, Inst, Invalid, Op, Reg16, Reg8, SliceDecoder, Sreg, MAX_INST_LEN};
 
 use crate::x86::MemAddrT;
 
-use super::{Arg, Cpu, Decoder, Flags, OpSize};
+use super::{
+    int::{INT_BREAKPOINT, INT_INVALID_OPCODE, INT_OVERFLOW},
+    Arg, Cpu, CpuModel, Decoder, Flags, OpSize,
+};
 
 impl Cpu {
-    pub fn next_inst(&mut self) -> (Inst, u32, Vec<u8>) {
-        struct EA<'a> {
-            ip: u16,
-            cpu: &'a Cpu,
-            bytes: Vec<u8>,
-        }
-
-        impl<'a> Iterator for EA<'a> {
-            type Item = u8;
-
-            fn next(&mut self) -> Option<u8> {
-                let Some(v) = self.cpu.read_mem(Sreg::CS, self.ip, OpSize::Byte) else {
-                    return None;
-                };
-                self.bytes.push(v as u8);
-                self.ip += 1;
-                Some(v as u8)
-            }
-        }
-
+    // reads the bytes of the instruction at cs:ip, the longest one fits
+    pub fn fetch(&self) -> [u8; MAX_INST_LEN] {
         let ip = self.read_ip();
-        let pc = self.calc_ea(Sreg::CS, ip);
+        let mut buf = [0u8; MAX_INST_LEN];
+        for (i, b) in buf.iter_mut().enumerate() {
+            // ip wraps around within the code segment, and nothing mapped
+            // there means the bus floats high
+            let off = ip.wrapping_add(i as u16);
+            *b = self.read_mem(Sreg::CS, off, OpSize::Byte).unwrap_or(0xff) as u8;
+        }
+        buf
+    }
 
+    // decodes the instruction at cs:ip, returns it with its address and the
+    // fetched bytes (the first inst.size ones belong to the instruction)
+    pub fn next_inst(&self) -> (Inst, MemAddrT, [u8; MAX_INST_LEN]) {
+        let pc = self.calc_ea(Sreg::CS, self.read_ip());
         trace!("exec: pc={:04x}", pc);
 
-        let mut ea = EA {
-            ip,
-            cpu: self,
-            bytes: vec![],
+        let buf = self.fetch();
+        // the buffer can hold any instruction, but stay safe: one that can
+        // not be decoded is handled as an invalid one
+        let inst = match SliceDecoder::new(&buf, pc).decode() {
+            Ok(d) => d.inst,
+            Err(e) => {
+                warn!("exec: pc={:05x}: {}", pc, e);
+                Inst {
+                    op: Op::Invalid(Invalid::Unknown),
+                    size: 1,
+                    ..Default::default()
+                }
+            }
         };
-        let mut dec = Decoder::new(&mut ea);
-
-        let inst = dec.next_i().unwrap(); // !!! we have to do something about this
-
-        let bytes = ea.bytes;        
-        (inst, pc, bytes)
+        (inst, pc, buf)
     }
 
     pub fn tick(&mut self) {
+        if self.halted {
+            // only an interrupt can wake the cpu up
+            self.check_interrupts(false);
+            return;
+        }
+
+        let trap = self.is_flag_set(Flags::T);
+
         let (inst, _, _) = self.next_inst();
         debug!("tick: inst={:?}", inst);
 
-        let mut nip = self.read_ip() + inst.size as u16;
+        let mut nip = self.read_ip().wrapping_add(inst.size as u16);
         match inst.op {
             Op::Nop => (),
             Op::Add(a1, a2) => {
-                let v1 = self.read_arg(&a1);
-                let v2 = self.read_arg(&a2);
-                let nv = v1.wrapping_add(v2);
-                self.write_arg(&a1, nv);
-                // todo: set flags
-                match v1.cmp(&v2) {
-                    Ordering::Less => self.set_flag(Flags::C),
-                    Ordering::Equal => self.set_flag(Flags::Z),
-                    Ordering::Greater => self.clear_flag(Flags::C),
-                }
+                let sz = self.arg_size(&inst, &a1);
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                let nv = self.alu_add(sz, v1, v2, false);
+                self.write_arg(&inst, &a1, nv);
             }
             Op::Adc(a1, a2) => {
-                let v1 = self.read_arg(&a1);
-                let v2 = self.read_arg(&a2);
-                let cf = self.is_flag_set(Flags::C) as u16;
-                let nv = v1.wrapping_add(v2).wrapping_add(cf);
-                self.write_arg(&a1, nv);
-                // todo: set flags
+                let sz = self.arg_size(&inst, &a1);
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                let cf = self.is_flag_set(Flags::C);
+                let nv = self.alu_add(sz, v1, v2, cf);
+                self.write_arg(&inst, &a1, nv);
             }
             Op::Sbb(a1, a2) => {
-                let v1 = self.read_arg(&a1);
-                let v2 = self.read_arg(&a2);
-                let cf = self.is_flag_set(Flags::C) as u16;
-                let nv = v1.wrapping_sub(v2).wrapping_sub(cf);
-                self.write_arg(&a1, nv);
-                // todo: set flags
+                let sz = self.arg_size(&inst, &a1);
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                let cf = self.is_flag_set(Flags::C);
+                let nv = self.alu_sub(sz, v1, v2, cf);
+                self.write_arg(&inst, &a1, nv);
             }
             Op::Sub(a1, a2) => {
-                let v1 = self.read_arg(&a1);
-                let v2 = self.read_arg(&a2);
-                let nv = v1.wrapping_sub(v2);
-                self.write_arg(&a1, nv);
-                // todo: set flags
+                let sz = self.arg_size(&inst, &a1);
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                let nv = self.alu_sub(sz, v1, v2, false);
+                self.write_arg(&inst, &a1, nv);
             }
             Op::And(a1, a2) => {
-                let v1 = self.read_arg(&a1);
-                let v2 = self.read_arg(&a2);
-                self.write_arg(&a1, v1 & v2);
-                // todo: set flags
+                let sz = self.arg_size(&inst, &a1);
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                let nv = self.alu_logic(sz, v1 & v2);
+                self.write_arg(&inst, &a1, nv);
             }
             Op::Or(a1, a2) => {
-                let v1 = self.read_arg(&a1);
-                let v2 = self.read_arg(&a2);
-                self.write_arg(&a1, v1 | v2);
+                let sz = self.arg_size(&inst, &a1);
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                let nv = self.alu_logic(sz, v1 | v2);
+                self.write_arg(&inst, &a1, nv);
             }
             Op::Xor(a1, a2) => {
-                let v1 = self.read_arg(&a1);
-                let v2 = self.read_arg(&a2);
-                self.write_arg(&a1, v1 ^ v2);
+                let sz = self.arg_size(&inst, &a1);
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                let nv = self.alu_logic(sz, v1 ^ v2);
+                self.write_arg(&inst, &a1, nv);
             }
             Op::Cmp(a1, a2) => {
-                let v1 = self.read_arg(&a1);
-                let v2 = self.read_arg(&a2);
-                let res = v1.wrapping_sub(v2);
-
-                // todo: do something clever with the flags
-                match v1.cmp(&v2) {
-                    Ordering::Less => self.set_flag(Flags::C),
-                    Ordering::Equal => self.set_flag(Flags::Z),
-                    Ordering::Greater => self.clear_flag(Flags::C),
-                }
-                match (v1 as i16).cmp(&(v2 as i16)) {
-                    Ordering::Less => self.set_flag(Flags::S),
-                    Ordering::Equal => self.set_flag(Flags::Z),
-                    Ordering::Greater => self.clear_flag(Flags::S),
-                }
+                let sz = self.arg_size(&inst, &a1);
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                // same as sub, but the result is dropped
+                self.alu_sub(sz, v1, v2, false);
+            }
+            Op::Test(a1, a2) => {
+                let sz = self.arg_size(&inst, &a1);
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                self.alu_logic(sz, v1 & v2);
+            }
+            Op::Call(a1) => {
+                // relative form, or indirect through a register or memory
+                let target = match a1 {
+                    Arg::Imm16(rel16) => nip.wrapping_add_signed(rel16),
+                    _ => self.read_arg(&inst, &a1),
+                };
+                trace!(" - CALL: {:04x} [ret={:04x}]", target, nip);
+                self.push_word(nip);
+                nip = target;
+            }
+            Op::CallFar(seg, off) => {
+                let seg = self.read_arg(&inst, &seg);
+                let off = self.read_arg(&inst, &off);
+                self.far_call(seg, nip);
+                nip = off;
+            }
+            Op::CallFarMem(a1) => {
+                let (seg, off) = self.read_far_ptr(&inst, &a1);
+                self.far_call(seg, nip);
+                nip = off;
             }
-            Op::Call(a1) => match a1 {
-                Arg::Imm16(rel16) => {
-                    info!(" - CALL rel={} [nip={:04x}]", rel16, nip);
-                    let sp = self.read_reg16(Reg16::SP);
-                    let ss = self.read_sreg(Sreg::SS);
-                    trace!(" - CALL: sp={:04x} <- 0x{:04x}", sp, nip);
-                    self.write_mem(Sreg::SS, sp, nip, OpSize::Word);
-                    self.write_reg16(Reg16::SP, sp.wrapping_sub(2));
-                    nip = nip.wrapping_add_signed(rel16);
-                    trace!(" - CALL: nip set to {:04x}", nip);
-                }
-                _ => panic!("unknown form of call"),
-            },
             Op::Ret => {
-                let nip_copy = nip;
-                let sp = self.read_reg16(Reg16::SP);
-                let nsp = sp.wrapping_add(2);
-                trace!(" - RET: nsp={:04x}", nsp);
-                nip = self.read_mem(Sreg::SS, nsp, OpSize::Word).unwrap();
+                nip = self.pop_word();
                 trace!(" - RET nip={:04x}", nip);
-                self.write_reg16(Reg16::SP, nsp);
             }
-            Op::Push(a1) => {
-                let v = self.read_arg(&a1);
+            Op::RetImm(n) => {
+                nip = self.pop_word();
+                // the callee drops its arguments
                 let sp = self.read_reg16(Reg16::SP);
-                let ss = self.read_sreg(Sreg::SS);
-                trace!(" - PUSH: sp={:04x}", sp);
-
-                self.write_mem(Sreg::SS, sp, v, OpSize::Word);
-                self.write_reg16(Reg16::SP, sp.wrapping_sub(2));
+                self.write_reg16(Reg16::SP, sp.wrapping_add(n));
+            }
+            Op::Retf | Op::RetfImm(_) => {
+                nip = self.pop_word();
+                let cs = self.pop_word();
+                self.write_sreg(Sreg::CS, cs);
+                if let Op::RetfImm(n) = inst.op {
+                    let sp = self.read_reg16(Reg16::SP);
+                    self.write_reg16(Reg16::SP, sp.wrapping_add(n));
+                }
+                trace!(" - RETF {:04x}:{:04x}", cs, nip);
+            }
+            Op::Push(a1) => {
+                // the 8086 pushes sp after it has been decremented, the
+                // 80286 pushes its value before the instruction
+                let v = match (a1, self.model) {
+                    (Arg::Reg16(Reg16::SP), CpuModel::I8086) => {
+                        self.read_reg16(Reg16::SP).wrapping_sub(2)
+                    }
+                    _ => self.read_arg(&inst, &a1),
+                };
+                trace!(" - PUSH {:?} -> {:04x}", a1, v);
+                self.push_word(v);
             }
             Op::Pop(a1) => {
-                let sp = self.read_reg16(Reg16::SP);
-                let nsp = sp.wrapping_add(2);
-                trace!(" - POP: nsp={:04x}", sp);
-                let v = self.read_mem(Sreg::SS, nsp, OpSize::Word).unwrap();
+                let v = self.pop_word();
                 trace!(" - POP {:?} <- {:04x}", a1, v);
-                self.write_arg(&a1, v as u16);
-                self.write_reg16(Reg16::SP, nsp);
-            }
-            Op::Daa => todo!(),
-            Op::Das => todo!(),
-            Op::Aaa => {
-                // todo: not sure if this is correct
-                let mut ax = self.read_reg16(Reg16::AX);
-                let mut ah = (ax >> 8) as u8;
-                let mut al = (ax & 0xFF) as u8;
-                let al_low = ax & 0x0F;
-                if al_low > 9 || self.is_flag_set(Flags::A) {
-                    // https://www.pcjs.org/documents/manuals/intel/8086/ops/AAA/
-
-                    // todo: check !!!! this is what happens in 286+
-                    // ax = ax.wrapping_add(0x106);
-
-                    // todo: but this is what we want for 8086:
-                    al = al.wrapping_add(0x06);
-                    ah = ah.wrapping_add(0x01);
-                    ax = (ah as u16) << 8 | al as u16;
-
-                    self.set_flag(Flags::A);
-                    self.set_flag(Flags::C);
-                } else {
-                    self.clear_flag(Flags::A);
-                    self.clear_flag(Flags::C);
+                self.write_arg(&inst, &a1, v);
+                if a1 == Arg::Sreg(Sreg::SS) {
+                    self.inhibit_interrupts();
                 }
-                ax = ax & 0xFF0F;
-                self.write_reg16(Reg16::AX, ax);
-            }
-            Op::Aad(b1) => {
-                // todo: not sure if this is correct
-                let al = self.read_reg8(Reg8::AL);
-                let ah = self.read_reg8(Reg8::AH);
-                let al = al + (ah * b1 as u8);
-                self.write_reg8(Reg8::AL, al);
-                self.write_reg8(Reg8::AH, 0);
             }
+            Op::Daa => self.daa(),
+            Op::Das => self.das(),
+            Op::Aaa => self.aaa(),
+            Op::Aas => self.aas(),
+            Op::Aad(b1) => self.aad(b1),
             Op::Aam(b1) => {
-                // todo: not sure if this is correct
-                let al = self.read_reg8(Reg8::AL);
-                let ah = al / b1;
-                self.write_reg8(Reg8::AL, al % b1);
-                self.write_reg8(Reg8::AH, ah);
-            }
-            Op::Aas => {
-                // todo: not sure if this is correct
-                let mut ax = self.read_reg16(Reg16::AX);
-                let al_low = ax & 0x0F;
-                if al_low > 9 || self.is_flag_set(Flags::A) {
-                    ax = ax.wrapping_sub(0x0600);
-                    let ah = (ax >> 8) as u8;
-                    let ah = ah.wrapping_sub(1);
-                    let al = ax & 0xff;
-                    ax = (ah as u16) << 8 | al as u16;
-                    self.set_flag(Flags::A);
-                    self.set_flag(Flags::C);
-                } else {
-                    self.clear_flag(Flags::A);
-                    self.clear_flag(Flags::C);
-                    ax = ax & 0xFF0F;
+                if !self.aam(b1) {
+                    nip = self.divide_error(nip);
                 }
-                self.write_reg16(Reg16::AX, ax);
             }
-            Op::Inc(_) => todo!(),
-            Op::Dec(_) => todo!(),
-            Op::Jcc(cc, disp) => {
-                let cond = match cc {
-                    Cc::O => self.is_flag_set(Flags::C),
-                    Cc::NO => !self.is_flag_set(Flags::C),
-                    Cc::B => self.is_flag_set(Flags::C),
-                    Cc::NB => !self.is_flag_set(Flags::C),
-                    Cc::E => self.is_flag_set(Flags::Z),
-                    Cc::NE => !self.is_flag_set(Flags::Z),
-                    Cc::BE => self.is_flag_set(Flags::C) || self.is_flag_set(Flags::Z),
-                    Cc::NBE => !self.is_flag_set(Flags::C) && !self.is_flag_set(Flags::Z),
-                    Cc::S => self.is_flag_set(Flags::S),
-                    Cc::NS => !self.is_flag_set(Flags::S),
-                    Cc::P => self.is_flag_set(Flags::P),
-                    Cc::NP => !self.is_flag_set(Flags::P),
-                    Cc::L => self.is_flag_set(Flags::S) != self.is_flag_set(Flags::O),
-                    Cc::NL => self.is_flag_set(Flags::S) == self.is_flag_set(Flags::O),
-                    Cc::LE => {
-                        self.is_flag_set(Flags::Z)
-                            || (self.is_flag_set(Flags::S) != self.is_flag_set(Flags::O))
-                    }
-                    Cc::NLE => {
-                        !self.is_flag_set(Flags::Z)
-                            && (self.is_flag_set(Flags::S) == self.is_flag_set(Flags::O))
-                    }
+            Op::Inc(a1) | Op::Dec(a1) => {
+                // same as add/sub 1, but CF is preserved
+                let sz = self.arg_size(&inst, &a1);
+                let v = self.read_arg(&inst, &a1);
+                let cf = self.is_flag_set(Flags::C);
+                let nv = match inst.op {
+                    Op::Inc(_) => self.alu_add(sz, v, 1, false),
+                    _ => self.alu_sub(sz, v, 1, false),
                 };
+                self.update_flag(Flags::C, cf);
+                self.write_arg(&inst, &a1, nv);
+            }
+            Op::Jcc(cc, disp) => {
+                let cond = cc.evaluate(self.read_flags());
                 trace!(" - J: cc={:?} cond={}", cc, cond);
                 if cond {
-                    nip = nip.wrapping_add(disp as u16);
+                    nip = nip.wrapping_add_signed(disp as i16);
+                }
+            }
+            Op::Jmp(a1) => {
+                nip = match a1 {
+                    Arg::Imm8(rel8) => nip.wrapping_add_signed(rel8 as i16),
+                    Arg::Imm16(rel16) => nip.wrapping_add_signed(rel16),
+                    _ => self.read_arg(&inst, &a1),
+                };
+            }
+            Op::JmpFarMem(a1) => {
+                let (seg, off) = self.read_far_ptr(&inst, &a1);
+                self.write_sreg(Sreg::CS, seg);
+                nip = off;
+            }
+            Op::Jcxz(disp) => {
+                if self.read_reg16(Reg16::CX) == 0 {
+                    nip = nip.wrapping_add_signed(disp as i16);
+                }
+            }
+            Op::Loop(disp) | Op::Loopz(disp) | Op::Loopnz(disp) => {
+                // cx is decremented without touching the flags
+                let cx = self.read_reg16(Reg16::CX).wrapping_sub(1);
+                self.write_reg16(Reg16::CX, cx);
+                let cond = match inst.op {
+                    Op::Loopz(_) => self.is_flag_set(Flags::Z),
+                    Op::Loopnz(_) => !self.is_flag_set(Flags::Z),
+                    _ => true,
+                };
+                if cx != 0 && cond {
+                    nip = nip.wrapping_add_signed(disp as i16);
                 }
             }
             Op::JmpFar(seg, off) => {
-                let seg = self.read_arg(&seg);
+                let seg = self.read_arg(&inst, &seg);
                 self.write_sreg(Sreg::CS, seg);
-                nip = self.read_arg(&off);
+                nip = self.read_arg(&inst, &off);
             }
             Op::Cbw => {
                 let al = self.read_reg8(Reg8::AL);
@@ -277,23 +266,78 @@ impl Cpu {
                 self.write_reg16(Reg16::AX, ax);
                 self.write_reg16(Reg16::DX, dx);
             }
-            Op::Test(_, _) => todo!(),
-            Op::Xchg(_, _) => todo!(),
+            Op::Xchg(a1, a2) => {
+                let v1 = self.read_arg(&inst, &a1);
+                let v2 = self.read_arg(&inst, &a2);
+                self.write_arg(&inst, &a1, v2);
+                self.write_arg(&inst, &a2, v1);
+            }
             Op::Mov(a1, a2) => {
-                let v2 = self.read_arg(&a2);
+                let v2 = self.read_arg(&inst, &a2);
                 trace!("MOV {:?} <- {:04X}", a1, v2);
-                self.write_arg(&a1, v2);
+                self.write_arg(&inst, &a1, v2);
+                if a1 == Arg::Sreg(Sreg::SS) {
+                    self.inhibit_interrupts();
+                }
+            }
+            Op::Lea(a1, a2) => {
+                // only the offset is computed, no memory is accessed
+                let Arg::Mem(mem) = a2 else {
+                    panic!("lea needs a memory operand")
+                };
+                let (_, off) = self.mem_addr(&inst, &mem);
+                self.write_arg(&inst, &a1, off);
+            }
+            Op::Lds(a1, a2) | Op::Les(a1, a2) => {
+                let (seg, off) = self.read_far_ptr(&inst, &a2);
+                self.write_arg(&inst, &a1, off);
+                let sreg = match inst.op {
+                    Op::Lds(_, _) => Sreg::DS,
+                    _ => Sreg::ES,
+                };
+                self.write_sreg(sreg, seg);
+            }
+            Op::Xlat => {
+                // al = [bx + al], ds unless overridden
+                let seg = inst.seg.unwrap_or(Sreg::DS);
+                let off = self
+                    .read_reg16(Reg16::BX)
+                    .wrapping_add(self.read_reg8(Reg8::AL) as u16);
+                let v = self.read_mem(seg, off, OpSize::Byte).unwrap_or(0xff);
+                self.write_reg8(Reg8::AL, v as u8);
+            }
+            Op::Lahf => {
+                let flags = self.read_flags();
+                self.write_reg8(Reg8::AH, flags as u8);
+            }
+            Op::Sahf => {
+                // only SF, ZF, AF, PF and CF are loaded
+                let ah = self.read_reg8(Reg8::AH) as u16;
+                let flags = self.read_flags();
+                self.write_flags(flags & 0xff00 | ah);
+            }
+            Op::Pushf => {
+                let flags = self.read_flags();
+                self.push_word(flags);
+            }
+            Op::Popf => {
+                let flags = self.pop_word();
+                self.write_flags(flags);
             }
-            Op::Lea(_, _) => todo!(),
             Op::In(a1, a2) => {
-                let port = self.read_arg(&a1);
-                let val = self.read_arg(&a2);
+                // the port is either an immediate byte or dx
+                let sz = self.arg_size(&inst, &a1);
+                let port = self.read_arg(&inst, &a2);
+                let val = self.read_io(port, sz);
                 trace!("IN: port {:04X} -> {:04X}", port, val);
+                self.write_arg(&inst, &a1, val);
             }
             Op::Out(a1, a2) => {
-                let port = self.read_arg(&a1);
-                let val = self.read_arg(&a2);
-                self.write_io(port, val, OpSize::Byte);
+                let sz = self.arg_size(&inst, &a2);
+                let port = self.read_arg(&inst, &a1);
+                let val = self.read_arg(&inst, &a2);
+                trace!("OUT: port {:04X} <- {:04X}", port, val);
+                self.write_io(port, val, sz);
             }
             Op::Hlt => {
                 self.halted = true;
@@ -312,6 +356,7 @@ impl Cpu {
             }
             Op::Sti => {
                 self.set_flag(Flags::I);
+                self.inhibit_interrupts();
             }
             Op::Cld => {
                 self.clear_flag(Flags::D);
@@ -320,10 +365,102 @@ impl Cpu {
                 self.set_flag(Flags::D);
             }
 
-            Op::Error => todo!(),
-            Op::Invalid(_) => todo!(),
+            Op::Not(a1) => {
+                let v = self.read_arg(&inst, &a1);
+                self.write_arg(&inst, &a1, !v);
+            }
+            Op::Neg(a1) => {
+                let sz = self.arg_size(&inst, &a1);
+                let v = self.read_arg(&inst, &a1);
+                // CF is set unless the operand was zero
+                let nv = self.alu_sub(sz, 0, v, false);
+                self.write_arg(&inst, &a1, nv);
+            }
+            Op::Mul(a1) | Op::Imul(a1) => {
+                let sz = self.arg_size(&inst, &a1);
+                let v = self.read_arg(&inst, &a1);
+                let acc = self.read_reg16(Reg16::AX);
+                let res = match inst.op {
+                    Op::Mul(_) => self.alu_mul(sz, acc, v),
+                    _ => self.alu_imul(sz, acc, v),
+                };
+                match sz {
+                    OpSize::Byte => self.write_reg16(Reg16::AX, res as u16),
+                    OpSize::Word => {
+                        self.write_reg16(Reg16::AX, res as u16);
+                        self.write_reg16(Reg16::DX, (res >> 16) as u16);
+                    }
+                }
+            }
+            Op::Div(a1) | Op::Idiv(a1) => {
+                let sz = self.arg_size(&inst, &a1);
+                let v = self.read_arg(&inst, &a1);
+                let n = match sz {
+                    OpSize::Byte => self.read_reg16(Reg16::AX) as u32,
+                    OpSize::Word => {
+                        (self.read_reg16(Reg16::DX) as u32) << 16
+                            | self.read_reg16(Reg16::AX) as u32
+                    }
+                };
+                let res = match inst.op {
+                    Op::Div(_) => self.alu_div(sz, n, v),
+                    _ => self.alu_idiv(sz, n, v),
+                };
+                trace!(" - DIV: {:08X} / {:04X} = {:?}", n, v, res);
+                match (res, sz) {
+                    (Some((q, r)), OpSize::Byte) => {
+                        self.write_reg8(Reg8::AL, q as u8);
+                        self.write_reg8(Reg8::AH, r as u8);
+                    }
+                    (Some((q, r)), OpSize::Word) => {
+                        self.write_reg16(Reg16::AX, q);
+                        self.write_reg16(Reg16::DX, r);
+                    }
+                    (None, _) => nip = self.divide_error(nip),
+                }
+            }
+            Op::Rol(_, _)
+            | Op::Ror(_, _)
+            | Op::Rcl(_, _)
+            | Op::Rcr(_, _)
+            | Op::Shl(_, _)
+            | Op::Shr(_, _)
+            | Op::Sar(_, _)
+            | Op::Setmo(_, _) => self.shift_op(&inst),
+            Op::Movs | Op::Cmps | Op::Stos | Op::Lods | Op::Scas => {
+                if self.string_op(&inst) {
+                    // executed again, unless an interrupt comes first
+                    nip = self.read_ip();
+                }
+            }
+            Op::Int(n) => self.raise_exception(n),
+            Op::Int3 => self.raise_exception(INT_BREAKPOINT),
+            Op::Into => {
+                if self.is_flag_set(Flags::O) {
+                    self.raise_exception(INT_OVERFLOW);
+                }
+            }
+            Op::Iret => {
+                self.iret();
+                nip = self.read_ip();
+            }
+            // there is no coprocessor: esc does nothing and wait never blocks
+            Op::Esc(_, _) | Op::Wait => (),
+
+            Op::Invalid(_) => {
+                // the 8086 has no invalid opcode exception, what undefined
+                // opcodes do is not modelled and they are skipped. the
+                // 80286 raises int 6
+                warn!("exec: invalid instruction {:?}", inst.op);
+                if self.model == CpuModel::I80286 {
+                    self.raise_exception(INT_INVALID_OPCODE);
+                    nip = self.read_ip();
+                }
+            }
         }
 
         self.write_ip(nip);
+
+        self.check_interrupts(trap);
     }
 }
@@ -1,4 +1,4 @@
-use super::{Result, IOMap, MemAddrT, MemMap, MemOps, OpSize, OpSizeT, Config, dump};
+use super::{Result, IOMap, IrqLines, MemAddrT, MemMap, MemOps, OpSize, OpSizeT, Config, dump};
 
 mod ram;
 use ram::DeviceRAM;
@@ -10,7 +10,8 @@ pub trait Device {
     fn name(&self) -> String;
 }
 
-pub fn init_devices(cfg: &Config, vm: &mut MemMap, io: &mut IOMap) -> Result<()> {
+// devices that raise interrupts keep a clone of `irq`
+pub fn init_devices(cfg: &Config, vm: &mut MemMap, io: &mut IOMap, irq: &IrqLines) -> Result<()> {
     DeviceRAM::register(cfg, vm, io)?; // todo: RAM can be loaded from file (snapshot)
     DeviceROM::register(cfg, vm, io)?; // todo: ROM can be loaded from file
     Ok(())
@@ -0,0 +1,151 @@
+use std::{cell::Cell, rc::Rc};
+
+use tracing::debug;
+
+use super::{Cpu, CpuModel, Flags, MemAddrT, OpSize, Sreg};
+
+// vectors of the predefined interrupts
+pub const INT_DIVIDE_ERROR: u8 = 0;
+pub const INT_SINGLE_STEP: u8 = 1;
+pub const INT_NMI: u8 = 2;
+pub const INT_BREAKPOINT: u8 = 3;
+pub const INT_OVERFLOW: u8 = 4;
+pub const INT_INVALID_OPCODE: u8 = 6; // 80286 only
+
+// maskable interrupt request lines, shared between the cpu and the devices:
+// a device keeps a clone and raises or lowers its line, the cpu acknowledges
+// the lowest pending line between two instructions when IF is set
+#[derive(Debug, Clone, Default)]
+pub struct IrqLines(Rc<Cell<u8>>);
+
+impl IrqLines {
+    pub fn raise(&self, line: u8) {
+        self.0.set(self.0.get() | 1 << (line & 7));
+    }
+
+    pub fn lower(&self, line: u8) {
+        self.0.set(self.0.get() & !(1 << (line & 7)));
+    }
+
+    pub fn pending(&self) -> Option<u8> {
+        let lines = self.0.get();
+        if lines == 0 {
+            return None;
+        }
+        Some(lines.trailing_zeros() as u8)
+    }
+}
+
+impl Cpu {
+    // handle for the devices that want to raise interrupts
+    pub fn irq_lines(&self) -> IrqLines {
+        self.irq.clone()
+    }
+
+    pub fn raise_irq(&self, line: u8) {
+        self.irq.raise(line);
+    }
+
+    pub fn lower_irq(&self, line: u8) {
+        self.irq.lower(line);
+    }
+
+    // vector used for irq line 0 (the pc bios programs the 8259 with 08h)
+    pub fn set_irq_base(&mut self, base: u8) {
+        self.irq_base = base;
+    }
+
+    pub fn raise_nmi(&mut self) {
+        self.nmi = true;
+    }
+
+    // requests an interrupt from the instruction being executed (int n, into,
+    // divide error): it is taken as soon as the instruction completes
+    pub fn raise_exception(&mut self, vector: u8) {
+        self.exception = Some(vector);
+    }
+
+    // divide error: the 8086 pushes the address of the next instruction,
+    // the 80286 the address of the faulting one. returns the ip to use
+    pub fn divide_error(&mut self, nip: u16) -> u16 {
+        self.raise_exception(INT_DIVIDE_ERROR);
+        match self.model {
+            CpuModel::I8086 => nip,
+            CpuModel::I80286 => self.read_ip(),
+        }
+    }
+
+    // interrupts are not recognized after mov ss, pop ss or sti, so that
+    // ss:sp can be loaded with two instructions
+    pub fn inhibit_interrupts(&mut self) {
+        self.int_shadow = true;
+    }
+
+    // enters an interrupt handler: flags, cs and ip are pushed, IF and TF are
+    // cleared and cs:ip is loaded from the vector table at 0000:0000
+    pub fn interrupt(&mut self, vector: u8) {
+        debug!("interrupt: vector={:02x}", vector);
+
+        let flags = self.read_flags();
+        self.push_word(flags);
+        self.clear_flag(Flags::I);
+        self.clear_flag(Flags::T);
+
+        let cs = self.read_sreg(Sreg::CS);
+        self.push_word(cs);
+        let ip = self.read_ip();
+        self.push_word(ip);
+
+        let ea = vector as MemAddrT * 4;
+        let ip = self.read_mem_ea(ea, OpSize::Word).unwrap_or(0xffff);
+        let cs = self.read_mem_ea(ea + 2, OpSize::Word).unwrap_or(0xffff);
+        self.write_sreg(Sreg::CS, cs);
+        self.write_ip(ip);
+
+        self.halted = false;
+    }
+
+    pub fn iret(&mut self) {
+        let ip = self.pop_word();
+        let cs = self.pop_word();
+        let flags = self.pop_word();
+        self.write_sreg(Sreg::CS, cs);
+        self.write_ip(ip);
+        self.write_flags(flags);
+    }
+
+    // called between two instructions, `trap` is the value TF had when the
+    // last instruction started
+    pub fn check_interrupts(&mut self, trap: bool) {
+        if let Some(vector) = self.exception.take() {
+            self.interrupt(vector);
+        }
+
+        if self.int_shadow {
+            self.int_shadow = false;
+            return;
+        }
+
+        // a repeated string instruction is interrupted between two iterations
+        let rep_resume = self.rep_resume.take();
+        let external = self.nmi || trap || (self.is_flag_set(Flags::I) && self.irq.pending().is_some());
+        if let (Some(ip), true) = (rep_resume, external) {
+            self.write_ip(ip);
+        }
+
+        if self.nmi {
+            self.nmi = false;
+            self.interrupt(INT_NMI);
+        } else if self.is_flag_set(Flags::I) {
+            if let Some(line) = self.irq.pending() {
+                self.irq.lower(line);
+                self.interrupt(self.irq_base.wrapping_add(line));
+            }
+        }
+
+        // the trap is entered last, so its handler runs first
+        if trap {
+            self.interrupt(INT_SINGLE_STEP);
+        }
+    }
+}
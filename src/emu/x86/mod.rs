@@ -5,8 +5,8 @@ use std::{cmp::Ordering, collections::HashMap, fs::File, io::Read};
 
 use tracing::debug;
 
-use crate::{inst_to_string, Result};
-use lib8086::{Arg, Cc, Decoder, Inst, Op, Reg16, Reg8, Sreg, MemAddrT, IoAddrT, OpSizeT};
+use crate::Result;
+use lib8086::{Arg, Cc, Decoder, Flags, Inst, Op, Reg16, Reg8, Sreg, MemAddrT, IoAddrT, OpSizeT};
 
 #[derive(Debug, Clone, Copy)]
 pub enum OpSize {
@@ -25,25 +25,23 @@ use io::{IOOps, IOMap};
 
 mod exec;
 mod args;
+mod alu;
+mod stack;
+mod string;
+mod shift;
+mod bcd;
+
+mod int;
+pub use int::IrqLines;
 
 mod hw;
 use hw::init_devices;
 
 mod cfg;
-pub use cfg::Config;
+pub use cfg::{Config, CpuModel};
 
-#[derive(Debug, Clone, Copy)]
-pub enum Flags {
-    C = 0,
-    P = 2,
-    A = 4,
-    Z = 6,
-    S = 7,
-    T = 8,
-    I = 9,
-    D = 10,
-    O = 11,
-}
+// CF, PF, AF, ZF, SF, TF, IF, DF and OF
+const FLAGS_MASK: u16 = 0x0fd5;
 
 #[derive(Debug, Default)]
 pub struct Regs {
@@ -71,6 +69,13 @@ pub struct Cpu {
     ip: u16,
     flags: u16,
     halted: bool,
+    model: CpuModel,
+    rep_resume: Option<u16>,
+    irq: IrqLines,
+    irq_base: u8,
+    nmi: bool,
+    exception: Option<u8>,
+    int_shadow: bool,
     io_map: IOMap,
     mem_map: MemMap,
 }
@@ -80,7 +85,9 @@ impl Cpu {
         let mut io_map = IOMap::new();
         let mut mem_map = MemMap::new();
 
-        init_devices(cfg,&mut mem_map, &mut io_map)?;
+        let irq = IrqLines::default();
+
+        init_devices(cfg,&mut mem_map, &mut io_map, &irq)?;
 
         Ok(Self {
             regs: Regs::default(),
@@ -88,6 +95,13 @@ impl Cpu {
             ip: 0,
             flags: 0,
             halted: false,
+            model: cfg.model,
+            rep_resume: None,
+            irq,
+            irq_base: 0x08,
+            nmi: false,
+            exception: None,
+            int_shadow: false,
             io_map,
             mem_map,
         })
@@ -116,7 +130,7 @@ impl Cpu {
         );
 
         println!("FL={:04X} CF={:01X} PF={:01X} AF={:01X} ZF={:01X} SF={:01X} TF={:01X} IF={:01X} DF={:01X} OF={:01X}",
-            self.flags,
+            self.read_flags(),
             self.is_flag_set(Flags::C) as u8,
             self.is_flag_set(Flags::P) as u8,
             self.is_flag_set(Flags::A) as u8,
@@ -199,13 +213,13 @@ impl Cpu {
         }
     }
 
-    // todo: later 
     pub fn calc_ea(&self, seg: Sreg, offset: u16) -> MemAddrT {
         // on 8086, we can access 1MB memory, thus we need to use 32-bit address
         let base = self.read_sreg(seg) as MemAddrT;
         let base = base.wrapping_shl(4);
         let ea = base.wrapping_add(offset as MemAddrT);
-        ea
+        // only 20 address lines: ffff:0010 wraps around to 0000:0000
+        ea & 0xf_ffff
     }
 
     pub fn read_ip(&self) -> u16 {
@@ -217,38 +231,42 @@ impl Cpu {
         self.ip = val;
     }
 
+    // the flags image as pushed or transferred: bit 1 always reads as one,
+    // and on the 8086 so do bits 12-15 (they are zero on the 80286 in real
+    // mode)
     pub fn read_flags(&self) -> u16 {
-        self.flags
+        let fixed = match self.model {
+            CpuModel::I8086 => 0xf002,
+            CpuModel::I80286 => 0x0002,
+        };
+        self.flags | fixed
     }
 
+    // only the defined flags can be changed
     pub fn write_flags(&mut self, val: u16) {
-        self.flags = val;
-    }
-
-    fn flag_mask(f: Flags) -> u16 {
-        1 << f as u16
+        self.flags = val & FLAGS_MASK;
     }
 
     pub fn set_flag(&mut self, f: Flags) {
-        let m = Self::flag_mask(f);
+        let m = f.mask();
         self.flags |= m;
         debug!("set_flag: f={:?} mask={:04x} flags={:04x}", f, m, self.flags);
     }
 
     pub fn clear_flag(&mut self, f: Flags) {
-        let m = Self::flag_mask(f);
+        let m = f.mask();
         self.flags &= !m;
         debug!("clear_flag: f={:?} mask={:04x} flags={:04x}", f, m, self.flags);
     }
 
     pub fn toggle_flag(&mut self, f: Flags) {
-        let m = Self::flag_mask(f);
+        let m = f.mask();
         self.flags ^= m;
         debug!("toggle_flag: f={:?} mask={:04x} flags={:04x}", f, m, self.flags);
     }
 
     pub fn is_flag_set(&self, f: Flags) -> bool {
-        let m = Self::flag_mask(f);
+        let m = f.mask();
         (self.flags & m) != 0
     }
 
@@ -265,17 +283,32 @@ impl Cpu {
     }
 
     pub fn read_mem(&self, seg: Sreg, off: u16, sz: OpSize) -> Option<OpSizeT> {
+        if let OpSize::Word = sz {
+            if off == 0xffff {
+                // a word at offset ffff wraps around within the segment
+                let lo = self.read_mem(seg, off, OpSize::Byte)?;
+                let hi = self.read_mem(seg, 0, OpSize::Byte)?;
+                return Some(hi << 8 | lo);
+            }
+        }
         let ea = self.calc_ea(seg, off);
         self.read_mem_ea(ea, sz)
     }
 
     pub fn write_mem(&mut self, seg: Sreg, off: u16, val: OpSizeT, sz: OpSize) {
+        if let OpSize::Word = sz {
+            if off == 0xffff {
+                self.write_mem(seg, off, val & 0xff, OpSize::Byte);
+                self.write_mem(seg, 0, val >> 8, OpSize::Byte);
+                return;
+            }
+        }
         let ea = self.calc_ea(seg, off);
         self.write_mem_ea(ea, val, sz);
     }
 
     pub fn read_io(&self, port: u16, sz: OpSize) -> OpSizeT {
-        self.io_map.read(port, sz).unwrap_or(OpSizeT::default())
+        self.io_map.read(port, sz).unwrap_or_default()
     }
 
     pub fn write_io(&mut self, port: IoAddrT, val: OpSizeT, sz: OpSize) {
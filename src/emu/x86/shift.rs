@@ -0,0 +1,97 @@
+use lib8086::{Inst, Op};
+
+use super::{Cpu, CpuModel, Flags, OpSize};
+
+impl Cpu {
+    // executes a shift or rotate: the 8086 shifts one bit at a time, as many
+    // times as the count says (cl is not masked), so CF and OF are the ones
+    // of the last step. a zero count leaves the operand and the flags alone
+    pub fn shift_op(&mut self, inst: &Inst) {
+        let (a1, a2) = match inst.op {
+            Op::Rol(a1, a2)
+            | Op::Ror(a1, a2)
+            | Op::Rcl(a1, a2)
+            | Op::Rcr(a1, a2)
+            | Op::Shl(a1, a2)
+            | Op::Shr(a1, a2)
+            | Op::Sar(a1, a2)
+            | Op::Setmo(a1, a2) => (a1, a2),
+            _ => unreachable!(),
+        };
+
+        let sz = self.arg_size(inst, &a1);
+        let mut count = self.read_arg(inst, &a2) as u8;
+        if self.model != CpuModel::I8086 {
+            count &= 0x1f;
+        }
+        if count == 0 {
+            return;
+        }
+
+        let mask = sz.mask() as u16;
+        let msb = sz.sign_bit() as u16;
+        let mut v = self.read_arg(inst, &a1);
+        let mut cf = self.is_flag_set(Flags::C);
+        let mut of = self.is_flag_set(Flags::O);
+
+        // /6 is setmo on the 8086 (the operand is set to all ones, flags as
+        // for an or) and an alias of shl on later cpus
+        if let (Op::Setmo(_, _), CpuModel::I8086) = (&inst.op, self.model) {
+            let nv = self.alu_logic(sz, mask);
+            self.write_arg(inst, &a1, nv);
+            return;
+        }
+
+        for _ in 0..count {
+            match inst.op {
+                Op::Rol(_, _) => {
+                    cf = v & msb != 0;
+                    v = (v << 1 | cf as u16) & mask;
+                    of = (v & msb != 0) != cf;
+                }
+                Op::Ror(_, _) => {
+                    cf = v & 1 != 0;
+                    v = v >> 1 | if cf { msb } else { 0 };
+                    of = (v & msb != 0) != (v & msb >> 1 != 0);
+                }
+                Op::Rcl(_, _) => {
+                    let c = cf;
+                    cf = v & msb != 0;
+                    v = (v << 1 | c as u16) & mask;
+                    of = (v & msb != 0) != cf;
+                }
+                Op::Rcr(_, _) => {
+                    let c = cf;
+                    cf = v & 1 != 0;
+                    v = v >> 1 | if c { msb } else { 0 };
+                    of = (v & msb != 0) != (v & msb >> 1 != 0);
+                }
+                Op::Shl(_, _) | Op::Setmo(_, _) => {
+                    cf = v & msb != 0;
+                    v = (v << 1) & mask;
+                    of = (v & msb != 0) != cf;
+                }
+                Op::Shr(_, _) => {
+                    cf = v & 1 != 0;
+                    of = v & msb != 0;
+                    v >>= 1;
+                }
+                Op::Sar(_, _) => {
+                    cf = v & 1 != 0;
+                    of = false;
+                    v = v >> 1 | (v & msb);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        self.update_flag(Flags::C, cf);
+        self.update_flag(Flags::O, of);
+        // rotates only touch CF and OF, AF is undefined for shifts (cleared)
+        if !matches!(inst.op, Op::Rol(_, _) | Op::Ror(_, _) | Op::Rcl(_, _) | Op::Rcr(_, _)) {
+            self.clear_flag(Flags::A);
+            self.set_szp_flags(sz, v);
+        }
+        self.write_arg(inst, &a1, v);
+    }
+}
@@ -0,0 +1,33 @@
+use lib8086::Reg16;
+
+use tracing::trace;
+
+use super::{Cpu, OpSize, Sreg};
+
+impl Cpu {
+    // the stack grows down: sp is decremented first, then the word is written
+    // at ss:sp (sp wraps around within the stack segment)
+    pub fn push_word(&mut self, val: u16) {
+        let sp = self.read_reg16(Reg16::SP).wrapping_sub(2);
+        self.write_reg16(Reg16::SP, sp);
+        self.write_mem(Sreg::SS, sp, val, OpSize::Word);
+    }
+
+    // the word is read at ss:sp, then sp is incremented
+    pub fn pop_word(&mut self) -> u16 {
+        let sp = self.read_reg16(Reg16::SP);
+        let val = self.read_mem(Sreg::SS, sp, OpSize::Word).unwrap_or(0xffff);
+        self.write_reg16(Reg16::SP, sp.wrapping_add(2));
+        val
+    }
+
+    // pushes the return address of a far call and switches to the new code
+    // segment, the caller sets ip
+    pub fn far_call(&mut self, seg: u16, nip: u16) {
+        let cs = self.read_sreg(Sreg::CS);
+        self.push_word(cs);
+        self.push_word(nip);
+        self.write_sreg(Sreg::CS, seg);
+        trace!(" - CALLF {:04x} [ret={:04x}:{:04x}]", seg, cs, nip);
+    }
+}
@@ -0,0 +1,95 @@
+use lib8086::{Inst, Op, Reg16, Reg8, Rep};
+
+use super::{CpuModel, Cpu, Flags, OpSize, Sreg};
+
+impl Cpu {
+    // executes one iteration of a string instruction, returns true if the
+    // instruction has to be executed again (rep prefix and cx not exhausted)
+    pub fn string_op(&mut self, inst: &Inst) -> bool {
+        if inst.rep.is_some() && self.read_reg16(Reg16::CX) == 0 {
+            return false;
+        }
+
+        let sz: OpSize = inst.width.into();
+        let step = match sz {
+            OpSize::Byte => 1u16,
+            OpSize::Word => 2u16,
+        };
+        let step = if self.is_flag_set(Flags::D) {
+            step.wrapping_neg()
+        } else {
+            step
+        };
+
+        // the source can be overridden, the destination is always es:di
+        let src = inst.seg.unwrap_or(Sreg::DS);
+        let si = self.read_reg16(Reg16::SI);
+        let di = self.read_reg16(Reg16::DI);
+
+        match inst.op {
+            Op::Movs => {
+                let v = self.read_mem(src, si, sz).unwrap_or(0xffff);
+                self.write_mem(Sreg::ES, di, v, sz);
+                self.write_reg16(Reg16::SI, si.wrapping_add(step));
+                self.write_reg16(Reg16::DI, di.wrapping_add(step));
+            }
+            Op::Cmps => {
+                let v1 = self.read_mem(src, si, sz).unwrap_or(0xffff);
+                let v2 = self.read_mem(Sreg::ES, di, sz).unwrap_or(0xffff);
+                self.alu_sub(sz, v1, v2, false);
+                self.write_reg16(Reg16::SI, si.wrapping_add(step));
+                self.write_reg16(Reg16::DI, di.wrapping_add(step));
+            }
+            Op::Scas => {
+                let acc = self.read_reg16(Reg16::AX);
+                let v = self.read_mem(Sreg::ES, di, sz).unwrap_or(0xffff);
+                self.alu_sub(sz, acc, v, false);
+                self.write_reg16(Reg16::DI, di.wrapping_add(step));
+            }
+            Op::Lods => {
+                let v = self.read_mem(src, si, sz).unwrap_or(0xffff);
+                match sz {
+                    OpSize::Byte => self.write_reg8(Reg8::AL, v as u8),
+                    OpSize::Word => self.write_reg16(Reg16::AX, v),
+                }
+                self.write_reg16(Reg16::SI, si.wrapping_add(step));
+            }
+            Op::Stos => {
+                let acc = self.read_reg16(Reg16::AX);
+                self.write_mem(Sreg::ES, di, acc, sz);
+                self.write_reg16(Reg16::DI, di.wrapping_add(step));
+            }
+            _ => unreachable!("not a string operation: {:?}", inst.op),
+        }
+
+        let Some(rep) = inst.rep else {
+            return false;
+        };
+
+        let cx = self.read_reg16(Reg16::CX).wrapping_sub(1);
+        self.write_reg16(Reg16::CX, cx);
+        if cx == 0 {
+            return false;
+        }
+
+        // only cmps and scas look at ZF: repe stops when ZF is cleared, and
+        // repne when ZF is set
+        if let Op::Cmps | Op::Scas = inst.op {
+            let zf = self.is_flag_set(Flags::Z);
+            match rep {
+                Rep::Rep if !zf => return false,
+                Rep::Repne if zf => return false,
+                _ => (),
+            }
+        }
+
+        // the 8086 only remembers the prefix just before the opcode when it
+        // is interrupted: on return, the other prefixes are lost
+        if self.model == CpuModel::I8086 {
+            let prefixes = inst.lock as u16 + inst.rep.is_some() as u16 + inst.seg.is_some() as u16;
+            self.rep_resume = Some(self.read_ip().wrapping_add(prefixes - 1));
+        }
+
+        true
+    }
+}
@@ -1,58 +1,73 @@
 // use tracing::debug;
 
-use crate::op::{Arg, Cc, Inst, Invalid, Op, Reg16, Reg8, Rep, Sreg};
+use crate::op::{Arg, Cc, Inst, Invalid, Mem, Op, Reg16, Reg8, Rep, Sreg, Width};
 
-pub struct Decoder<'a> {
+// reasons why no instruction could be decoded. undefined opcodes are not
+// errors, they decode to Op::Invalid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeError {
+    // there is no byte left where an instruction starts
+    EndOfInput,
+    // the input stops in the middle of an instruction, after this many bytes
+    Truncated(usize),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::EndOfInput => write!(f, "end of input"),
+            DecodeError::Truncated(n) => write!(f, "truncated instruction after {} bytes", n),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// longest possible instruction: three prefixes, opcode, modrm, a 16 bit
+// displacement and a 16 bit immediate
+pub const MAX_INST_LEN: usize = 9;
+
+pub struct Decoder<I: Iterator<Item = u8>> {
     sreg: Option<Sreg>,
     rep: Option<Rep>,
     size: usize,
     lock: bool,
-    line: &'a mut dyn Iterator<Item = u8>,
+    width: Width,
+    line: I,
 }
 
-impl<'a> Decoder<'a> {
-    pub fn new(line: &'a mut dyn Iterator<Item = u8>) -> Decoder<'a> {
+impl<I: Iterator<Item = u8>> Decoder<I> {
+    pub fn new(line: I) -> Decoder<I> {
         Decoder {
             sreg: None,
             rep: None,
             size: 0,
             lock: false,
+            width: Width::Byte,
             line,
         }
     }
 }
 
-fn modrm8(b: u8) -> (Arg, Arg) {
-    let modrm = b >> 6;
-    let reg = (b >> 3) & 0x7;
-    let rm = b & 0x7;
-    let arg1 = match modrm {
-        0 => Arg::Reg8(From::from(rm)),
-        1 => unimplemented!("modrm8 1"),
-        2 => unimplemented!("modrm8 2"),
-        3 => Arg::Reg8(From::from(rm)),
-        _ => unreachable!(),
-    };
-    let arg2 = Arg::Reg8(From::from(reg));
-    (arg1, arg2)
-}
-
-fn modrm16(b: u8) -> (Arg, Arg) {
-    let modrm = b >> 6;
-    let reg = (b >> 3) & 0x7;
-    let rm = b & 0x7;
-    let arg1 = match modrm {
-        0 => Arg::Reg16(From::from(rm)),
-        1 => unimplemented!("modrm16 1"),
-        2 => unimplemented!("modrm16 2"),
-        3 => Arg::Reg16(From::from(rm)),
-        _ => unreachable!(),
+// operand size of an opcode: most of the time this is the w bit (bit 0),
+// except for the few opcodes that can only work on words or where w is bit 3
+fn opcode_width(b0: u8) -> Width {
+    let w = match b0 {
+        0x40..=0x5f => true, // inc, dec, push, pop r16
+        0x8c..=0x8f => true, // mov sreg, lea, pop r/m16
+        0x90..=0x9f => true, // xchg, cbw, cwd, call far, pushf, ...
+        0xb0..=0xbf => b0 & 0x8 != 0, // mov reg, imm
+        0xc4 | 0xc5 => true, // les, lds
+        _ => b0 & 0x1 != 0,
     };
-    let arg2 = Arg::Reg16(From::from(reg));
-    (arg1, arg2)
+    if w {
+        Width::Word
+    } else {
+        Width::Byte
+    }
 }
 
-impl<'a> Decoder<'a> {
+impl<I: Iterator<Item = u8>> Decoder<I> {
     fn nextb(&mut self) -> Option<u8> {
         let n = self.line.next();
         if n.is_some() {
@@ -67,26 +82,81 @@ impl<'a> Decoder<'a> {
         Some((b2 as u16) << 8 | b1 as u16)
     }
 
+    // decodes the r/m part of a modrm byte (reading the displacement, if any)
+    fn modrm_mem(&mut self, modrm: u8, rm: u8) -> Option<Mem> {
+        let (base, index) = match rm {
+            0 => (Reg16::BX, Some(Reg16::SI)),
+            1 => (Reg16::BX, Some(Reg16::DI)),
+            2 => (Reg16::BP, Some(Reg16::SI)),
+            3 => (Reg16::BP, Some(Reg16::DI)),
+            4 => (Reg16::SI, None),
+            5 => (Reg16::DI, None),
+            6 => (Reg16::BP, None),
+            7 => (Reg16::BX, None),
+            _ => unreachable!(),
+        };
+        let mem = match (modrm, index) {
+            // mod=0, rm=6 is not [bp] but a direct address
+            (0, None) if rm == 6 => Mem::Direct(self.nextw()?),
+            (0, None) => Mem::Reg(base),
+            (0, Some(index)) => Mem::RegReg(base, index),
+            (1, None) => Mem::RegOff8(base, self.nextb()? as i8),
+            (1, Some(index)) => Mem::RegRegOff8(base, index, self.nextb()? as i8),
+            (2, None) => Mem::RegOff16(base, self.nextw()? as i16),
+            (2, Some(index)) => Mem::RegRegOff16(base, index, self.nextw()? as i16),
+            _ => unreachable!(),
+        };
+        Some(mem)
+    }
+
+    // returns (r/m, reg) for a byte operation
+    fn modrm8(&mut self, b: u8) -> Option<(Arg, Arg)> {
+        let modrm = b >> 6;
+        let reg = (b >> 3) & 0x7;
+        let rm = b & 0x7;
+        let arg1 = match modrm {
+            0..=2 => Arg::Mem(self.modrm_mem(modrm, rm)?),
+            3 => Arg::Reg8(From::from(rm)),
+            _ => unreachable!(),
+        };
+        let arg2 = Arg::Reg8(From::from(reg));
+        Some((arg1, arg2))
+    }
+
+    // returns (r/m, reg) for a word operation
+    fn modrm16(&mut self, b: u8) -> Option<(Arg, Arg)> {
+        let modrm = b >> 6;
+        let reg = (b >> 3) & 0x7;
+        let rm = b & 0x7;
+        let arg1 = match modrm {
+            0..=2 => Arg::Mem(self.modrm_mem(modrm, rm)?),
+            3 => Arg::Reg16(From::from(rm)),
+            _ => unreachable!(),
+        };
+        let arg2 = Arg::Reg16(From::from(reg));
+        Some((arg1, arg2))
+    }
+
     fn next_0(&mut self, b0: u8) -> Option<Op> {
         match b0 & 0xf {
             0x0 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Add(a0, a1))
             }
             0x1 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Add(a0, a1))
             }
             0x2 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Add(a1, a0))
             }
             0x3 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Add(a1, a0))
             }
             0x4 => {
@@ -102,22 +172,22 @@ impl<'a> Decoder<'a> {
 
             0x8 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Or(a0, a1))
             }
             0x9 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
-                Some(Op::Or(a1, a0))
+                let (a0, a1) = self.modrm16(b1)?;
+                Some(Op::Or(a0, a1))
             }
             0xa => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
-                Some(Op::Or(a0, a1))
+                let (a0, a1) = self.modrm8(b1)?;
+                Some(Op::Or(a1, a0))
             }
             0xb => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Or(a1, a0))
             }
             0xc => {
@@ -139,22 +209,22 @@ impl<'a> Decoder<'a> {
         match b0 & 0xf {
             0x0 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Adc(a0, a1))
             }
             0x1 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
-                Some(Op::Adc(a1, a0))
+                let (a0, a1) = self.modrm16(b1)?;
+                Some(Op::Adc(a0, a1))
             }
             0x2 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
-                Some(Op::Adc(a0, a1))
+                let (a0, a1) = self.modrm8(b1)?;
+                Some(Op::Adc(a1, a0))
             }
             0x3 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Adc(a1, a0))
             }
             0x4 => {
@@ -170,22 +240,22 @@ impl<'a> Decoder<'a> {
 
             0x8 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Sbb(a0, a1))
             }
             0x9 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
-                Some(Op::Sbb(a1, a0))
+                let (a0, a1) = self.modrm16(b1)?;
+                Some(Op::Sbb(a0, a1))
             }
             0xa => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
-                Some(Op::Sbb(a0, a1))
+                let (a0, a1) = self.modrm8(b1)?;
+                Some(Op::Sbb(a1, a0))
             }
             0xb => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Sbb(a1, a0))
             }
             0xc => {
@@ -207,22 +277,22 @@ impl<'a> Decoder<'a> {
         match b0 & 0xf {
             0x0 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::And(a0, a1))
             }
             0x1 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
-                Some(Op::And(a1, a0))
+                let (a0, a1) = self.modrm16(b1)?;
+                Some(Op::And(a0, a1))
             }
             0x2 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
-                Some(Op::And(a0, a1))
+                let (a0, a1) = self.modrm8(b1)?;
+                Some(Op::And(a1, a0))
             }
             0x3 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::And(a1, a0))
             }
             0x4 => {
@@ -234,7 +304,7 @@ impl<'a> Decoder<'a> {
                 Some(Op::And(Arg::Reg16(Reg16::AX), Arg::Uimm16(w)))
             }
             0x6 => {
-                if self.sreg == Some(Sreg::ES) {
+                if self.sreg.is_some() {
                     return Some(Op::Invalid(Invalid::TooManyPrefix));
                 }
                 self.sreg = Some(Sreg::ES);
@@ -244,22 +314,22 @@ impl<'a> Decoder<'a> {
 
             0x8 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Sub(a0, a1))
             }
             0x9 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
-                Some(Op::Sub(a1, a0))
+                let (a0, a1) = self.modrm16(b1)?;
+                Some(Op::Sub(a0, a1))
             }
             0xa => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
-                Some(Op::Sub(a0, a1))
+                let (a0, a1) = self.modrm8(b1)?;
+                Some(Op::Sub(a1, a0))
             }
             0xb => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Sub(a1, a0))
             }
             0xc => {
@@ -271,7 +341,7 @@ impl<'a> Decoder<'a> {
                 Some(Op::Sub(Arg::Reg16(Reg16::AX), Arg::Uimm16(w)))
             }
             0xe => {
-                if self.sreg == Some(Sreg::CS) {
+                if self.sreg.is_some() {
                     return Some(Op::Invalid(Invalid::TooManyPrefix));
                 }
                 self.sreg = Some(Sreg::CS);
@@ -287,22 +357,22 @@ impl<'a> Decoder<'a> {
         match b0 & 0xf {
             0x0 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Xor(a0, a1))
             }
             0x1 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
-                Some(Op::Xor(a1, a0))
+                let (a0, a1) = self.modrm16(b1)?;
+                Some(Op::Xor(a0, a1))
             }
             0x2 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
-                Some(Op::Xor(a0, a1))
+                let (a0, a1) = self.modrm8(b1)?;
+                Some(Op::Xor(a1, a0))
             }
             0x3 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Xor(a1, a0))
             }
             0x4 => {
@@ -314,7 +384,7 @@ impl<'a> Decoder<'a> {
                 Some(Op::Xor(Arg::Reg16(Reg16::AX), Arg::Uimm16(w)))
             }
             0x6 => {
-                if self.sreg == Some(Sreg::SS) {
+                if self.sreg.is_some() {
                     return Some(Op::Invalid(Invalid::TooManyPrefix));
                 }
                 self.sreg = Some(Sreg::SS);
@@ -324,22 +394,22 @@ impl<'a> Decoder<'a> {
 
             0x8 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Cmp(a0, a1))
             }
             0x9 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
-                Some(Op::Cmp(a1, a0))
+                let (a0, a1) = self.modrm16(b1)?;
+                Some(Op::Cmp(a0, a1))
             }
             0xa => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
-                Some(Op::Cmp(a0, a1))
+                let (a0, a1) = self.modrm8(b1)?;
+                Some(Op::Cmp(a1, a0))
             }
             0xb => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Cmp(a1, a0))
             }
             0xc => {
@@ -351,7 +421,7 @@ impl<'a> Decoder<'a> {
                 Some(Op::Cmp(Arg::Reg16(Reg16::AX), Arg::Uimm16(w)))
             }
             0xe => {
-                if self.sreg == Some(Sreg::DS) {
+                if self.sreg.is_some() {
                     return Some(Op::Invalid(Invalid::TooManyPrefix));
                 }
                 self.sreg = Some(Sreg::DS);
@@ -365,7 +435,7 @@ impl<'a> Decoder<'a> {
 
     fn next_4(&mut self, b0: u8) -> Option<Op> {
         match b0 & 0xf {
-            0x0..=0x7 => Some(Op::Inc(Arg::Reg16(From::from(b0)))),
+            0x0..=0x7 => Some(Op::Inc(Arg::Reg16(From::from(b0 & 0x7)))),
             0x8..=0xf => Some(Op::Dec(Arg::Reg16(From::from(b0 & 0x7)))),
             _ => unreachable!(),
         }
@@ -396,39 +466,23 @@ impl<'a> Decoder<'a> {
         match b0 & 0xf {
             0x0 => {
                 let b1 = self.nextb()?;
-                let (a0, _) = modrm16(b1);
-                let ww = self.nextw()?;
+                let (a0, _) = self.modrm8(b1)?;
+                let b = self.nextb()?;
                 match (b1 >> 3) & 0x7 {
-                    0b000 => Some(Op::Add(a0, Arg::Uimm16(ww))),
-                    0b001 => Some(Op::Or(a0, Arg::Uimm16(ww))),
-                    0b010 => Some(Op::Adc(a0, Arg::Uimm16(ww))),
-                    0b011 => Some(Op::Sbb(a0, Arg::Uimm16(ww))),
-                    0b100 => Some(Op::And(a0, Arg::Uimm16(ww))),
-                    0b101 => Some(Op::Sub(a0, Arg::Uimm16(ww))),
-                    0b110 => Some(Op::Xor(a0, Arg::Uimm16(ww))),
-                    0b111 => Some(Op::Cmp(a0, Arg::Uimm16(ww))),
+                    0b000 => Some(Op::Add(a0, Arg::Uimm8(b))),
+                    0b001 => Some(Op::Or(a0, Arg::Uimm8(b))),
+                    0b010 => Some(Op::Adc(a0, Arg::Uimm8(b))),
+                    0b011 => Some(Op::Sbb(a0, Arg::Uimm8(b))),
+                    0b100 => Some(Op::And(a0, Arg::Uimm8(b))),
+                    0b101 => Some(Op::Sub(a0, Arg::Uimm8(b))),
+                    0b110 => Some(Op::Xor(a0, Arg::Uimm8(b))),
+                    0b111 => Some(Op::Cmp(a0, Arg::Uimm8(b))),
                     _ => unreachable!(),
                 }
             }
             0x1 => {
                 let b1 = self.nextb()?;
-                let (a0, _) = modrm16(b1);
-                let b = self.nextb()? as i8;
-                match (b1 >> 3) & 0x7 {
-                    0b000 => Some(Op::Add(a0, Arg::Imm8(b))),
-                    0b001 => Some(Op::Or(a0, Arg::Imm8(b))),
-                    0b010 => Some(Op::Adc(a0, Arg::Imm8(b))),
-                    0b011 => Some(Op::Sbb(a0, Arg::Imm8(b))),
-                    0b100 => Some(Op::And(a0, Arg::Imm8(b))),
-                    0b101 => Some(Op::Sub(a0, Arg::Imm8(b))),
-                    0b110 => Some(Op::Xor(a0, Arg::Imm8(b))),
-                    0b111 => Some(Op::Cmp(a0, Arg::Imm8(b))),
-                    _ => unreachable!(),
-                }
-            }
-            0x2 => {
-                let b1 = self.nextb()?;
-                let (a0, _) = modrm16(b1);
+                let (a0, _) = self.modrm16(b1)?;
                 let ww = self.nextw()?;
                 match (b1 >> 3) & 0x7 {
                     0b000 => Some(Op::Add(a0, Arg::Uimm16(ww))),
@@ -442,9 +496,26 @@ impl<'a> Decoder<'a> {
                     _ => unreachable!(),
                 }
             }
+            0x2 => {
+                // 0x82 -> same as 0x80
+                let b1 = self.nextb()?;
+                let (a0, _) = self.modrm8(b1)?;
+                let b = self.nextb()?;
+                match (b1 >> 3) & 0x7 {
+                    0b000 => Some(Op::Add(a0, Arg::Uimm8(b))),
+                    0b001 => Some(Op::Or(a0, Arg::Uimm8(b))),
+                    0b010 => Some(Op::Adc(a0, Arg::Uimm8(b))),
+                    0b011 => Some(Op::Sbb(a0, Arg::Uimm8(b))),
+                    0b100 => Some(Op::And(a0, Arg::Uimm8(b))),
+                    0b101 => Some(Op::Sub(a0, Arg::Uimm8(b))),
+                    0b110 => Some(Op::Xor(a0, Arg::Uimm8(b))),
+                    0b111 => Some(Op::Cmp(a0, Arg::Uimm8(b))),
+                    _ => unreachable!(),
+                }
+            }
             0x3 => {
                 let b1 = self.nextb()?;
-                let (a0, _) = modrm16(b1);
+                let (a0, _) = self.modrm16(b1)?;
                 let b = self.nextb()? as i8;
                 match (b1 >> 3) & 0x7 {
                     0b000 => Some(Op::Add(a0, Arg::Imm8(b))),
@@ -460,67 +531,78 @@ impl<'a> Decoder<'a> {
             }
             0x4 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Test(a0, a1))
             }
             0x5 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Test(a0, a1))
             }
             0x6 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Xchg(a0, a1))
             }
             0x7 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Xchg(a0, a1))
             }
 
             0x8 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Mov(a0, a1))
             }
             0x9 => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Mov(a0, a1))
             }
             0xa => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm8(b1);
+                let (a0, a1) = self.modrm8(b1)?;
                 Some(Op::Mov(a1, a0))
             }
             0xb => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
+                let (a0, a1) = self.modrm16(b1)?;
                 Some(Op::Mov(a1, a0))
             }
 
-            0xc => Some(Op::Invalid(Invalid::UnexpectedByte(b0))),
+            0xc => {
+                let b1 = self.nextb()?;
+                let (a0, _) = self.modrm16(b1)?;
+                let sr = (b1 >> 3) & 0x7;
+                if sr & 0b100 != 0 {
+                    return Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1)));
+                }
+                Some(Op::Mov(a0, Arg::Sreg(Sreg::from(sr))))
+            }
             0xd => {
                 let b1 = self.nextb()?;
-                let (a0, a1) = modrm16(b1);
-                Some(Op::Lea(a0, a1))
+                if b1 >> 6 == 0b11 {
+                    // lea needs a memory operand
+                    return Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1)));
+                }
+                let (a0, a1) = self.modrm16(b1)?;
+                Some(Op::Lea(a1, a0))
             }
             0xe => {
                 let b1 = self.nextb()?;
-                let (a0, _) = modrm16(b1);
-                let sr = b1 >> 3;
+                let (a0, _) = self.modrm16(b1)?;
+                let sr = (b1 >> 3) & 0x7;
                 if sr & 0b100 != 0 {
                     return Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1)));
                 }
-                let sr = sr % 0b11;
                 Some(Op::Mov(Arg::Sreg(Sreg::from(sr)), a0))
             }
             0xf => {
                 let b1 = self.nextb()?;
-                let (_, a1) = modrm16(b1);
+                let (a0, _) = self.modrm16(b1)?;
                 match (b1 >> 3) & 0x7 {
-                    0b000 => Some(Op::Pop(a1)),
+                    0b000 => Some(Op::Pop(a0)),
                     _ => Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1))),
                 }
             }
@@ -532,22 +614,61 @@ impl<'a> Decoder<'a> {
     fn next_9(&mut self, b0: u8) -> Option<Op> {
         match b0 & 0xf {
             0x0 => Some(Op::Nop), // xchg ax, ax
-            0x1..=0x7 => unimplemented!("0x{:02} (XCHG)", b0),
+            0x1..=0x7 => Some(Op::Xchg(Arg::Reg16(Reg16::AX), Arg::Reg16(From::from(b0 & 0x7)))),
             0x8 => Some(Op::Cbw),
             0x9 => Some(Op::Cwd),
-            0xA => unimplemented!("Call Ap"),
-            0xB => unimplemented!("Op::Wait"),
-            0xC => unimplemented!("Op::Pushf"),
-            0xD => unimplemented!("Op::Popf"),
-            0xE => unimplemented!("Op::Sahf"),
-            0xF => unimplemented!("Op::Lahf"),
+            0xa => {
+                // 9a -> call far
+                let w1 = self.nextw()?;
+                let w2 = self.nextw()?;
+                Some(Op::CallFar(Arg::Uimm16(w2), Arg::Uimm16(w1)))
+            }
+            0xb => Some(Op::Wait),
+            0xc => Some(Op::Pushf),
+            0xd => Some(Op::Popf),
+            0xe => Some(Op::Sahf),
+            0xf => Some(Op::Lahf),
             _ => unreachable!(),
         }
     }
 
     fn next_a(&mut self, b0: u8) -> Option<Op> {
         match b0 & 0xf {
-            0x0..=0xf => unimplemented!("0x{:02x}", b0),
+            0x0 => {
+                // a0 -> mov al, moffs8
+                let w1 = self.nextw()?;
+                Some(Op::Mov(Arg::Reg8(Reg8::AL), Arg::Mem(Mem::Direct(w1))))
+            }
+            0x1 => {
+                // a1 -> mov ax, moffs16
+                let w1 = self.nextw()?;
+                Some(Op::Mov(Arg::Reg16(Reg16::AX), Arg::Mem(Mem::Direct(w1))))
+            }
+            0x2 => {
+                // a2 -> mov moffs8, al
+                let w1 = self.nextw()?;
+                Some(Op::Mov(Arg::Mem(Mem::Direct(w1)), Arg::Reg8(Reg8::AL)))
+            }
+            0x3 => {
+                // a3 -> mov moffs16, ax
+                let w1 = self.nextw()?;
+                Some(Op::Mov(Arg::Mem(Mem::Direct(w1)), Arg::Reg16(Reg16::AX)))
+            }
+            0x4 | 0x5 => Some(Op::Movs),
+            0x6 | 0x7 => Some(Op::Cmps),
+            0x8 => {
+                // a8 -> test al, imm8
+                let b1 = self.nextb()?;
+                Some(Op::Test(Arg::Reg8(Reg8::AL), Arg::Uimm8(b1)))
+            }
+            0x9 => {
+                // a9 -> test ax, imm16
+                let w1 = self.nextw()?;
+                Some(Op::Test(Arg::Reg16(Reg16::AX), Arg::Uimm16(w1)))
+            }
+            0xa | 0xb => Some(Op::Stos),
+            0xc | 0xd => Some(Op::Lods),
+            0xe | 0xf => Some(Op::Scas),
             _ => unreachable!(),
         }
     }
@@ -567,41 +688,103 @@ impl<'a> Decoder<'a> {
 
     fn next_c(&mut self, b0: u8) -> Option<Op> {
         match b0 & 0xf {
-            0x0 => {
-                // 0xc0 ->
-                unimplemented!("0x{:02x}", b0)
-            }
-            0x1 => {
-                // 0xc1 -> ret
-                unimplemented!("0x{:02x}", b0)
+            0x0 | 0x1 => {
+                // 0xc0, 0xc1 -> shift imm8 (80186+)
+                Some(Op::Invalid(Invalid::UnexpectedByte(b0)))
             }
             0x2 => {
-                // 0xc2 -> ret
-                unimplemented!("0x{:02x}", b0)
+                // 0xc2 -> ret imm16
+                let w1 = self.nextw()?;
+                Some(Op::RetImm(w1))
             }
             0x3 => {
                 // 0xc3 -> ret
                 Some(Op::Ret)
             }
-            0x4..=0xf => unimplemented!("0x{:02x}", b0),
+            0x4 | 0x5 => {
+                // 0xc4 -> les r16, m16:16, 0xc5 -> lds r16, m16:16
+                let b1 = self.nextb()?;
+                let (a0, a1) = self.modrm16(b1)?;
+                if b1 >> 6 == 0b11 {
+                    return Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1)));
+                }
+                if b0 == 0xc4 {
+                    Some(Op::Les(a1, a0))
+                } else {
+                    Some(Op::Lds(a1, a0))
+                }
+            }
+            0x6 => {
+                // 0xc6 -> mov r/m8, imm8
+                let b1 = self.nextb()?;
+                let (a0, _) = self.modrm8(b1)?;
+                let b2 = self.nextb()?;
+                match (b1 >> 3) & 0x7 {
+                    0b000 => Some(Op::Mov(a0, Arg::Uimm8(b2))),
+                    _ => Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1))),
+                }
+            }
+            0x7 => {
+                // 0xc7 -> mov r/m16, imm16
+                let b1 = self.nextb()?;
+                let (a0, _) = self.modrm16(b1)?;
+                let w1 = self.nextw()?;
+                match (b1 >> 3) & 0x7 {
+                    0b000 => Some(Op::Mov(a0, Arg::Uimm16(w1))),
+                    _ => Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1))),
+                }
+            }
+            0x8 | 0x9 => {
+                // 0xc8, 0xc9 -> enter, leave (80186+)
+                Some(Op::Invalid(Invalid::UnexpectedByte(b0)))
+            }
+            0xa => {
+                // 0xca -> retf imm16
+                let w1 = self.nextw()?;
+                Some(Op::RetfImm(w1))
+            }
+            0xb => Some(Op::Retf),
+            0xc => Some(Op::Int3),
+            0xd => {
+                // 0xcd -> int imm8
+                let b1 = self.nextb()?;
+                Some(Op::Int(b1))
+            }
+            0xe => Some(Op::Into),
+            0xf => Some(Op::Iret),
+            _ => unreachable!(),
+        }
+    }
+
+    // grp2: d0 -> r/m8, 1 ; d1 -> r/m16, 1 ; d2 -> r/m8, cl ; d3 -> r/m16, cl
+    fn next_shift(&mut self, b0: u8) -> Option<Op> {
+        let b1 = self.nextb()?;
+        let (a0, _) = if b0 & 1 == 0 {
+            self.modrm8(b1)?
+        } else {
+            self.modrm16(b1)?
+        };
+        let cnt = if b0 & 2 == 0 {
+            Arg::Uimm8(1)
+        } else {
+            Arg::Reg8(Reg8::CL)
+        };
+        match (b1 >> 3) & 0x7 {
+            0b000 => Some(Op::Rol(a0, cnt)),
+            0b001 => Some(Op::Ror(a0, cnt)),
+            0b010 => Some(Op::Rcl(a0, cnt)),
+            0b011 => Some(Op::Rcr(a0, cnt)),
+            0b100 => Some(Op::Shl(a0, cnt)),
+            0b101 => Some(Op::Shr(a0, cnt)),
+            0b110 => Some(Op::Setmo(a0, cnt)),
+            0b111 => Some(Op::Sar(a0, cnt)),
             _ => unreachable!(),
         }
     }
 
     fn next_d(&mut self, b0: u8) -> Option<Op> {
         match b0 & 0xf {
-            0x0 => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0x1 => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0x2 => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0x3 => {
-                unimplemented!("0x{:02x}", b0)
-            }
+            0x0..=0x3 => self.next_shift(b0),
             0x4 => {
                 // 0xd4 -> aam
                 let b1 = self.nextb()?;
@@ -613,34 +796,16 @@ impl<'a> Decoder<'a> {
                 Some(Op::Aad(b1))
             }
             0x6 => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0x7 => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0x8 => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0x9 => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0xa => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0xb => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0xc => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0xd => {
-                unimplemented!("0x{:02x}", b0)
-            }
-            0xe => {
-                unimplemented!("0x{:02x}", b0)
+                // 0xd6 -> salc (undocumented)
+                Some(Op::Invalid(Invalid::UnexpectedByte(b0)))
             }
-            0xf => {
-                unimplemented!("0x{:02x}", b0)
+            0x7 => Some(Op::Xlat),
+            0x8..=0xf => {
+                // 0xd8..0xdf -> esc (8087 opcodes)
+                let b1 = self.nextb()?;
+                let (a0, _) = self.modrm16(b1)?;
+                let code = (b0 & 0x7) << 3 | (b1 >> 3) & 0x7;
+                Some(Op::Esc(code, a0))
             }
             _ => unreachable!(),
         }
@@ -648,39 +813,99 @@ impl<'a> Decoder<'a> {
 
     fn next_e(&mut self, b0: u8) -> Option<Op> {
         match b0 & 0xf {
-            0x0 => unimplemented!("0x{:02x}", b0),
-            0x1 => unimplemented!("0x{:02x}", b0),
-            0x2 => unimplemented!("0x{:02x}", b0),
-            0x3 => unimplemented!("0x{:02x}", b0),
+            0x0 => {
+                // e0 -> loopnz rel8
+                let b1 = self.nextb()? as i8;
+                Some(Op::Loopnz(b1))
+            }
+            0x1 => {
+                // e1 -> loopz rel8
+                let b1 = self.nextb()? as i8;
+                Some(Op::Loopz(b1))
+            }
+            0x2 => {
+                // e2 -> loop rel8
+                let b1 = self.nextb()? as i8;
+                Some(Op::Loop(b1))
+            }
+            0x3 => {
+                // e3 -> jcxz rel8
+                let b1 = self.nextb()? as i8;
+                Some(Op::Jcxz(b1))
+            }
             0x4 => {
                 // e4 -> in al, imm8
                 let b1 = self.nextb()?;
                 Some(Op::In(Arg::Reg8(Reg8::AL), Arg::Uimm8(b1)))
             }
-            0x5 => unimplemented!("0x{:02x}", b0),
+            0x5 => {
+                // e5 -> in ax, imm8
+                let b1 = self.nextb()?;
+                Some(Op::In(Arg::Reg16(Reg16::AX), Arg::Uimm8(b1)))
+            }
             0x6 => {
                 // e6 -> out imm8, al
                 let b1 = self.nextb()?;
                 Some(Op::Out(Arg::Uimm8(b1), Arg::Reg8(Reg8::AL)))
             }
-            0x7 => unimplemented!("0x{:02x}", b0),
+            0x7 => {
+                // e7 -> out imm8, ax
+                let b1 = self.nextb()?;
+                Some(Op::Out(Arg::Uimm8(b1), Arg::Reg16(Reg16::AX)))
+            }
             0x8 => {
                 // e8 => CALL rel16
                 let w1 = self.nextw()? as i16;
                 Some(Op::Call(Arg::Imm16(w1)))
             }
-            0x9 => unimplemented!("0x{:02x}", b0),
+            0x9 => {
+                // e9 -> jmp rel16
+                let w1 = self.nextw()? as i16;
+                Some(Op::Jmp(Arg::Imm16(w1)))
+            }
             0xa => {
                 // ea -> jmp far
                 let w1 = self.nextw()?;
                 let w2 = self.nextw()?;
                 Some(Op::JmpFar(Arg::Uimm16(w2), Arg::Uimm16(w1)))
             }
-            0xb => unimplemented!("0x{:02x}", b0),
-            0xc => unimplemented!("0x{:02x}", b0),
-            0xd => unimplemented!("0x{:02x}", b0),
-            0xe => unimplemented!("0x{:02x}", b0),
-            0xf => unimplemented!("0x{:02x}", b0),
+            0xb => {
+                // eb -> jmp rel8
+                let b1 = self.nextb()? as i8;
+                Some(Op::Jmp(Arg::Imm8(b1)))
+            }
+            0xc => Some(Op::In(Arg::Reg8(Reg8::AL), Arg::Reg16(Reg16::DX))),
+            0xd => Some(Op::In(Arg::Reg16(Reg16::AX), Arg::Reg16(Reg16::DX))),
+            0xe => Some(Op::Out(Arg::Reg16(Reg16::DX), Arg::Reg8(Reg8::AL))),
+            0xf => Some(Op::Out(Arg::Reg16(Reg16::DX), Arg::Reg16(Reg16::AX))),
+            _ => unreachable!(),
+        }
+    }
+
+    // grp3: f6 -> r/m8 ; f7 -> r/m16
+    fn next_grp3(&mut self, b0: u8) -> Option<Op> {
+        let b1 = self.nextb()?;
+        let (a0, _) = if b0 & 1 == 0 {
+            self.modrm8(b1)?
+        } else {
+            self.modrm16(b1)?
+        };
+        match (b1 >> 3) & 0x7 {
+            0b000 => {
+                let imm = if b0 & 1 == 0 {
+                    Arg::Uimm8(self.nextb()?)
+                } else {
+                    Arg::Uimm16(self.nextw()?)
+                };
+                Some(Op::Test(a0, imm))
+            }
+            0b001 => Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1))),
+            0b010 => Some(Op::Not(a0)),
+            0b011 => Some(Op::Neg(a0)),
+            0b100 => Some(Op::Mul(a0)),
+            0b101 => Some(Op::Imul(a0)),
+            0b110 => Some(Op::Div(a0)),
+            0b111 => Some(Op::Idiv(a0)),
             _ => unreachable!(),
         }
     }
@@ -722,13 +947,9 @@ impl<'a> Decoder<'a> {
                 // 0xf5 -> cmc
                 Some(Op::Cmc)
             }
-            0x6 => {
-                // 0xf6 -> grp3a
-                unimplemented!()
-            }
-            0x7 => {
-                // 0xf7 -> grp3b
-                unimplemented!()
+            0x6 | 0x7 => {
+                // 0xf6 -> grp3a, 0xf7 -> grp3b
+                self.next_grp3(b0)
             }
             0x8 => {
                 // 0xf8 -> clc
@@ -755,10 +976,30 @@ impl<'a> Decoder<'a> {
                 Some(Op::Std)
             }
             0xe => {
-                unimplemented!("0x{:02x}", b0) // grp4
+                // 0xfe -> grp4: inc/dec r/m8
+                let b1 = self.nextb()?;
+                let (a0, _) = self.modrm8(b1)?;
+                match (b1 >> 3) & 0x7 {
+                    0b000 => Some(Op::Inc(a0)),
+                    0b001 => Some(Op::Dec(a0)),
+                    _ => Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1))),
+                }
             }
             0xf => {
-                unimplemented!("0x{:02x}", b0) // grp5
+                // 0xff -> grp5
+                let b1 = self.nextb()?;
+                let (a0, _) = self.modrm16(b1)?;
+                let is_mem = b1 >> 6 != 0b11;
+                match (b1 >> 3) & 0x7 {
+                    0b000 => Some(Op::Inc(a0)),
+                    0b001 => Some(Op::Dec(a0)),
+                    0b010 => Some(Op::Call(a0)),
+                    0b011 if is_mem => Some(Op::CallFarMem(a0)),
+                    0b100 => Some(Op::Jmp(a0)),
+                    0b101 if is_mem => Some(Op::JmpFarMem(a0)),
+                    0b110 => Some(Op::Push(a0)),
+                    _ => Some(Op::Invalid(Invalid::UnexpectedBytes(b0, b1))),
+                }
             }
             _ => unreachable!(),
         }
@@ -766,6 +1007,7 @@ impl<'a> Decoder<'a> {
 
     fn next_o(&mut self) -> Option<Op> {
         let b0 = self.nextb()?;
+        self.width = opcode_width(b0);
         match b0 & 0xf0 {
             0x00 => self.next_0(b0),
             0x10 => self.next_1(b0),
@@ -787,17 +1029,27 @@ impl<'a> Decoder<'a> {
         }
     }
 
-    pub fn next_i(&mut self) -> Option<Inst> {
+    // decodes the next instruction. at most one prefix of each kind (segment,
+    // rep, lock) is accepted, so the decoder never reads more than
+    // MAX_INST_LEN bytes
+    pub fn next_i(&mut self) -> Result<Inst, DecodeError> {
         self.sreg = None;
+        self.rep = None;
         self.size = 0;
         self.lock = false;
-        let op = self.next_o().unwrap(); // todo
-        Some(Inst {
+        let Some(op) = self.next_o() else {
+            return Err(match self.size {
+                0 => DecodeError::EndOfInput,
+                n => DecodeError::Truncated(n),
+            });
+        };
+        Ok(Inst {
             lock: self.lock,
             rep: self.rep,
             seg: self.sreg,
             op,
             size: self.size as u8,
+            width: self.width,
         })
     }
 }
@@ -0,0 +1,606 @@
+use crate::op::{Arg, Inst, Mem, Op, Reg16, Reg8, Rep, Sreg, Width};
+
+// how the encoder chooses between the encodings of a same instruction
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EncMode {
+    // shortest encoding: sign extended imm8, 8 bit or no displacement,
+    // short jumps, accumulator and register forms
+    #[default]
+    Shortest,
+    // the operand forms are kept as given: Imm8/Uimm16 immediates, Off8/Off16
+    // displacements and Imm8/Imm16 jumps select the encoding. the decoder
+    // gives the same Inst back (register and accumulator forms are still
+    // used, they do not change the Inst)
+    Fixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodeError {
+    // this combination of operands has no encoding
+    InvalidOperands,
+    // an immediate or a displacement does not fit its field
+    OutOfRange,
+    // Op::Invalid is not an instruction
+    NotAnInstruction,
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::InvalidOperands => write!(f, "invalid combination of operands"),
+            EncodeError::OutOfRange => write!(f, "value out of range"),
+            EncodeError::NotAnInstruction => write!(f, "not an instruction"),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+type Result<T> = std::result::Result<T, EncodeError>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Encoder {
+    mode: EncMode,
+}
+
+// operand size implied by a register operand
+fn reg_width(arg: &Arg) -> Option<Width> {
+    match arg {
+        Arg::Reg8(_) => Some(Width::Byte),
+        Arg::Reg16(_) | Arg::Sreg(_) => Some(Width::Word),
+        _ => None,
+    }
+}
+
+fn is_rm(arg: &Arg) -> bool {
+    matches!(arg, Arg::Reg8(_) | Arg::Reg16(_) | Arg::Mem(_))
+}
+
+fn is_imm(arg: &Arg) -> bool {
+    matches!(arg, Arg::Imm8(_) | Arg::Uimm8(_) | Arg::Imm16(_) | Arg::Uimm16(_))
+}
+
+fn is_acc(arg: &Arg) -> bool {
+    matches!(arg, Arg::Reg8(Reg8::AL) | Arg::Reg16(Reg16::AX))
+}
+
+fn reg_code(arg: &Arg) -> Result<u8> {
+    match arg {
+        Arg::Reg8(r) => Ok(u8::from(*r)),
+        Arg::Reg16(r) => Ok(u8::from(*r)),
+        Arg::Sreg(s) => Ok(*s as u8),
+        _ => Err(EncodeError::InvalidOperands),
+    }
+}
+
+// immediate value as a 16 bit word (imm8 are sign extended)
+fn imm_value(arg: &Arg) -> Result<u16> {
+    match *arg {
+        Arg::Imm8(v) => Ok(v as i16 as u16),
+        Arg::Uimm8(v) => Ok(v as u16),
+        Arg::Imm16(v) => Ok(v as u16),
+        Arg::Uimm16(v) => Ok(v),
+        _ => Err(EncodeError::InvalidOperands),
+    }
+}
+
+// immediate for a byte operation: the value must fit, signed or not
+fn imm8(arg: &Arg) -> Result<u8> {
+    let v = imm_value(arg)?;
+    if v <= 0xff || v >= 0xff80 {
+        Ok(v as u8)
+    } else {
+        Err(EncodeError::OutOfRange)
+    }
+}
+
+// the immediate, if it can be given as a sign extended byte
+fn simm8(arg: &Arg) -> Option<i8> {
+    let v = imm_value(arg).ok()? as i16;
+    i8::try_from(v).ok()
+}
+
+// rm code of the base/index combinations (bx+si, bx+di, bp+si, bp+di, si,
+// di, bp, bx)
+fn rm_code(base: Reg16, index: Option<Reg16>) -> Result<u8> {
+    let (base, index) = match (base, index) {
+        // [si+bx] is [bx+si]
+        (Reg16::SI | Reg16::DI, Some(i @ (Reg16::BX | Reg16::BP))) => (i, Some(base)),
+        _ => (base, index),
+    };
+    match (base, index) {
+        (Reg16::BX, Some(Reg16::SI)) => Ok(0),
+        (Reg16::BX, Some(Reg16::DI)) => Ok(1),
+        (Reg16::BP, Some(Reg16::SI)) => Ok(2),
+        (Reg16::BP, Some(Reg16::DI)) => Ok(3),
+        (Reg16::SI, None) => Ok(4),
+        (Reg16::DI, None) => Ok(5),
+        (Reg16::BP, None) => Ok(6),
+        (Reg16::BX, None) => Ok(7),
+        _ => Err(EncodeError::InvalidOperands),
+    }
+}
+
+fn push_word(out: &mut Vec<u8>, w: u16) {
+    out.extend_from_slice(&w.to_le_bytes());
+}
+
+impl Encoder {
+    pub fn new(mode: EncMode) -> Encoder {
+        Encoder { mode }
+    }
+
+    pub fn mode(&self) -> EncMode {
+        self.mode
+    }
+
+    // encodes an instruction, prefixes included. relative jumps and calls
+    // keep their target: when a near jump is shortened, its displacement is
+    // adjusted for the shorter instruction
+    pub fn encode(&self, inst: &Inst) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(crate::MAX_INST_LEN);
+        if let Some(seg) = inst.seg {
+            out.push(0x26 | (seg as u8) << 3);
+        }
+        if inst.lock {
+            out.push(0xf0);
+        }
+        match inst.rep {
+            Some(Rep::Rep) => out.push(0xf3),
+            Some(Rep::Repne) => out.push(0xf2),
+            None => (),
+        }
+        self.encode_op(inst, &mut out)?;
+        Ok(out)
+    }
+
+    // modrm byte for a register or memory operand, followed by the
+    // displacement
+    fn modrm(&self, out: &mut Vec<u8>, reg: u8, rm: &Arg) -> Result<()> {
+        let reg = (reg & 7) << 3;
+        let mem = match rm {
+            Arg::Reg8(_) | Arg::Reg16(_) => {
+                out.push(0xc0 | reg | reg_code(rm)?);
+                return Ok(());
+            }
+            Arg::Mem(mem) => mem,
+            _ => return Err(EncodeError::InvalidOperands),
+        };
+
+        let Some(base) = mem.base() else {
+            // direct address: mod=0, rm=6
+            out.push(reg | 6);
+            push_word(out, mem.disp());
+            return Ok(());
+        };
+        let rm = rm_code(base, mem.index())?;
+        let disp = mem.disp() as i16;
+
+        // [bp] can only be encoded with a displacement
+        let md = match (self.mode, mem) {
+            (EncMode::Shortest, _) if disp == 0 && rm != 6 => 0,
+            (EncMode::Shortest, _) if i8::try_from(disp).is_ok() => 1,
+            (EncMode::Shortest, _) => 2,
+            (EncMode::Fixed, Mem::Reg(_) | Mem::RegReg(_, _)) if rm != 6 => 0,
+            (EncMode::Fixed, Mem::RegOff16(_, _) | Mem::RegRegOff16(_, _, _)) => 2,
+            (EncMode::Fixed, _) => 1,
+        };
+        out.push(md << 6 | reg | rm);
+        match md {
+            1 => out.push(disp as u8),
+            2 => push_word(out, disp as u16),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // add, or, adc, sbb, and, sub, xor and cmp: n is the operation number,
+    // as found in the opcode (bits 5-3) and in the reg field of 80-83
+    fn alu(&self, inst: &Inst, out: &mut Vec<u8>, n: u8, a1: &Arg, a2: &Arg) -> Result<()> {
+        let w = reg_width(a1).or(reg_width(a2)).unwrap_or(inst.width);
+        let wb = (w == Width::Word) as u8;
+        let base = n << 3;
+        match (a1, a2) {
+            (Arg::Sreg(_), _) | (_, Arg::Sreg(_)) => Err(EncodeError::InvalidOperands),
+            (_, Arg::Reg8(_) | Arg::Reg16(_)) if is_rm(a1) => {
+                if reg_width(a1).is_some_and(|w1| Some(w1) != reg_width(a2)) {
+                    return Err(EncodeError::InvalidOperands);
+                }
+                out.push(base | wb);
+                self.modrm(out, reg_code(a2)?, a1)
+            }
+            (Arg::Reg8(_) | Arg::Reg16(_), Arg::Mem(_)) => {
+                out.push(base | 2 | wb);
+                self.modrm(out, reg_code(a1)?, a2)
+            }
+            (_, imm) if is_rm(a1) && is_imm(imm) => {
+                let sx = simm8(imm);
+                match w {
+                    Width::Byte if is_acc(a1) => {
+                        out.push(base | 4);
+                        out.push(imm8(imm)?);
+                    }
+                    Width::Byte => {
+                        out.push(0x80);
+                        self.modrm(out, n, a1)?;
+                        out.push(imm8(imm)?);
+                    }
+                    // imm8 is kept as is in fixed mode, the shortest mode
+                    // only uses it when it saves a byte
+                    Width::Word if matches!(imm, Arg::Imm8(_)) && self.mode == EncMode::Fixed => {
+                        out.push(0x83);
+                        self.modrm(out, n, a1)?;
+                        out.push(sx.ok_or(EncodeError::OutOfRange)? as u8);
+                    }
+                    // like nasm, the sign extended form is preferred to the
+                    // accumulator one when both have the same size
+                    Width::Word if sx.is_some() && self.mode == EncMode::Shortest => {
+                        out.push(0x83);
+                        self.modrm(out, n, a1)?;
+                        out.push(sx.unwrap() as u8);
+                    }
+                    Width::Word if is_acc(a1) => {
+                        out.push(base | 5);
+                        push_word(out, imm_value(imm)?);
+                    }
+                    Width::Word => {
+                        out.push(0x81);
+                        self.modrm(out, n, a1)?;
+                        push_word(out, imm_value(imm)?);
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(EncodeError::InvalidOperands),
+        }
+    }
+
+    // single r/m operand instructions: opcode for the byte form (the word
+    // form is the next one) and the reg field
+    fn grp(&self, inst: &Inst, out: &mut Vec<u8>, opcode: u8, n: u8, a1: &Arg) -> Result<()> {
+        if !is_rm(a1) {
+            return Err(EncodeError::InvalidOperands);
+        }
+        let w = reg_width(a1).unwrap_or(inst.width);
+        out.push(opcode | (w == Width::Word) as u8);
+        self.modrm(out, n, a1)
+    }
+
+    fn shift(&self, inst: &Inst, out: &mut Vec<u8>, n: u8, a1: &Arg, cnt: &Arg) -> Result<()> {
+        let opcode = match cnt {
+            Arg::Reg8(Reg8::CL) => 0xd2,
+            Arg::Imm8(1) | Arg::Uimm8(1) | Arg::Imm16(1) | Arg::Uimm16(1) => 0xd0,
+            _ => return Err(EncodeError::InvalidOperands),
+        };
+        self.grp(inst, out, opcode, n, a1)
+    }
+
+    fn rel8(out: &mut Vec<u8>, opcode: u8, rel: i8) -> Result<()> {
+        out.push(opcode);
+        out.push(rel as u8);
+        Ok(())
+    }
+
+    fn far(out: &mut Vec<u8>, opcode: u8, seg: &Arg, off: &Arg) -> Result<()> {
+        out.push(opcode);
+        push_word(out, imm_value(off)?);
+        push_word(out, imm_value(seg)?);
+        Ok(())
+    }
+
+    // r/m operand that must be in memory (far pointers, lea)
+    fn mem_only(&self, out: &mut Vec<u8>, opcode: u8, reg: u8, rm: &Arg) -> Result<()> {
+        if !matches!(rm, Arg::Mem(_)) {
+            return Err(EncodeError::InvalidOperands);
+        }
+        out.push(opcode);
+        self.modrm(out, reg, rm)
+    }
+
+    fn encode_op(&self, inst: &Inst, out: &mut Vec<u8>) -> Result<()> {
+        let byte = |out: &mut Vec<u8>, b: u8| {
+            out.push(b);
+            Ok(())
+        };
+        let string = |out: &mut Vec<u8>, opcode: u8| {
+            out.push(opcode | (inst.width == Width::Word) as u8);
+            Ok(())
+        };
+
+        match &inst.op {
+            Op::Nop => byte(out, 0x90),
+
+            Op::Add(a1, a2) => self.alu(inst, out, 0, a1, a2),
+            Op::Or(a1, a2) => self.alu(inst, out, 1, a1, a2),
+            Op::Adc(a1, a2) => self.alu(inst, out, 2, a1, a2),
+            Op::Sbb(a1, a2) => self.alu(inst, out, 3, a1, a2),
+            Op::And(a1, a2) => self.alu(inst, out, 4, a1, a2),
+            Op::Sub(a1, a2) => self.alu(inst, out, 5, a1, a2),
+            Op::Xor(a1, a2) => self.alu(inst, out, 6, a1, a2),
+            Op::Cmp(a1, a2) => self.alu(inst, out, 7, a1, a2),
+
+            Op::Not(a1) => self.grp(inst, out, 0xf6, 2, a1),
+            Op::Neg(a1) => self.grp(inst, out, 0xf6, 3, a1),
+            Op::Mul(a1) => self.grp(inst, out, 0xf6, 4, a1),
+            Op::Imul(a1) => self.grp(inst, out, 0xf6, 5, a1),
+            Op::Div(a1) => self.grp(inst, out, 0xf6, 6, a1),
+            Op::Idiv(a1) => self.grp(inst, out, 0xf6, 7, a1),
+
+            Op::Rol(a1, a2) => self.shift(inst, out, 0, a1, a2),
+            Op::Ror(a1, a2) => self.shift(inst, out, 1, a1, a2),
+            Op::Rcl(a1, a2) => self.shift(inst, out, 2, a1, a2),
+            Op::Rcr(a1, a2) => self.shift(inst, out, 3, a1, a2),
+            Op::Shl(a1, a2) => self.shift(inst, out, 4, a1, a2),
+            Op::Shr(a1, a2) => self.shift(inst, out, 5, a1, a2),
+            Op::Setmo(a1, a2) => self.shift(inst, out, 6, a1, a2),
+            Op::Sar(a1, a2) => self.shift(inst, out, 7, a1, a2),
+
+            Op::Push(a1) => match a1 {
+                Arg::Reg16(r) => byte(out, 0x50 | u8::from(*r)),
+                Arg::Sreg(s) => byte(out, 0x06 | (*s as u8) << 3),
+                Arg::Mem(_) => self.mem_only(out, 0xff, 6, a1),
+                _ => Err(EncodeError::InvalidOperands),
+            },
+            Op::Pop(a1) => match a1 {
+                Arg::Reg16(r) => byte(out, 0x58 | u8::from(*r)),
+                // pop cs (0fh) is not decoded either
+                Arg::Sreg(Sreg::CS) => Err(EncodeError::InvalidOperands),
+                Arg::Sreg(s) => byte(out, 0x07 | (*s as u8) << 3),
+                Arg::Mem(_) => self.mem_only(out, 0x8f, 0, a1),
+                _ => Err(EncodeError::InvalidOperands),
+            },
+            Op::Pushf => byte(out, 0x9c),
+            Op::Popf => byte(out, 0x9d),
+
+            Op::Aaa => byte(out, 0x37),
+            Op::Aad(b) => {
+                out.extend_from_slice(&[0xd5, *b]);
+                Ok(())
+            }
+            Op::Aam(b) => {
+                out.extend_from_slice(&[0xd4, *b]);
+                Ok(())
+            }
+            Op::Aas => byte(out, 0x3f),
+            Op::Daa => byte(out, 0x27),
+            Op::Das => byte(out, 0x2f),
+
+            Op::Inc(Arg::Reg16(r)) => byte(out, 0x40 | u8::from(*r)),
+            Op::Dec(Arg::Reg16(r)) => byte(out, 0x48 | u8::from(*r)),
+            Op::Inc(a1) => self.grp(inst, out, 0xfe, 0, a1),
+            Op::Dec(a1) => self.grp(inst, out, 0xfe, 1, a1),
+
+            Op::Jcc(cc, rel) => Self::rel8(out, 0x70 | *cc as u8, *rel),
+            Op::Jcxz(rel) => Self::rel8(out, 0xe3, *rel),
+            Op::Loop(rel) => Self::rel8(out, 0xe2, *rel),
+            Op::Loopz(rel) => Self::rel8(out, 0xe1, *rel),
+            Op::Loopnz(rel) => Self::rel8(out, 0xe0, *rel),
+
+            Op::Call(Arg::Imm16(rel)) => {
+                out.push(0xe8);
+                push_word(out, *rel as u16);
+                Ok(())
+            }
+            Op::Call(a1) if is_rm(a1) => {
+                out.push(0xff);
+                self.modrm(out, 2, a1)
+            }
+            Op::Call(_) => Err(EncodeError::InvalidOperands),
+            Op::CallFar(seg, off) => Self::far(out, 0x9a, seg, off),
+            Op::CallFarMem(a1) => self.mem_only(out, 0xff, 3, a1),
+            Op::Ret => byte(out, 0xc3),
+            Op::RetImm(n) => {
+                out.push(0xc2);
+                push_word(out, *n);
+                Ok(())
+            }
+            Op::Retf => byte(out, 0xcb),
+            Op::RetfImm(n) => {
+                out.push(0xca);
+                push_word(out, *n);
+                Ok(())
+            }
+
+            Op::Jmp(Arg::Imm8(rel)) => Self::rel8(out, 0xeb, *rel),
+            Op::Jmp(Arg::Imm16(rel)) => {
+                // the short form is one byte shorter, the displacement
+                // grows by one for the same target
+                let short = rel.checked_add(1).and_then(|r| i8::try_from(r).ok());
+                match (self.mode, short) {
+                    (EncMode::Shortest, Some(rel8)) => Self::rel8(out, 0xeb, rel8),
+                    _ => {
+                        out.push(0xe9);
+                        push_word(out, *rel as u16);
+                        Ok(())
+                    }
+                }
+            }
+            Op::Jmp(a1) if is_rm(a1) => {
+                out.push(0xff);
+                self.modrm(out, 4, a1)
+            }
+            Op::Jmp(_) => Err(EncodeError::InvalidOperands),
+            Op::JmpFar(seg, off) => Self::far(out, 0xea, seg, off),
+            Op::JmpFarMem(a1) => self.mem_only(out, 0xff, 5, a1),
+
+            Op::Int(n) => {
+                out.extend_from_slice(&[0xcd, *n]);
+                Ok(())
+            }
+            Op::Int3 => byte(out, 0xcc),
+            Op::Into => byte(out, 0xce),
+            Op::Iret => byte(out, 0xcf),
+
+            Op::Test(a1, a2) => {
+                let w = reg_width(a1).or(reg_width(a2)).unwrap_or(inst.width);
+                let wb = (w == Width::Word) as u8;
+                match (a1, a2) {
+                    (_, Arg::Reg8(_) | Arg::Reg16(_)) if is_rm(a1) => {
+                        out.push(0x84 | wb);
+                        self.modrm(out, reg_code(a2)?, a1)
+                    }
+                    // test is symmetric, the register goes in the reg field
+                    (Arg::Reg8(_) | Arg::Reg16(_), Arg::Mem(_)) => {
+                        out.push(0x84 | wb);
+                        self.modrm(out, reg_code(a1)?, a2)
+                    }
+                    (_, imm) if is_acc(a1) && is_imm(imm) => {
+                        out.push(0xa8 | wb);
+                        match w {
+                            Width::Byte => out.push(imm8(imm)?),
+                            Width::Word => push_word(out, imm_value(imm)?),
+                        }
+                        Ok(())
+                    }
+                    (_, imm) if is_rm(a1) && is_imm(imm) => {
+                        out.push(0xf6 | wb);
+                        self.modrm(out, 0, a1)?;
+                        match w {
+                            Width::Byte => out.push(imm8(imm)?),
+                            Width::Word => push_word(out, imm_value(imm)?),
+                        }
+                        Ok(())
+                    }
+                    _ => Err(EncodeError::InvalidOperands),
+                }
+            }
+            Op::Xchg(a1, a2) => match (a1, a2) {
+                // xchg ax, ax is nop: only the shortest mode uses 90h for it
+                (Arg::Reg16(Reg16::AX), Arg::Reg16(r))
+                    if *r != Reg16::AX || self.mode == EncMode::Shortest =>
+                {
+                    byte(out, 0x90 | u8::from(*r))
+                }
+                (Arg::Reg16(r), Arg::Reg16(Reg16::AX)) if self.mode == EncMode::Shortest => {
+                    byte(out, 0x90 | u8::from(*r))
+                }
+                (_, Arg::Reg8(_) | Arg::Reg16(_)) if is_rm(a1) => {
+                    let w = reg_width(a2).unwrap();
+                    if reg_width(a1).is_some_and(|w1| w1 != w) {
+                        return Err(EncodeError::InvalidOperands);
+                    }
+                    out.push(0x86 | (w == Width::Word) as u8);
+                    self.modrm(out, reg_code(a2)?, a1)
+                }
+                (Arg::Reg8(_) | Arg::Reg16(_), Arg::Mem(_)) => {
+                    let w = reg_width(a1).unwrap();
+                    out.push(0x86 | (w == Width::Word) as u8);
+                    self.modrm(out, reg_code(a1)?, a2)
+                }
+                _ => Err(EncodeError::InvalidOperands),
+            },
+
+            Op::Mov(a1, a2) => self.mov(inst, out, a1, a2),
+            Op::Lea(Arg::Reg16(r), a2) => self.mem_only(out, 0x8d, u8::from(*r), a2),
+            Op::Lds(Arg::Reg16(r), a2) => self.mem_only(out, 0xc5, u8::from(*r), a2),
+            Op::Les(Arg::Reg16(r), a2) => self.mem_only(out, 0xc4, u8::from(*r), a2),
+            Op::Lea(_, _) | Op::Lds(_, _) | Op::Les(_, _) => Err(EncodeError::InvalidOperands),
+            Op::Xlat => byte(out, 0xd7),
+            Op::Sahf => byte(out, 0x9e),
+            Op::Lahf => byte(out, 0x9f),
+
+            Op::Movs => string(out, 0xa4),
+            Op::Cmps => string(out, 0xa6),
+            Op::Stos => string(out, 0xaa),
+            Op::Lods => string(out, 0xac),
+            Op::Scas => string(out, 0xae),
+
+            Op::In(a1, a2) | Op::Out(a2, a1) => {
+                let out_bit = matches!(inst.op, Op::Out(_, _)) as u8 * 2;
+                let wb = match a1 {
+                    Arg::Reg8(Reg8::AL) => 0,
+                    Arg::Reg16(Reg16::AX) => 1,
+                    _ => return Err(EncodeError::InvalidOperands),
+                };
+                match a2 {
+                    Arg::Reg16(Reg16::DX) => byte(out, 0xec | out_bit | wb),
+                    port if is_imm(port) => {
+                        out.push(0xe4 | out_bit | wb);
+                        let p = imm_value(port)?;
+                        if p > 0xff {
+                            return Err(EncodeError::OutOfRange);
+                        }
+                        out.push(p as u8);
+                        Ok(())
+                    }
+                    _ => Err(EncodeError::InvalidOperands),
+                }
+            }
+
+            Op::Cbw => byte(out, 0x98),
+            Op::Cwd => byte(out, 0x99),
+            Op::Esc(code, a1) if *code < 0x40 && is_rm(a1) => {
+                out.push(0xd8 | code >> 3);
+                self.modrm(out, code & 7, a1)
+            }
+            Op::Esc(_, _) => Err(EncodeError::InvalidOperands),
+            Op::Wait => byte(out, 0x9b),
+
+            Op::Hlt => byte(out, 0xf4),
+            Op::Cmc => byte(out, 0xf5),
+            Op::Clc => byte(out, 0xf8),
+            Op::Stc => byte(out, 0xf9),
+            Op::Cli => byte(out, 0xfa),
+            Op::Sti => byte(out, 0xfb),
+            Op::Cld => byte(out, 0xfc),
+            Op::Std => byte(out, 0xfd),
+
+            Op::Invalid(_) => Err(EncodeError::NotAnInstruction),
+        }
+    }
+
+    fn mov(&self, inst: &Inst, out: &mut Vec<u8>, a1: &Arg, a2: &Arg) -> Result<()> {
+        let w = reg_width(a1).or(reg_width(a2)).unwrap_or(inst.width);
+        let wb = (w == Width::Word) as u8;
+        match (a1, a2) {
+            // accumulator and direct address
+            (acc, Arg::Mem(Mem::Direct(addr))) if is_acc(acc) => {
+                out.push(0xa0 | wb);
+                push_word(out, *addr);
+                Ok(())
+            }
+            (Arg::Mem(Mem::Direct(addr)), acc) if is_acc(acc) => {
+                out.push(0xa2 | wb);
+                push_word(out, *addr);
+                Ok(())
+            }
+            (Arg::Sreg(s), rm) if is_rm(rm) && reg_width(rm) != Some(Width::Byte) => {
+                out.push(0x8e);
+                self.modrm(out, *s as u8, rm)
+            }
+            (rm, Arg::Sreg(s)) if is_rm(rm) && reg_width(rm) != Some(Width::Byte) => {
+                out.push(0x8c);
+                self.modrm(out, *s as u8, rm)
+            }
+            (_, Arg::Reg8(_) | Arg::Reg16(_)) if is_rm(a1) => {
+                if reg_width(a1).is_some_and(|w1| Some(w1) != reg_width(a2)) {
+                    return Err(EncodeError::InvalidOperands);
+                }
+                out.push(0x88 | wb);
+                self.modrm(out, reg_code(a2)?, a1)
+            }
+            (Arg::Reg8(_) | Arg::Reg16(_), Arg::Mem(_)) => {
+                out.push(0x8a | wb);
+                self.modrm(out, reg_code(a1)?, a2)
+            }
+            (Arg::Reg8(_) | Arg::Reg16(_), imm) if is_imm(imm) => {
+                out.push(0xb0 | wb << 3 | reg_code(a1)?);
+                match w {
+                    Width::Byte => out.push(imm8(imm)?),
+                    Width::Word => push_word(out, imm_value(imm)?),
+                }
+                Ok(())
+            }
+            (Arg::Mem(_), imm) if is_imm(imm) => {
+                out.push(0xc6 | wb);
+                self.modrm(out, 0, a1)?;
+                match w {
+                    Width::Byte => out.push(imm8(imm)?),
+                    Width::Word => push_word(out, imm_value(imm)?),
+                }
+                Ok(())
+            }
+            _ => Err(EncodeError::InvalidOperands),
+        }
+    }
+}
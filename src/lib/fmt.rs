@@ -0,0 +1,787 @@
+use std::{fmt, str::FromStr};
+
+use crate::{Arg, Cc, Inst, Mem, MemAddrT, Op, Reg16, Reg8, Rep, Sreg, Width};
+
+// the text of an instruction, in the syntax of an assembler
+pub trait Formatter {
+    // pc is the address of the instruction and cs its code segment, for
+    // the relative branches
+    fn format(&self, cs: u16, pc: MemAddrT, inst: &Inst) -> String {
+        self.format_with(cs, pc, inst, &|_| None)
+    }
+    // names gives the labels of the branch targets, if any
+    fn format_with(&self, cs: u16, pc: MemAddrT, inst: &Inst, names: &Names) -> String {
+        self.text(cs, pc, inst, names).to_string()
+    }
+    // bytes that are not code
+    fn format_data(&self, bytes: &[u8]) -> String {
+        self.text_data(bytes).to_string()
+    }
+    // the same, with the mnemonic and the operands apart
+    fn text(&self, cs: u16, pc: MemAddrT, inst: &Inst, names: &Names) -> Text;
+    fn text_data(&self, bytes: &[u8]) -> Text;
+}
+
+pub type Names<'a> = dyn Fn(MemAddrT) -> Option<String> + 'a;
+
+// the mnemonic, with its prefixes, and the operands in the order of the
+// syntax
+#[derive(Debug, Clone, PartialEq)]
+pub struct Text {
+    pub mnemonic: String,
+    pub operands: Vec<String>,
+    separator: &'static str,
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.mnemonic)?;
+        if !self.operands.is_empty() {
+            write!(f, " {}", self.operands.join(self.separator))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Syntax {
+    #[default]
+    Nasm,
+    Masm, // and tasm
+    Att,  // gnu as
+}
+
+impl Syntax {
+    pub fn formatter(self, opts: FmtOptions) -> Box<dyn Formatter> {
+        match self {
+            Syntax::Nasm => Box::new(Nasm(opts)),
+            Syntax::Masm => Box::new(Masm(opts)),
+            Syntax::Att => Box::new(Att(opts)),
+        }
+    }
+}
+
+impl FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nasm" => Ok(Syntax::Nasm),
+            "masm" | "tasm" => Ok(Syntax::Masm),
+            "att" | "gas" => Ok(Syntax::Att),
+            _ => Err(format!("unknown syntax `{}`", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hex {
+    Prefix, // 0x1F
+    Suffix, // 01Fh
+}
+
+impl FromStr for Hex {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0x" => Ok(Hex::Prefix),
+            "h" => Ok(Hex::Suffix),
+            _ => Err(format!("unknown hex style `{}`", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FmtOptions {
+    pub uppercase: bool,  // mnemonics, registers and keywords
+    pub hex: Option<Hex>, // None: the usual style of the syntax
+    // nasm: the encodings an assembler would shorten are pinned, with
+    // `jmp near`, `strict word` immediates and `[word bx+0x0001]`
+    pub strict: bool,
+}
+
+pub struct Nasm(pub FmtOptions);
+pub struct Masm(pub FmtOptions);
+pub struct Att(pub FmtOptions);
+
+impl Formatter for Nasm {
+    fn text(&self, cs: u16, pc: MemAddrT, inst: &Inst, names: &Names) -> Text {
+        render(self, cs, pc, inst, names)
+    }
+
+    fn text_data(&self, bytes: &[u8]) -> Text {
+        data(self, bytes)
+    }
+}
+
+impl Formatter for Masm {
+    fn text(&self, cs: u16, pc: MemAddrT, inst: &Inst, names: &Names) -> Text {
+        render(self, cs, pc, inst, names)
+    }
+
+    fn text_data(&self, bytes: &[u8]) -> Text {
+        data(self, bytes)
+    }
+}
+
+impl Formatter for Att {
+    fn text(&self, cs: u16, pc: MemAddrT, inst: &Inst, names: &Names) -> Text {
+        render(self, cs, pc, inst, names)
+    }
+
+    fn text_data(&self, bytes: &[u8]) -> Text {
+        data(self, bytes)
+    }
+}
+
+// an instruction broken down into a mnemonic and operands, before the
+// syntax is applied
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Plain,
+    Short,       // jmp rel8
+    Near,        // jmp rel16
+    Indirect,    // jmp/call r/m16
+    Far,         // jmp/call seg:off
+    FarIndirect, // jmp/call m16:16
+}
+
+enum Operand {
+    Reg8(Reg8),
+    Reg16(Reg16),
+    Sreg(Sreg),
+    Imm8(u8),
+    Imm16(u16),
+    SImm8(i8), // sign extended to the size of the operation
+    Mem(Mem, Option<Sreg>),
+    Target(MemAddrT),
+    Far(u16, u16),
+    Port, // dx, in in and out
+}
+
+struct Parts {
+    prefixes: Vec<&'static str>,
+    name: &'static str,
+    ops: Vec<Operand>, // intel order: destination first
+    kind: Kind,
+    size: Option<Width>, // of the memory operand, when no register gives it
+    sx: bool,            // a word immediate could be a sign extended byte
+}
+
+fn reg8_name(r: Reg8) -> &'static str {
+    ["al", "cl", "dl", "bl", "ah", "ch", "dh", "bh"][r as usize]
+}
+
+fn reg16_name(r: Reg16) -> &'static str {
+    ["ax", "cx", "dx", "bx", "sp", "bp", "si", "di"][r as usize]
+}
+
+fn sreg_name(s: Sreg) -> &'static str {
+    match s {
+        Sreg::ES => "es",
+        Sreg::CS => "cs",
+        Sreg::SS => "ss",
+        Sreg::DS => "ds",
+    }
+}
+
+fn jcc_name(cc: Cc) -> &'static str {
+    match cc {
+        Cc::O => "jo",
+        Cc::NO => "jno",
+        Cc::B => "jb",
+        Cc::NB => "jnb",
+        Cc::E => "je",
+        Cc::NE => "jne",
+        Cc::BE => "jbe",
+        Cc::NBE => "jnbe",
+        Cc::S => "js",
+        Cc::NS => "jns",
+        Cc::P => "jp",
+        Cc::NP => "jnp",
+        Cc::L => "jl",
+        Cc::NL => "jnl",
+        Cc::LE => "jle",
+        Cc::NLE => "jnle",
+    }
+}
+
+fn operand(a: &Arg, seg: Option<Sreg>) -> Operand {
+    match *a {
+        Arg::Reg8(r) => Operand::Reg8(r),
+        Arg::Reg16(r) => Operand::Reg16(r),
+        Arg::Sreg(s) => Operand::Sreg(s),
+        Arg::Imm8(i) => Operand::SImm8(i),
+        Arg::Uimm8(i) => Operand::Imm8(i),
+        Arg::Imm16(i) => Operand::Imm16(i as u16),
+        Arg::Uimm16(i) => Operand::Imm16(i),
+        Arg::Mem(m) => Operand::Mem(m, seg),
+    }
+}
+
+fn parts(cs: u16, pc: MemAddrT, inst: &Inst) -> Parts {
+    let mut kind = Kind::Plain;
+    let (name, args): (&'static str, Vec<&Arg>) = match &inst.op {
+        Op::Add(a, b) => ("add", vec![a, b]),
+        Op::Adc(a, b) => ("adc", vec![a, b]),
+        Op::Sbb(a, b) => ("sbb", vec![a, b]),
+        Op::Sub(a, b) => ("sub", vec![a, b]),
+        Op::And(a, b) => ("and", vec![a, b]),
+        Op::Or(a, b) => ("or", vec![a, b]),
+        Op::Xor(a, b) => ("xor", vec![a, b]),
+        Op::Cmp(a, b) => ("cmp", vec![a, b]),
+        Op::Test(a, b) => ("test", vec![a, b]),
+        Op::Xchg(a, b) => ("xchg", vec![a, b]),
+        Op::Mov(a, b) => ("mov", vec![a, b]),
+        Op::Lea(a, b) => ("lea", vec![a, b]),
+        Op::Lds(a, b) => ("lds", vec![a, b]),
+        Op::Les(a, b) => ("les", vec![a, b]),
+        Op::In(a, b) => ("in", vec![a, b]),
+        Op::Out(a, b) => ("out", vec![a, b]),
+        Op::Rol(a, b) => ("rol", vec![a, b]),
+        Op::Ror(a, b) => ("ror", vec![a, b]),
+        Op::Rcl(a, b) => ("rcl", vec![a, b]),
+        Op::Rcr(a, b) => ("rcr", vec![a, b]),
+        Op::Shl(a, b) => ("shl", vec![a, b]),
+        Op::Shr(a, b) => ("shr", vec![a, b]),
+        Op::Sar(a, b) => ("sar", vec![a, b]),
+        Op::Setmo(a, b) => ("setmo", vec![a, b]),
+
+        Op::Not(a) => ("not", vec![a]),
+        Op::Neg(a) => ("neg", vec![a]),
+        Op::Mul(a) => ("mul", vec![a]),
+        Op::Imul(a) => ("imul", vec![a]),
+        Op::Div(a) => ("div", vec![a]),
+        Op::Idiv(a) => ("idiv", vec![a]),
+        Op::Inc(a) => ("inc", vec![a]),
+        Op::Dec(a) => ("dec", vec![a]),
+        Op::Push(a) => ("push", vec![a]),
+        Op::Pop(a) => ("pop", vec![a]),
+
+        Op::Jmp(Arg::Imm8(_)) => {
+            kind = Kind::Short;
+            ("jmp", vec![])
+        }
+        Op::Jmp(Arg::Imm16(_)) => {
+            kind = Kind::Near;
+            ("jmp", vec![])
+        }
+        Op::Call(Arg::Imm16(_)) => ("call", vec![]),
+        Op::Jmp(a) => {
+            kind = Kind::Indirect;
+            ("jmp", vec![a])
+        }
+        Op::Call(a) => {
+            kind = Kind::Indirect;
+            ("call", vec![a])
+        }
+        Op::JmpFar(..) | Op::CallFar(..) => {
+            kind = Kind::Far;
+            (
+                if matches!(inst.op, Op::JmpFar(..)) {
+                    "jmp"
+                } else {
+                    "call"
+                },
+                vec![],
+            )
+        }
+        Op::JmpFarMem(a) => {
+            kind = Kind::FarIndirect;
+            ("jmp", vec![a])
+        }
+        Op::CallFarMem(a) => {
+            kind = Kind::FarIndirect;
+            ("call", vec![a])
+        }
+        Op::Jcc(cc, _) => (jcc_name(*cc), vec![]),
+        Op::Jcxz(_) => ("jcxz", vec![]),
+        Op::Loop(_) => ("loop", vec![]),
+        Op::Loopz(_) => ("loopz", vec![]),
+        Op::Loopnz(_) => ("loopnz", vec![]),
+
+        Op::Ret | Op::RetImm(_) => ("ret", vec![]),
+        Op::Retf | Op::RetfImm(_) => ("retf", vec![]),
+        Op::Int(_) => ("int", vec![]),
+        Op::Int3 => ("int3", vec![]),
+        Op::Into => ("into", vec![]),
+        Op::Iret => ("iret", vec![]),
+
+        Op::Aaa => ("aaa", vec![]),
+        Op::Aas => ("aas", vec![]),
+        Op::Daa => ("daa", vec![]),
+        Op::Das => ("das", vec![]),
+        Op::Aad(_) => ("aad", vec![]),
+        Op::Aam(_) => ("aam", vec![]),
+        Op::Cbw => ("cbw", vec![]),
+        Op::Cwd => ("cwd", vec![]),
+
+        Op::Movs => (
+            if inst.width == Width::Byte {
+                "movsb"
+            } else {
+                "movsw"
+            },
+            vec![],
+        ),
+        Op::Cmps => (
+            if inst.width == Width::Byte {
+                "cmpsb"
+            } else {
+                "cmpsw"
+            },
+            vec![],
+        ),
+        Op::Stos => (
+            if inst.width == Width::Byte {
+                "stosb"
+            } else {
+                "stosw"
+            },
+            vec![],
+        ),
+        Op::Lods => (
+            if inst.width == Width::Byte {
+                "lodsb"
+            } else {
+                "lodsw"
+            },
+            vec![],
+        ),
+        Op::Scas => (
+            if inst.width == Width::Byte {
+                "scasb"
+            } else {
+                "scasw"
+            },
+            vec![],
+        ),
+        Op::Xlat => ("xlatb", vec![]),
+
+        Op::Esc(_, a) => ("esc", vec![a]),
+        Op::Wait => ("wait", vec![]),
+
+        Op::Nop => ("nop", vec![]),
+        Op::Pushf => ("pushf", vec![]),
+        Op::Popf => ("popf", vec![]),
+        Op::Sahf => ("sahf", vec![]),
+        Op::Lahf => ("lahf", vec![]),
+        Op::Hlt => ("hlt", vec![]),
+        Op::Cmc => ("cmc", vec![]),
+        Op::Clc => ("clc", vec![]),
+        Op::Stc => ("stc", vec![]),
+        Op::Cli => ("cli", vec![]),
+        Op::Sti => ("sti", vec![]),
+        Op::Cld => ("cld", vec![]),
+        Op::Std => ("std", vec![]),
+        Op::Invalid(_) => ("invalid", vec![]),
+    };
+
+    let mut ops: Vec<Operand> = args.iter().map(|a| operand(a, inst.seg)).collect();
+    match &inst.op {
+        Op::JmpFar(Arg::Uimm16(s), Arg::Uimm16(o))
+        | Op::CallFar(Arg::Uimm16(s), Arg::Uimm16(o)) => ops.push(Operand::Far(*s, *o)),
+        // not from the decoder: the operands as they are
+        Op::JmpFar(seg, off) | Op::CallFar(seg, off) => {
+            ops.extend([seg, off].map(|a| operand(a, inst.seg)))
+        }
+        Op::RetImm(w) | Op::RetfImm(w) => ops.push(Operand::Imm16(*w)),
+        Op::Int(b) => ops.push(Operand::Imm8(*b)),
+        // 10 is the default base
+        Op::Aad(b) | Op::Aam(b) if *b != 10 => ops.push(Operand::Imm8(*b)),
+        Op::Esc(code, _) => ops.insert(0, Operand::Imm8(*code)),
+        // the port in dx
+        Op::In(_, Arg::Reg16(Reg16::DX)) => ops[1] = Operand::Port,
+        Op::Out(Arg::Reg16(Reg16::DX), _) => ops[0] = Operand::Port,
+        // the relative branches
+        _ => ops.extend(inst.target(cs, pc).map(Operand::Target)),
+    }
+
+    // the size of a memory operand is ambiguous without a register. the
+    // count of a shift does not give it
+    let has_mem = ops.iter().any(|o| matches!(o, Operand::Mem(..)));
+    let shift = matches!(
+        inst.op,
+        Op::Rol(..)
+            | Op::Ror(..)
+            | Op::Rcl(..)
+            | Op::Rcr(..)
+            | Op::Shl(..)
+            | Op::Shr(..)
+            | Op::Sar(..)
+            | Op::Setmo(..)
+    );
+    let has_reg = ops[..if shift { 1 } else { ops.len() }]
+        .iter()
+        .any(|o| matches!(o, Operand::Reg8(_) | Operand::Reg16(_) | Operand::Sreg(_)));
+    let sized = !matches!(
+        inst.op,
+        Op::Lea(..) | Op::Lds(..) | Op::Les(..) | Op::Esc(..)
+    );
+    let size = (has_mem && !has_reg && sized).then_some(inst.width);
+    let sx = matches!(
+        inst.op,
+        Op::Add(..)
+            | Op::Or(..)
+            | Op::Adc(..)
+            | Op::Sbb(..)
+            | Op::And(..)
+            | Op::Sub(..)
+            | Op::Xor(..)
+            | Op::Cmp(..)
+    ) && inst.width == Width::Word;
+
+    let mut prefixes = vec![];
+    if inst.lock {
+        prefixes.push("lock");
+    }
+    match inst.rep {
+        Some(Rep::Rep) if matches!(inst.op, Op::Cmps | Op::Scas) => prefixes.push("repe"),
+        Some(Rep::Rep) => prefixes.push("rep"),
+        Some(Rep::Repne) => prefixes.push("repne"),
+        None => (),
+    }
+    // a segment override without memory operand is a prefix
+    if let (Some(seg), false) = (inst.seg, has_mem) {
+        prefixes.push(sreg_name(seg));
+    }
+
+    Parts {
+        prefixes,
+        name,
+        ops,
+        kind,
+        size,
+        sx,
+    }
+}
+
+// what differs from one syntax to another
+trait Style {
+    fn options(&self) -> FmtOptions;
+    fn hex_style(&self) -> Hex;
+    fn mnemonic(&self, p: &Parts) -> String;
+    fn operand(&self, o: &Operand, p: &Parts) -> String;
+    // a branch target, or its label
+    fn target(&self, t: String, _p: &Parts) -> String {
+        t
+    }
+    // at&t puts the destination last
+    fn reversed(&self) -> bool {
+        false
+    }
+    fn separator(&self) -> &'static str {
+        ", "
+    }
+    fn data(&self) -> &'static str {
+        "db"
+    }
+
+    fn kw(&self, s: &str) -> String {
+        if self.options().uppercase {
+            s.to_uppercase()
+        } else {
+            s.to_string()
+        }
+    }
+
+    fn num(&self, v: MemAddrT, digits: usize) -> String {
+        let h = format!("{:0w$X}", v, w = digits);
+        match self.options().hex.unwrap_or(self.hex_style()) {
+            Hex::Prefix => format!("0x{}", h),
+            // a number starts with a digit
+            Hex::Suffix if h.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                format!("0{}{}", h, self.kw("h"))
+            }
+            Hex::Suffix => format!("{}{}", h, self.kw("h")),
+        }
+    }
+
+    fn signed(&self, v: i32, digits: usize) -> String {
+        match v {
+            v if v < 0 => format!("-{}", self.num(v.unsigned_abs(), digits)),
+            v => self.num(v as MemAddrT, digits),
+        }
+    }
+
+    // the displacement, after a register
+    fn disp(&self, m: &Mem) -> String {
+        match *m {
+            Mem::RegOff8(_, d) | Mem::RegRegOff8(_, _, d) if d < 0 => self.signed(d as i32, 2),
+            Mem::RegOff8(_, d) | Mem::RegRegOff8(_, _, d) => {
+                format!("+{}", self.num(d as MemAddrT, 2))
+            }
+            Mem::RegOff16(_, d) | Mem::RegRegOff16(_, _, d) if d < 0 => self.signed(d as i32, 4),
+            Mem::RegOff16(_, d) | Mem::RegRegOff16(_, _, d) => {
+                format!("+{}", self.num(d as MemAddrT, 4))
+            }
+            _ => String::new(),
+        }
+    }
+
+    // base+index+disp, in intel syntax
+    fn ea(&self, m: &Mem) -> String {
+        match *m {
+            Mem::Direct(d) => self.num(d as MemAddrT, 4),
+            Mem::Reg(r) | Mem::RegOff8(r, _) | Mem::RegOff16(r, _) => {
+                format!("{}{}", self.kw(reg16_name(r)), self.disp(m))
+            }
+            Mem::RegReg(b, i) | Mem::RegRegOff8(b, i, _) | Mem::RegRegOff16(b, i, _) => {
+                format!(
+                    "{}+{}{}",
+                    self.kw(reg16_name(b)),
+                    self.kw(reg16_name(i)),
+                    self.disp(m)
+                )
+            }
+        }
+    }
+
+    // the intel operands, shared by nasm and masm
+    fn intel(&self, o: &Operand) -> Option<String> {
+        Some(match *o {
+            Operand::Reg8(r) => self.kw(reg8_name(r)),
+            Operand::Reg16(r) => self.kw(reg16_name(r)),
+            Operand::Sreg(s) => self.kw(sreg_name(s)),
+            Operand::Imm8(i) => self.num(i as MemAddrT, 2),
+            Operand::Imm16(i) => self.num(i as MemAddrT, 4),
+            Operand::Target(t) => self.num(t, 4),
+            Operand::Far(s, o) => format!(
+                "{}:{}",
+                self.num(s as MemAddrT, 4),
+                self.num(o as MemAddrT, 4)
+            ),
+            Operand::Port => self.kw("dx"),
+            _ => return None,
+        })
+    }
+}
+
+fn render(s: &dyn Style, cs: u16, pc: MemAddrT, inst: &Inst, names: &Names) -> Text {
+    let p = parts(cs, pc, inst);
+    let mut mnemonic = String::new();
+    for prefix in &p.prefixes {
+        mnemonic.push_str(&s.kw(prefix));
+        mnemonic.push(' ');
+    }
+    mnemonic.push_str(&s.mnemonic(&p));
+    let mut operands: Vec<String> = p
+        .ops
+        .iter()
+        .map(|o| match o {
+            Operand::Target(t) => s.target(names(*t).unwrap_or_else(|| s.operand(o, &p)), &p),
+            _ => s.operand(o, &p),
+        })
+        .collect();
+    // a far pointer keeps its segment first
+    if s.reversed() && !matches!(p.kind, Kind::Far) {
+        operands.reverse();
+    }
+    Text {
+        mnemonic,
+        operands,
+        separator: s.separator(),
+    }
+}
+
+fn data(s: &dyn Style, bytes: &[u8]) -> Text {
+    Text {
+        mnemonic: s.kw(s.data()),
+        operands: bytes.iter().map(|b| s.num(*b as MemAddrT, 2)).collect(),
+        separator: s.separator(),
+    }
+}
+
+// nasm: `mov word [es:bx+0x10], 0x0001`
+impl Style for Nasm {
+    fn options(&self) -> FmtOptions {
+        self.0
+    }
+
+    fn hex_style(&self) -> Hex {
+        Hex::Prefix
+    }
+
+    fn mnemonic(&self, p: &Parts) -> String {
+        self.kw(p.name)
+    }
+
+    fn target(&self, t: String, p: &Parts) -> String {
+        match p.kind {
+            Kind::Short => format!("{} {}", self.kw("short"), t),
+            Kind::Near if self.0.strict => format!("{} {}", self.kw("near"), t),
+            _ => t,
+        }
+    }
+
+    fn operand(&self, o: &Operand, p: &Parts) -> String {
+        let strict = self.0.strict;
+        match *o {
+            Operand::Imm16(i) if strict && p.sx && i8::try_from(i as i16).is_ok() => {
+                format!("{} {}", self.kw("strict word"), self.num(i as MemAddrT, 4))
+            }
+            Operand::SImm8(i) => format!("{} {}", self.kw("byte"), self.signed(i as i32, 2)),
+            Operand::Mem(m, seg) => {
+                let size = match (p.kind, p.size) {
+                    (Kind::FarIndirect, _) => self.kw("far "),
+                    (Kind::Indirect, _) | (_, Some(Width::Word)) => self.kw("word "),
+                    (_, Some(Width::Byte)) => self.kw("byte "),
+                    (_, None) => String::new(),
+                };
+                // the displacements an assembler would shorten or drop
+                let disp = match m {
+                    Mem::RegOff16(_, d) | Mem::RegRegOff16(_, _, d)
+                        if strict && i8::try_from(d).is_ok() =>
+                    {
+                        self.kw("word ")
+                    }
+                    Mem::RegOff8(r, 0) if strict && r != Reg16::BP => self.kw("byte "),
+                    Mem::RegRegOff8(_, _, 0) if strict => self.kw("byte "),
+                    _ => String::new(),
+                };
+                let seg = seg.map_or(String::new(), |s| format!("{}:", self.kw(sreg_name(s))));
+                format!("{}[{}{}{}]", size, disp, seg, self.ea(&m))
+            }
+            _ => self.intel(o).unwrap(),
+        }
+    }
+}
+
+// masm and tasm: `mov word ptr es:[bx+10h], 0001h`
+impl Style for Masm {
+    fn options(&self) -> FmtOptions {
+        self.0
+    }
+
+    fn hex_style(&self) -> Hex {
+        Hex::Suffix
+    }
+
+    fn mnemonic(&self, p: &Parts) -> String {
+        match p.name {
+            // no int3 mnemonic: masm gives int 3 the one byte form
+            "int3" => self.kw("int 3"),
+            name => self.kw(name),
+        }
+    }
+
+    fn target(&self, t: String, p: &Parts) -> String {
+        match p.kind {
+            Kind::Short => format!("{} {}", self.kw("short"), t),
+            _ => t,
+        }
+    }
+
+    fn operand(&self, o: &Operand, p: &Parts) -> String {
+        if let Some(s) = self.intel(o) {
+            return s;
+        }
+        match *o {
+            Operand::SImm8(i) => self.signed(i as i32, 2),
+            Operand::Mem(m, seg) => {
+                let size = match (p.kind, p.size) {
+                    (Kind::FarIndirect, _) => self.kw("dword ptr "),
+                    (Kind::Indirect, _) | (_, Some(Width::Word)) => self.kw("word ptr "),
+                    (_, Some(Width::Byte)) => self.kw("byte ptr "),
+                    (_, None) => String::new(),
+                };
+                // masm reads [1234h] as the immediate 1234h, a direct
+                // address needs a segment
+                let seg = match (seg, m) {
+                    (Some(s), _) => format!("{}:", self.kw(sreg_name(s))),
+                    (None, Mem::Direct(_)) => format!("{}:", self.kw("ds")),
+                    (None, _) => String::new(),
+                };
+                format!("{}{}[{}]", size, seg, self.ea(&m))
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+// gnu as: `movw $0x0001, %es:0x10(%bx)`, the destination last
+impl Style for Att {
+    fn options(&self) -> FmtOptions {
+        self.0
+    }
+
+    fn hex_style(&self) -> Hex {
+        Hex::Prefix
+    }
+
+    fn reversed(&self) -> bool {
+        true
+    }
+
+    fn separator(&self) -> &'static str {
+        ","
+    }
+
+    fn data(&self) -> &'static str {
+        ".byte"
+    }
+
+    fn mnemonic(&self, p: &Parts) -> String {
+        let name = match (p.name, p.kind) {
+            ("jmp", Kind::Far | Kind::FarIndirect) => "ljmp",
+            ("call", Kind::Far | Kind::FarIndirect) => "lcall",
+            ("retf", _) => "lret",
+            ("cbw", _) => "cbtw",
+            ("cwd", _) => "cwtd",
+            (name, _) => name,
+        };
+        // the size comes with the mnemonic
+        let suffix = match (p.kind, p.size) {
+            (Kind::Plain, Some(Width::Byte)) => "b",
+            (Kind::Plain, Some(Width::Word)) => "w",
+            _ => "",
+        };
+        self.kw(&format!("{}{}", name, suffix))
+    }
+
+    fn operand(&self, o: &Operand, p: &Parts) -> String {
+        // indirect branches go through *
+        let star = match p.kind {
+            Kind::Indirect | Kind::FarIndirect => "*",
+            _ => "",
+        };
+        match *o {
+            Operand::Reg8(r) => format!("{}%{}", star, self.kw(reg8_name(r))),
+            Operand::Reg16(r) => format!("{}%{}", star, self.kw(reg16_name(r))),
+            Operand::Sreg(s) => format!("%{}", self.kw(sreg_name(s))),
+            Operand::Imm8(i) => format!("${}", self.num(i as MemAddrT, 2)),
+            Operand::Imm16(i) => format!("${}", self.num(i as MemAddrT, 4)),
+            Operand::SImm8(i) => format!("${}", self.signed(i as i32, 2)),
+            Operand::Target(t) => self.num(t, 4),
+            Operand::Far(s, o) => {
+                format!(
+                    "${},${}",
+                    self.num(s as MemAddrT, 4),
+                    self.num(o as MemAddrT, 4)
+                )
+            }
+            Operand::Port => format!("(%{})", self.kw("dx")),
+            Operand::Mem(m, seg) => {
+                let seg = seg.map_or(String::new(), |s| format!("%{}:", self.kw(sreg_name(s))));
+                let reg = |r: Reg16| format!("%{}", self.kw(reg16_name(r)));
+                // disp(base,index)
+                let ea = match m {
+                    Mem::Direct(d) => self.num(d as MemAddrT, 4),
+                    Mem::Reg(r) => format!("({})", reg(r)),
+                    Mem::RegOff8(r, d) => format!("{}({})", self.signed(d as i32, 2), reg(r)),
+                    Mem::RegOff16(r, d) => format!("{}({})", self.signed(d as i32, 4), reg(r)),
+                    Mem::RegReg(b, i) => format!("({},{})", reg(b), reg(i)),
+                    Mem::RegRegOff8(b, i, d) => {
+                        format!("{}({},{})", self.signed(d as i32, 2), reg(b), reg(i))
+                    }
+                    Mem::RegRegOff16(b, i, d) => {
+                        format!("{}({},{})", self.signed(d as i32, 4), reg(b), reg(i))
+                    }
+                };
+                format!("{}{}{}", star, seg, ea)
+            }
+        }
+    }
+}
@@ -3,7 +3,16 @@ pub type IoAddrT = u16;
 pub type OpSizeT = u16;
 
 mod op;
-pub use op::{Op, Rep, Inst, Arg, Invalid, Cc, Reg16, Reg8, Sreg};
+pub use op::{Op, Rep, Inst, Arg, Mem, Invalid, Cc, Flags, Reg16, Reg8, Sreg, Width};
 
 mod dec;
-pub use dec::Decoder;
+pub use dec::{Decoder, DecodeError, MAX_INST_LEN};
+
+mod slice;
+pub use slice::{Decoded, SliceDecoder};
+
+mod enc;
+pub use enc::{EncMode, EncodeError, Encoder};
+
+mod fmt;
+pub use fmt::{Att, FmtOptions, Formatter, Hex, Masm, Names, Nasm, Syntax, Text};
@@ -1,10 +1,13 @@
-#[derive(Debug)]
+use crate::MemAddrT;
+
+#[derive(Debug, PartialEq)]
 pub struct Inst {
     pub lock: bool,
     pub rep: Option<Rep>,
     pub seg: Option<Sreg>,
     pub op: Op,
     pub size: u8,
+    pub width: Width, // operand size, as given by the opcode (w bit)
 }
 
 impl Default for Inst {
@@ -15,10 +18,44 @@ impl Default for Inst {
             seg: None,
             op: Op::Nop,
             size: 0,
+            width: Width::Byte,
         }
     }
 }
 
+impl Inst {
+    // the address a direct branch at `pc` goes to, when cs is the code
+    // segment. relative offsets wrap around in the 64K of the segment, far
+    // pointers in the 1MB space
+    pub fn target(&self, cs: u16, pc: MemAddrT) -> Option<MemAddrT> {
+        let rel = match self.op {
+            Op::Jcc(_, rel) | Op::Jcxz(rel) | Op::Loop(rel) | Op::Loopz(rel) | Op::Loopnz(rel) => rel as i16,
+            Op::Jmp(Arg::Imm8(rel)) => rel as i16,
+            Op::Jmp(Arg::Imm16(rel)) | Op::Call(Arg::Imm16(rel)) => rel,
+            Op::JmpFar(Arg::Uimm16(seg), Arg::Uimm16(off)) | Op::CallFar(Arg::Uimm16(seg), Arg::Uimm16(off)) => {
+                return Some(linear(seg, off));
+            }
+            _ => return None,
+        };
+        let base = (cs as MemAddrT) << 4;
+        let ip = (pc.wrapping_sub(base) as u16)
+            .wrapping_add(self.size as u16)
+            .wrapping_add(rel as u16);
+        Some(linear(cs, ip))
+    }
+}
+
+// seg:off, with 20 address lines
+fn linear(seg: u16, off: u16) -> MemAddrT {
+    ((seg as MemAddrT) << 4).wrapping_add(off as MemAddrT) & 0xf_ffff
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Width {
+    Byte,
+    Word,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Rep {
     Rep,
@@ -39,8 +76,27 @@ pub enum Op {
     Xor(Arg, Arg),
     Cmp(Arg, Arg),
 
+    Not(Arg),
+    Neg(Arg),
+
+    Mul(Arg),
+    Imul(Arg),
+    Div(Arg),
+    Idiv(Arg),
+
+    Rol(Arg, Arg), // second arg is the count: 1 or CL
+    Ror(Arg, Arg),
+    Rcl(Arg, Arg),
+    Rcr(Arg, Arg),
+    Shl(Arg, Arg),
+    Shr(Arg, Arg),
+    Sar(Arg, Arg),
+    Setmo(Arg, Arg), // d0-d3 /6: undocumented, see Cpu::shift_op
+
     Push(Arg),
     Pop(Arg),
+    Pushf,
+    Popf,
 
     Aaa,
     Aad(u8),
@@ -54,17 +110,46 @@ pub enum Op {
     Dec(Arg),
 
     Jcc(Cc, i8),
-
-    Call(Arg),
+    Jcxz(i8),
+    Loop(i8),
+    Loopz(i8),
+    Loopnz(i8),
+
+    Call(Arg), // Imm16 is relative, otherwise r/m16 (indirect)
+    CallFar(Arg, Arg), // seg, off
+    CallFarMem(Arg), // indirect, through a m16:16 pointer
     Ret,
+    RetImm(u16),
+    Retf,
+    RetfImm(u16),
 
+    Jmp(Arg), // Imm8 or Imm16 are relative, otherwise r/m16 (indirect)
     JmpFar(Arg, Arg), // Arg can be Far
+    JmpFarMem(Arg),
+
+    Int(u8),
+    Int3,
+    Into,
+    Iret,
 
     Test(Arg, Arg),
     Xchg(Arg, Arg),
 
     Mov(Arg, Arg),
     Lea(Arg, Arg),
+    Lds(Arg, Arg),
+    Les(Arg, Arg),
+    Xlat,
+
+    Sahf,
+    Lahf,
+
+    // string operations (operand size is Inst::width)
+    Movs,
+    Cmps,
+    Stos,
+    Lods,
+    Scas,
 
     In(Arg, Arg),
     Out(Arg, Arg),
@@ -72,6 +157,9 @@ pub enum Op {
     Cbw,
     Cwd,
 
+    Esc(u8, Arg), // 6 bits coprocessor opcode, r/m operand
+    Wait,
+
     Hlt,
     Cmc,
     Clc,
@@ -81,12 +169,30 @@ pub enum Op {
     Cld,
     Std,
 
-    Error,
-
     Invalid(Invalid),
 }
 
-#[derive(Debug, PartialEq)]
+// bit positions in the FLAGS register
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flags {
+    C = 0,
+    P = 2,
+    A = 4,
+    Z = 6,
+    S = 7,
+    T = 8,
+    I = 9,
+    D = 10,
+    O = 11,
+}
+
+impl Flags {
+    pub fn mask(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Cc {
     O,
     NO,
@@ -106,6 +212,32 @@ pub enum Cc {
     NLE,
 }
 
+impl Cc {
+    // tells whether the condition holds for the given FLAGS register image.
+    // the odd conditions are the negation of the even ones before them
+    pub fn evaluate(self, flags: u16) -> bool {
+        let f = |flag: Flags| flags & flag.mask() != 0;
+        match self {
+            Cc::O => f(Flags::O),
+            Cc::NO => !f(Flags::O),
+            Cc::B => f(Flags::C),
+            Cc::NB => !f(Flags::C),
+            Cc::E => f(Flags::Z),
+            Cc::NE => !f(Flags::Z),
+            Cc::BE => f(Flags::C) || f(Flags::Z),
+            Cc::NBE => !(f(Flags::C) || f(Flags::Z)),
+            Cc::S => f(Flags::S),
+            Cc::NS => !f(Flags::S),
+            Cc::P => f(Flags::P),
+            Cc::NP => !f(Flags::P),
+            Cc::L => f(Flags::S) != f(Flags::O),
+            Cc::NL => f(Flags::S) == f(Flags::O),
+            Cc::LE => f(Flags::Z) || f(Flags::S) != f(Flags::O),
+            Cc::NLE => !(f(Flags::Z) || f(Flags::S) != f(Flags::O)),
+        }
+    }
+}
+
 impl From<u8> for Cc {
     fn from(b: u8) -> Self {
         match b {
@@ -138,7 +270,7 @@ pub enum Invalid {
     UnexpectedBytes(u8, u8),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Arg {
     Reg8(Reg8),
     Reg16(Reg16),
@@ -183,9 +315,9 @@ pub enum Reg8 {
     BH = 7,
 }
 
-impl Into<u8> for Reg8 {
-    fn into(self) -> u8 {
-        self as u8
+impl From<Reg8> for u8 {
+    fn from(r: Reg8) -> u8 {
+        r as u8
     }
 }
 
@@ -218,9 +350,9 @@ pub enum Reg16 {
     DI = 7,
 }
 
-impl Into<u8> for Reg16 {
-    fn into(self) -> u8 {
-        self as u8
+impl From<Reg16> for u8 {
+    fn from(r: Reg16) -> u8 {
+        r as u8
     }
 }
 
@@ -240,10 +372,50 @@ impl From<u8> for Reg16 {
     }
 }
 
-#[derive(Debug, PartialEq)]
+// effective address of a memory operand, as encoded by the modrm byte
+// (8-bit and 16-bit displacements are kept apart so that the original
+// encoding can be reproduced)
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Mem {
-    Reg(Reg16),
-    RegOff(Reg16, i8),
-    RegOff16(Reg16, i16),
-    RegOff32(Reg16, i32),
+    Direct(u16),                      // [disp16]
+    Reg(Reg16),                       // [si], [di], [bx]
+    RegOff8(Reg16, i8),               // [si+disp8], [di+disp8], [bp+disp8], [bx+disp8]
+    RegOff16(Reg16, i16),             // [si+disp16], [di+disp16], [bp+disp16], [bx+disp16]
+    RegReg(Reg16, Reg16),             // [bx+si], [bx+di], [bp+si], [bp+di]
+    RegRegOff8(Reg16, Reg16, i8),     // [bx+si+disp8], ...
+    RegRegOff16(Reg16, Reg16, i16),   // [bx+si+disp16], ...
+}
+
+impl Mem {
+    // base register, if any
+    pub fn base(&self) -> Option<Reg16> {
+        match *self {
+            Mem::Direct(_) => None,
+            Mem::Reg(r) | Mem::RegOff8(r, _) | Mem::RegOff16(r, _) => Some(r),
+            Mem::RegReg(b, _) | Mem::RegRegOff8(b, _, _) | Mem::RegRegOff16(b, _, _) => Some(b),
+        }
+    }
+
+    // index register, if any (only for the two registers forms)
+    pub fn index(&self) -> Option<Reg16> {
+        match *self {
+            Mem::RegReg(_, i) | Mem::RegRegOff8(_, i, _) | Mem::RegRegOff16(_, i, _) => Some(i),
+            _ => None,
+        }
+    }
+
+    // displacement, sign extended to 16 bits
+    pub fn disp(&self) -> u16 {
+        match *self {
+            Mem::Direct(d) => d,
+            Mem::RegOff8(_, d) | Mem::RegRegOff8(_, _, d) => d as i16 as u16,
+            Mem::RegOff16(_, d) | Mem::RegRegOff16(_, _, d) => d as u16,
+            Mem::Reg(_) | Mem::RegReg(_, _) => 0,
+        }
+    }
+
+    // true if the default segment is SS (bp based addressing)
+    pub fn uses_bp(&self) -> bool {
+        self.base() == Some(Reg16::BP)
+    }
 }
@@ -0,0 +1,74 @@
+use crate::dec::{DecodeError, Decoder};
+use crate::op::Inst;
+use crate::MemAddrT;
+
+// an instruction decoded from a slice, with where it was found
+#[derive(Debug)]
+pub struct Decoded<'a> {
+    pub inst: Inst,
+    // address of the first byte (origin + offset)
+    pub addr: MemAddrT,
+    // position of the first byte in the slice
+    pub offset: usize,
+    // the bytes of the instruction, borrowed from the slice
+    pub bytes: &'a [u8],
+}
+
+// decodes instructions from a byte slice loaded at `origin`. used as an
+// iterator it performs a linear sweep: it stops at the end of the slice, or
+// after returning Err(Truncated) for an incomplete last instruction
+pub struct SliceDecoder<'a> {
+    buf: &'a [u8],
+    origin: MemAddrT,
+    pos: usize,
+}
+
+impl<'a> SliceDecoder<'a> {
+    pub fn new(buf: &'a [u8], origin: MemAddrT) -> SliceDecoder<'a> {
+        SliceDecoder {
+            buf,
+            origin,
+            pos: 0,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    // moves to another offset in the slice (past the end is the end)
+    pub fn seek(&mut self, pos: usize) {
+        self.pos = pos.min(self.buf.len());
+    }
+
+    // decodes the instruction at the current position and moves past it
+    pub fn decode(&mut self) -> Result<Decoded<'a>, DecodeError> {
+        let offset = self.pos;
+        let line = self.buf[offset..].iter().copied();
+        let inst = match Decoder::new(line).next_i() {
+            Ok(inst) => inst,
+            Err(err) => {
+                self.pos = self.buf.len();
+                return Err(err);
+            }
+        };
+        self.pos += inst.size as usize;
+        Ok(Decoded {
+            inst,
+            addr: self.origin + offset as MemAddrT,
+            offset,
+            bytes: &self.buf[offset..self.pos],
+        })
+    }
+}
+
+impl<'a> Iterator for SliceDecoder<'a> {
+    type Item = Result<Decoded<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.decode() {
+            Err(DecodeError::EndOfInput) => None,
+            r => Some(r),
+        }
+    }
+}
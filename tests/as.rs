@@ -0,0 +1,360 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+// assembles `src` with as8086, returns the binary or the error output
+fn assemble(name: &str, src: &str) -> Result<Vec<u8>, String> {
+    assemble_with_warnings(name, src).map(|(bin, _)| bin)
+}
+
+// same, with the warnings of a successful run
+fn assemble_with_warnings(name: &str, src: &str) -> Result<(Vec<u8>, String), String> {
+    let dir = std::env::temp_dir().join(format!("as8086-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+    let asm = dir.join("t.asm");
+    let bin = dir.join("t.bin");
+    std::fs::write(&asm, src).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_as8086"))
+        .arg("-o")
+        .arg(&bin)
+        .arg(&asm)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr).to_string();
+    let res = if out.status.success() {
+        Ok((std::fs::read(&bin).unwrap(), stderr))
+    } else {
+        Err(stderr)
+    };
+    std::fs::remove_dir_all(&dir).unwrap();
+    res
+}
+
+#[test]
+fn assemble_test_rom() {
+    // as produced by nasm -f bin
+    let expected = [
+        0xb2, 0x07, 0x88, 0xd0, 0x88, 0xec, 0x08, 0xfc, 0x59, 0x38, 0xc8, 0x75, 0xf3, 0xf4,
+    ];
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let src = std::fs::read_to_string(root.join("tests/1.asm")).unwrap();
+    assert_eq!(assemble("rom", &src).unwrap(), expected);
+}
+
+#[test]
+fn assemble_labels_and_data() {
+    let src = "
+        ORG 100h
+        VAL EQU 0x1234
+        %define TWICE 2
+    start:
+        mov ax, VAL
+        mov bx, data        ; forward: not known in the first pass
+        jmp short .next
+    .next:
+        call start.next
+        times TWICE nop
+        add ax, 1           ; sign extended imm8, as nasm does
+    data:
+        db 'ab', 0
+        dw 1, 'x'
+    ";
+    let bin = assemble("labels", src).unwrap();
+    assert_eq!(
+        bin,
+        [
+            0xb8, 0x34, 0x12, // mov ax, VAL
+            0xbb, 0x10, 0x01, // mov bx, data
+            0xeb, 0x00, // jmp short .next
+            0xe8, 0xfd, 0xff, // call .next
+            0x90, 0x90, // times 2 nop
+            0x83, 0xc0, 0x01, // add ax, 1
+            b'a', b'b', 0, 0x01, 0x00, b'x', 0x00,
+        ]
+    );
+}
+
+#[test]
+fn assemble_errors() {
+    for (src, msg) in [
+        ("inc [bx]", "t.asm:1: error: operation size not specified"),
+        (
+            "jz away\ntimes 200 nop\naway:",
+            "t.asm:1: error: short jump out of range",
+        ),
+        (
+            "mov ax, nowhere",
+            "t.asm:1: error: symbol `nowhere` not defined",
+        ),
+        ("a: nop\na: nop", "t.asm:2: error: label `a` redefined"),
+        (
+            "%include \"missing.inc\"",
+            "t.asm:1: error: unable to find `missing.inc`",
+        ),
+    ] {
+        let err = assemble("errors", src).unwrap_err();
+        assert!(err.contains(msg), "{:?}: {}", src, err);
+    }
+}
+
+#[test]
+fn assemble_macros() {
+    let src = "
+    %macro fill 1-2 0x90
+        times %1 db %2
+    %endmacro
+    %macro count 1
+    %%top:
+        dec %1
+        jnz %%top
+    %endmacro
+        fill 2
+        fill 1, 0xcc
+        count cx
+        count dx            ; %%top is unique to each expansion
+    %ifdef FOO
+        nop
+    %elifidn cx, CX         ; case sensitive
+        hlt
+    %elifidni cx, CX
+        int3
+    %endif
+    ";
+    let bin = assemble("macros", src).unwrap();
+    assert_eq!(
+        bin,
+        [
+            0x90, 0x90, 0xcc, // fill
+            0x49, 0x75, 0xfd, // count cx
+            0x4a, 0x75, 0xfd, // count dx
+            0xcc, // int3
+        ]
+    );
+}
+
+#[test]
+fn assemble_expect_inc() {
+    // the line number comes from the line calling the macro
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let src = format!(
+        "%define DEBUG\n%include \"{}\"\nhlt\nEXPECT __FILE__, __LINE__, AX, 1\n",
+        root.join("tests/expect.inc").display()
+    );
+    let bin = assemble("expect", &src).unwrap();
+    assert_eq!(bin[..5], [0xf4, 4, 0, b'^', b'^']);
+    let name = 5 + bin[5..].iter().position(|&b| b == 0).unwrap();
+    assert!(bin[5..name].ends_with(b"t.asm"));
+    assert_eq!(bin[name + 1..], [4, 0, b'A', b'X', 1, 0]);
+}
+
+#[test]
+fn assemble_macro_diagnostics() {
+    let src = "%macro bad 0\n  %error oops\n%endmacro\n%macro outer 0\n  bad\n%endmacro\nouter\n";
+    let err = assemble("diag", src).unwrap_err();
+    for msg in [
+        "t.asm:2: error: oops",
+        "t.asm:5: note: in expansion of macro `bad`",
+        "t.asm:7: note: in expansion of macro `outer`",
+    ] {
+        assert!(err.contains(msg), "{}", err);
+    }
+
+    let err = assemble("params", "%macro m 2\n%endmacro\nm 1\n").unwrap_err();
+    assert!(
+        err.contains("t.asm:3: error: macro `m` exists, but not taking 1 parameters"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn assemble_listing_and_map() {
+    let dir = std::env::temp_dir().join(format!("as8086-{}-list", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let src = "
+        org 100h
+    %macro twice 1
+        %1
+        %1
+    %endmacro
+    start:
+        mov ax, 1
+        twice nop
+    data:
+        times 4 db 0
+        db 'abcdefghijk'
+    ";
+    std::fs::write(dir.join("t.asm"), src).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_as8086"))
+        .current_dir(&dir)
+        .args(["-o", "t.bin", "-l", "t.lst", "-m", "t.map", "t.asm"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let lst = std::fs::read_to_string(dir.join("t.lst")).unwrap();
+    let map = std::fs::read_to_string(dir.join("t.map")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    for line in [
+        "     7                                      start:",
+        "     8 00000100 B80100                          mov ax, 1",
+        "     9                                          twice nop",
+        "    10 00000103 90                  <1>         nop",
+        "    13 00000105 00<rep 4h>                      times 4 db 0",
+        "    14 00000109 616263646566676869-             db 'abcdefghijk'",
+        "    14 00000112 6A6B",
+    ] {
+        assert!(lst.lines().any(|l| l == line), "{:?} not in\n{}", line, lst);
+    }
+    // org 100h: the labels are 0x100 bytes after the segment
+    assert_eq!(map, "; symbols of t.asm\nFFF0:0100 start\nFFF0:0105 data\n");
+}
+
+#[test]
+fn assemble_expressions() {
+    let src = "
+        mov ax, 'AB'
+        mov bx, 1 << 4 | 3
+        mov cx, ~0 & 0xff ^ 1
+        mov dx, -7 // 2
+        mov si, -7 %% 4
+        mov di, !0 + (0x80 >> 3) * 2
+    ";
+    let bin = assemble("expr", src).unwrap();
+    assert_eq!(
+        bin,
+        [
+            0xb8, 0x41, 0x42, // 'AB'
+            0xbb, 0x13, 0x00, // 0x13
+            0xb9, 0xfe, 0x00, // 0xfe
+            0xba, 0xfd, 0xff, // -3
+            0xbe, 0xfd, 0xff, // -3
+            0xbf, 0x21, 0x00, // 0x21
+        ]
+    );
+}
+
+#[test]
+fn assemble_passes() {
+    // forward jumps are short when they can be, near when they must
+    let src = "
+        jmp a
+        jmp b
+    a:  times 200 nop
+    b:  ret
+    ";
+    let bin = assemble("passes", src).unwrap();
+    assert_eq!(bin[..5], [0xeb, 0x03, 0xe9, 0xc8, 0x00]);
+    assert_eq!(bin.len(), 5 + 200 + 1);
+
+    // the displacement fits in a byte only with the 16 bit form: the
+    // sizes would not settle without pinning it to the widest form
+    let src = "a: mov ax, [bx + 200 - (c - a)]\ntimes 69 nop\nc:";
+    let bin = assemble("pinned", src).unwrap();
+    assert_eq!(bin[..4], [0x8b, 0x87, 0x7f, 0x00]);
+
+    let err = assemble("far", "jz b\ntimes 200 nop\nb:").unwrap_err();
+    assert!(
+        err.contains("t.asm:1: error: short jump out of range (200)"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn assemble_bios() {
+    // the reset vector is at f000:fff0
+    let src = "
+        cpu 8086
+        org 0
+    start:
+        mov ax, cs
+        jmp start
+        times 0xfff0-($-$$) db 0xff
+    reset:
+        jmp 0xf000:start
+        times 0x10000-($-$$) db 0
+    ";
+    let bin = assemble("bios", src).unwrap();
+    assert_eq!(bin.len(), 0x10000);
+    assert_eq!(bin[..4], [0x8c, 0xc8, 0xeb, 0xfc]);
+    assert_eq!(bin[0xfff0..0xfff5], [0xea, 0x00, 0x00, 0x00, 0xf0]);
+}
+
+#[test]
+fn assemble_sections() {
+    let src = "
+        org 0x100
+    section .text
+        mov ax, SEG far_data
+        mov bx, far_data WRT .text
+        mov cx, section..data.start
+    section .data align=16 vstart=0
+    far_data:
+        dw $, $$
+    section .bss
+    buf: resb 4
+    section .text
+        mov dx, buf
+    ";
+    let bin = assemble("sections", src).unwrap();
+    assert_eq!(
+        bin,
+        [
+            0xb8, 0x01, 0x00, // seg far_data: .data is at 0x10 in the binary
+            0xbb, 0x10, 0x01, // far_data wrt .text
+            0xb9, 0x10, 0x01, // section..data.start
+            0xba, 0x14, 0x01, // buf, after .data
+            0, 0, 0, 0, // .data is aligned
+            0, 0, 0, 0, // $ and $$ from vstart
+        ]
+    );
+}
+
+#[test]
+fn assemble_strict() {
+    let src = "
+        add ax, 7
+        add bx, strict word 7
+        mov al, [byte bx]
+        mov al, [word bx+1]
+        mov byte [word es:bp+2], 3
+        jmp near next
+    next:
+    ";
+    let bin = assemble("strict", src).unwrap();
+    assert_eq!(
+        bin,
+        [
+            0x83, 0xc0, 0x07, // the byte form
+            0x81, 0xc3, 0x07, 0x00, // the word form, kept
+            0x8a, 0x47, 0x00, // a zero disp8
+            0x8a, 0x87, 0x01, 0x00, // a disp16
+            0x26, 0xc6, 0x86, 0x02, 0x00, 0x03, // the size, then the pin
+            0xe9, 0x00, 0x00, // not short
+        ]
+    );
+}
+
+#[test]
+fn assemble_displacement_range() {
+    let src = "
+        mov ax, [bx+70000]
+        mov ax, [bx+0xffff]
+        mov ax, [bx-0x8000]
+    ";
+    let (bin, warns) = assemble_with_warnings("disp", src).unwrap();
+    assert_eq!(
+        bin,
+        [
+            0x8b, 0x87, 0x70, 0x11, // truncated to 16 bits
+            0x8b, 0x47, 0xff, // -1
+            0x8b, 0x87, 0x00, 0x80,
+        ]
+    );
+    assert!(
+        warns.contains("word value 0x11170 exceeds bounds"),
+        "{}",
+        warns
+    );
+    assert_eq!(warns.lines().count(), 1, "{}", warns);
+}
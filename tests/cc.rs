@@ -0,0 +1,79 @@
+use lib8086::{Cc, Flags};
+
+// builds a FLAGS image from the five flags the conditions depend on
+fn flags(c: bool, p: bool, z: bool, s: bool, o: bool) -> u16 {
+    let mut f = 0;
+    for (on, flag) in [(c, Flags::C), (p, Flags::P), (z, Flags::Z), (s, Flags::S), (o, Flags::O)] {
+        if on {
+            f |= flag.mask();
+        }
+    }
+    f
+}
+
+// the truth table as the intel manual gives it for the low nibble of the
+// 7x opcodes: bits 3-1 select the test, bit 0 negates it
+fn expected(n: u8, c: bool, p: bool, z: bool, s: bool, o: bool) -> bool {
+    let t = match n >> 1 {
+        0 => o,
+        1 => c,
+        2 => z,
+        3 => c || z,
+        4 => s,
+        5 => p,
+        6 => s != o,
+        7 => z || s != o,
+        _ => unreachable!(),
+    };
+    t != (n & 1 == 1)
+}
+
+#[test]
+fn evaluate_all_conditions_and_flags() {
+    for n in 0..16u8 {
+        let cc = Cc::from(n);
+        for bits in 0..32u8 {
+            let (c, p, z, s, o) = (
+                bits & 1 != 0,
+                bits & 2 != 0,
+                bits & 4 != 0,
+                bits & 8 != 0,
+                bits & 16 != 0,
+            );
+            let f = flags(c, p, z, s, o);
+            let ex = expected(n, c, p, z, s, o);
+            assert_eq!(cc.evaluate(f), ex, "{:?} flags={:04X}", cc, f);
+
+            // AF, TF, IF, DF and the reserved bits never matter
+            let noise = Flags::A.mask() | Flags::T.mask() | Flags::I.mask() | Flags::D.mask() | 0xf002;
+            assert_eq!(cc.evaluate(f | noise), ex, "{:?} flags={:04X}", cc, f | noise);
+        }
+    }
+}
+
+#[test]
+fn evaluate_overflow_uses_of_not_cf() {
+    let of = Flags::O.mask();
+    let cf = Flags::C.mask();
+    assert!(Cc::O.evaluate(of));
+    assert!(!Cc::O.evaluate(cf));
+    assert!(Cc::NO.evaluate(cf));
+    assert!(!Cc::NO.evaluate(of));
+}
+
+#[test]
+fn evaluate_after_cmp() {
+    // cmp 1, 2: CF=1, SF=1, OF=0, ZF=0
+    let f = Flags::C.mask() | Flags::S.mask();
+    assert!(Cc::B.evaluate(f));
+    assert!(Cc::L.evaluate(f));
+    assert!(Cc::LE.evaluate(f));
+    assert!(Cc::NE.evaluate(f));
+    assert!(!Cc::NBE.evaluate(f));
+    assert!(!Cc::NLE.evaluate(f));
+
+    // cmp 7fffh, -1: signed 7fffh > -1, unsigned below. SF=1, OF=1, CF=1
+    let f = Flags::C.mask() | Flags::S.mask() | Flags::O.mask();
+    assert!(Cc::NLE.evaluate(f));
+    assert!(Cc::B.evaluate(f));
+}
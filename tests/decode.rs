@@ -0,0 +1,377 @@
+use lib8086::{
+    Arg, DecodeError, Decoder, Inst, Invalid, Mem, Op, Reg16, Reg8, Rep, SliceDecoder, Sreg, Width, MAX_INST_LEN,
+};
+
+// small deterministic generator, so that a failure can be reproduced
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+// decodes the whole buffer, checking that the sizes add up
+fn decode_all(buf: &[u8]) -> (Vec<Inst>, DecodeError) {
+    let mut it = buf.iter().cloned();
+    let mut dec = Decoder::new(&mut it);
+    let mut insts = vec![];
+    let mut total = 0;
+    loop {
+        match dec.next_i() {
+            Ok(inst) => {
+                assert!(inst.size >= 1 && inst.size as usize <= MAX_INST_LEN, "{:02x?}: {:?}", buf, inst);
+                total += inst.size as usize;
+                insts.push(inst);
+            }
+            Err(err) => {
+                if let DecodeError::Truncated(n) = err {
+                    total += n;
+                }
+                assert_eq!(total, buf.len(), "{:02x?}: {:?}", buf, err);
+                return (insts, err);
+            }
+        }
+    }
+}
+
+#[test]
+fn decode_empty() {
+    let (insts, err) = decode_all(&[]);
+    assert!(insts.is_empty());
+    assert_eq!(err, DecodeError::EndOfInput);
+}
+
+#[test]
+fn decode_truncated() {
+    // mov ax, imm16 without its last byte
+    let (insts, err) = decode_all(&[0x90, 0xb8, 0x34]);
+    assert_eq!(insts.len(), 1);
+    assert_eq!(err, DecodeError::Truncated(2));
+
+    // prefixes alone are not an instruction
+    let (_, err) = decode_all(&[0x26, 0xf3]);
+    assert_eq!(err, DecodeError::Truncated(2));
+
+    // add [bx+si+disp16], imm8 cut in its displacement
+    let (_, err) = decode_all(&[0x83, 0x80, 0x00]);
+    assert_eq!(err, DecodeError::Truncated(3));
+}
+
+#[test]
+fn decode_prefix_overflow() {
+    for buf in [
+        [0x26, 0x26, 0x90],
+        [0x26, 0x2e, 0x90],
+        [0xf3, 0xf2, 0x90],
+        [0xf0, 0xf0, 0x90],
+    ] {
+        let mut it = buf.iter().cloned();
+        let mut dec = Decoder::new(&mut it);
+        let inst = dec.next_i().unwrap();
+        assert_eq!(inst.op, Op::Invalid(Invalid::TooManyPrefix), "{:02x?}", buf);
+        assert_eq!(inst.size, 2);
+    }
+
+    // a long run of alternating prefixes does not recurse forever
+    let buf: Vec<u8> = (0..100_000).map(|i| [0x26, 0x2e, 0x36, 0x3e][i % 4]).collect();
+    decode_all(&buf);
+}
+
+#[test]
+fn decode_undefined_opcodes() {
+    for b0 in [0x0f, 0x60, 0x6f, 0xc0, 0xc1, 0xc8, 0xc9, 0xd6, 0xf1] {
+        let buf = [b0, 0, 0, 0, 0, 0];
+        let (insts, _) = decode_all(&buf);
+        assert!(matches!(insts[0].op, Op::Invalid(_)), "{:02x}: {:?}", b0, insts[0]);
+    }
+
+    // operands that must be in memory: lea, les, lds, call/jmp far
+    for buf in [[0x8d, 0xc0], [0xc4, 0xc0], [0xc5, 0xc0], [0xff, 0xd8], [0xff, 0xe8]] {
+        let (insts, _) = decode_all(&buf);
+        assert!(matches!(insts[0].op, Op::Invalid(_)), "{:02x?}: {:?}", buf, insts[0]);
+    }
+}
+
+#[test]
+fn decode_all_two_byte_sequences() {
+    for w in 0..=0xffffu16 {
+        decode_all(&w.to_le_bytes());
+    }
+}
+
+#[test]
+fn decode_random_sequences() {
+    let mut rng = XorShift(0x8086_8088_0186_0286);
+    for _ in 0..100_000 {
+        let len = (rng.next() % 24) as usize;
+        let buf: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+        decode_all(&buf);
+    }
+}
+
+#[test]
+fn decode_slice_positions() {
+    // nop; mov ax, 1234h; es rep movsw; then a truncated mov ax
+    let buf = [0x90, 0xb8, 0x34, 0x12, 0x26, 0xf3, 0xa5, 0xb8, 0x00];
+    let mut sweep = SliceDecoder::new(&buf, 0x7c00);
+
+    let d = sweep.next().unwrap().unwrap();
+    assert_eq!((d.addr, d.offset, d.bytes), (0x7c00, 0, &buf[0..1]));
+    assert_eq!(d.inst.op, Op::Nop);
+
+    let d = sweep.next().unwrap().unwrap();
+    assert_eq!((d.addr, d.offset, d.bytes), (0x7c01, 1, &buf[1..4]));
+    assert_eq!(d.inst.op, Op::Mov(Arg::Reg16(Reg16::AX), Arg::Uimm16(0x1234)));
+
+    let d = sweep.next().unwrap().unwrap();
+    assert_eq!((d.addr, d.offset, d.bytes), (0x7c04, 4, &buf[4..7]));
+    assert_eq!(d.inst.op, Op::Movs);
+
+    assert_eq!(sweep.next().unwrap().unwrap_err(), DecodeError::Truncated(2));
+    assert!(sweep.next().is_none());
+
+    // seeking back decodes again from there
+    sweep.seek(1);
+    assert_eq!(sweep.decode().unwrap().bytes, &buf[1..4]);
+    assert_eq!(sweep.position(), 4);
+}
+
+#[test]
+fn decode_slice_matches_iterator() {
+    let mut rng = XorShift(0x0123_4567_89ab_cdef);
+    for _ in 0..10_000 {
+        let len = (rng.next() % 32) as usize;
+        let buf: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+        let (insts, err) = decode_all(&buf);
+        let mut sweep = SliceDecoder::new(&buf, 0);
+        for inst in insts {
+            let d = sweep.next().unwrap().unwrap();
+            assert_eq!(d.inst, inst);
+            assert_eq!(d.bytes.len(), inst.size as usize);
+        }
+        match err {
+            DecodeError::EndOfInput => assert!(sweep.next().is_none()),
+            err => assert_eq!(sweep.next().unwrap().unwrap_err(), err),
+        }
+    }
+}
+
+// decodes one instruction that spans the whole buffer
+fn decode(buf: &[u8]) -> Inst {
+    let mut it = buf.iter().cloned();
+    let inst = Decoder::new(&mut it).next_i().unwrap();
+    assert_eq!(inst.size as usize, buf.len(), "{:02x?}: {:?}", buf, inst);
+    inst
+}
+
+#[test]
+fn decode_modrm_memory() {
+    use Reg16::*;
+    let ax = Arg::Reg16(AX);
+    let table: &[(&[u8], Op)] = &[
+        (&[0x8b, 0x00], Op::Mov(ax, Arg::Mem(Mem::RegReg(BX, SI)))),
+        (&[0x8b, 0x01], Op::Mov(ax, Arg::Mem(Mem::RegReg(BX, DI)))),
+        (&[0x8b, 0x02], Op::Mov(ax, Arg::Mem(Mem::RegReg(BP, SI)))),
+        (&[0x8b, 0x03], Op::Mov(ax, Arg::Mem(Mem::RegReg(BP, DI)))),
+        (&[0x8b, 0x04], Op::Mov(ax, Arg::Mem(Mem::Reg(SI)))),
+        (&[0x8b, 0x05], Op::Mov(ax, Arg::Mem(Mem::Reg(DI)))),
+        // mod=0, rm=6 is a direct address, not [bp]
+        (
+            &[0x8b, 0x06, 0x34, 0x12],
+            Op::Mov(ax, Arg::Mem(Mem::Direct(0x1234))),
+        ),
+        (&[0x8b, 0x07], Op::Mov(ax, Arg::Mem(Mem::Reg(BX)))),
+        // disp8, sign extended
+        (
+            &[0x8b, 0x40, 0xfe],
+            Op::Mov(ax, Arg::Mem(Mem::RegRegOff8(BX, SI, -2))),
+        ),
+        (
+            &[0x8b, 0x46, 0x00],
+            Op::Mov(ax, Arg::Mem(Mem::RegOff8(BP, 0))),
+        ),
+        (
+            &[0x8b, 0x47, 0x7f],
+            Op::Mov(ax, Arg::Mem(Mem::RegOff8(BX, 0x7f))),
+        ),
+        // disp16
+        (
+            &[0x8b, 0x83, 0x34, 0x12],
+            Op::Mov(ax, Arg::Mem(Mem::RegRegOff16(BP, DI, 0x1234))),
+        ),
+        (
+            &[0x8b, 0x86, 0x00, 0x80],
+            Op::Mov(ax, Arg::Mem(Mem::RegOff16(BP, -0x8000))),
+        ),
+        // mod=3 is a register
+        (&[0x8b, 0xc3], Op::Mov(ax, Arg::Reg16(BX))),
+        // the reg field, and the byte registers
+        (
+            &[0x8b, 0x1e, 0x00, 0x01],
+            Op::Mov(Arg::Reg16(BX), Arg::Mem(Mem::Direct(0x100))),
+        ),
+        (
+            &[0x8a, 0x4f, 0x10],
+            Op::Mov(Arg::Reg8(Reg8::CL), Arg::Mem(Mem::RegOff8(BX, 0x10))),
+        ),
+        (
+            &[0x88, 0x62, 0xff],
+            Op::Mov(Arg::Mem(Mem::RegRegOff8(BP, SI, -1)), Arg::Reg8(Reg8::AH)),
+        ),
+    ];
+    for (buf, op) in table {
+        assert_eq!(&decode(buf).op, op, "{:02x?}", buf);
+    }
+
+    // the segment prefix is kept apart from the operand
+    let inst = decode(&[0x26, 0x8b, 0x07]);
+    assert_eq!(inst.seg, Some(Sreg::ES));
+    assert_eq!(inst.op, Op::Mov(ax, Arg::Mem(Mem::Reg(BX))));
+}
+
+#[test]
+fn decode_alu_operand_order() {
+    use Reg16::*;
+    use Reg8::*;
+    let bx = Arg::Mem(Mem::Reg(BX));
+    let table: &[(&[u8], Op)] = &[
+        // d=0: r/m, reg. d=1: reg, r/m
+        (&[0x00, 0xd8], Op::Add(Arg::Reg8(AL), Arg::Reg8(BL))),
+        (&[0x02, 0xd8], Op::Add(Arg::Reg8(BL), Arg::Reg8(AL))),
+        (&[0x09, 0xd8], Op::Or(Arg::Reg16(AX), Arg::Reg16(BX))),
+        (&[0x0a, 0x07], Op::Or(Arg::Reg8(AL), bx)),
+        (&[0x11, 0x07], Op::Adc(bx, Arg::Reg16(AX))),
+        (&[0x12, 0x07], Op::Adc(Arg::Reg8(AL), bx)),
+        (&[0x19, 0x07], Op::Sbb(bx, Arg::Reg16(AX))),
+        (&[0x1a, 0x07], Op::Sbb(Arg::Reg8(AL), bx)),
+        (&[0x21, 0x07], Op::And(bx, Arg::Reg16(AX))),
+        (&[0x22, 0x07], Op::And(Arg::Reg8(AL), bx)),
+        (&[0x29, 0x07], Op::Sub(bx, Arg::Reg16(AX))),
+        (&[0x2a, 0x07], Op::Sub(Arg::Reg8(AL), bx)),
+        (&[0x31, 0x07], Op::Xor(bx, Arg::Reg16(AX))),
+        (&[0x32, 0x07], Op::Xor(Arg::Reg8(AL), bx)),
+        (&[0x39, 0x07], Op::Cmp(bx, Arg::Reg16(AX))),
+        (&[0x3a, 0x07], Op::Cmp(Arg::Reg8(AL), bx)),
+        // the immediate groups: 0x82 is 0x80
+        (&[0x80, 0x07, 0x05], Op::Add(bx, Arg::Uimm8(5))),
+        (&[0x82, 0xc9, 0x05], Op::Or(Arg::Reg8(CL), Arg::Uimm8(5))),
+        (&[0x81, 0xfb, 0x34, 0x12], Op::Cmp(Arg::Reg16(BX), Arg::Uimm16(0x1234))),
+        (&[0x83, 0xeb, 0xfe], Op::Sub(Arg::Reg16(BX), Arg::Imm8(-2))),
+        // xchg r/m8, mov to and from a segment register, lea and pop r/m16
+        (&[0x86, 0xd8], Op::Xchg(Arg::Reg8(AL), Arg::Reg8(BL))),
+        (&[0x8c, 0xd8], Op::Mov(Arg::Reg16(AX), Arg::Sreg(Sreg::DS))),
+        (&[0x8c, 0x07], Op::Mov(bx, Arg::Sreg(Sreg::ES))),
+        (&[0x8e, 0xd0], Op::Mov(Arg::Sreg(Sreg::SS), Arg::Reg16(AX))),
+        (&[0x8d, 0x47, 0x02], Op::Lea(Arg::Reg16(AX), Arg::Mem(Mem::RegOff8(BX, 2)))),
+        (&[0x8f, 0x07], Op::Pop(bx)),
+    ];
+    for (buf, op) in table {
+        assert_eq!(&decode(buf).op, op, "{:02x?}", buf);
+    }
+
+    // there are only four segment registers
+    assert!(matches!(decode(&[0x8e, 0xe0]).op, Op::Invalid(_)));
+}
+
+#[test]
+fn decode_upper_rows() {
+    use Reg16::*;
+    use Reg8::*;
+    let bx = Arg::Mem(Mem::Reg(BX));
+    let table: &[(&[u8], Op)] = &[
+        (&[0x90], Op::Nop),
+        (&[0x93], Op::Xchg(Arg::Reg16(AX), Arg::Reg16(BX))),
+        (&[0x98], Op::Cbw),
+        (&[0x99], Op::Cwd),
+        (&[0x9a, 0x78, 0x56, 0x34, 0x12], Op::CallFar(Arg::Uimm16(0x1234), Arg::Uimm16(0x5678))),
+        (&[0x9b], Op::Wait),
+        (&[0x9c], Op::Pushf),
+        (&[0x9d], Op::Popf),
+        (&[0x9e], Op::Sahf),
+        (&[0x9f], Op::Lahf),
+        (&[0xa0, 0x34, 0x12], Op::Mov(Arg::Reg8(AL), Arg::Mem(Mem::Direct(0x1234)))),
+        (&[0xa3, 0x34, 0x12], Op::Mov(Arg::Mem(Mem::Direct(0x1234)), Arg::Reg16(AX))),
+        (&[0xa4], Op::Movs),
+        (&[0xa7], Op::Cmps),
+        (&[0xa8, 0x05], Op::Test(Arg::Reg8(AL), Arg::Uimm8(5))),
+        (&[0xa9, 0x34, 0x12], Op::Test(Arg::Reg16(AX), Arg::Uimm16(0x1234))),
+        (&[0xaa], Op::Stos),
+        (&[0xad], Op::Lods),
+        (&[0xae], Op::Scas),
+        (&[0xb1, 0x05], Op::Mov(Arg::Reg8(CL), Arg::Uimm8(5))),
+        (&[0xbc, 0x34, 0x12], Op::Mov(Arg::Reg16(SP), Arg::Uimm16(0x1234))),
+        (&[0xc2, 0x04, 0x00], Op::RetImm(4)),
+        (&[0xc3], Op::Ret),
+        (&[0xc4, 0x1c], Op::Les(Arg::Reg16(BX), Arg::Mem(Mem::Reg(SI)))),
+        (&[0xc5, 0x3f], Op::Lds(Arg::Reg16(DI), bx)),
+        (&[0xc6, 0x07, 0x05], Op::Mov(bx, Arg::Uimm8(5))),
+        (&[0xc7, 0x07, 0x34, 0x12], Op::Mov(bx, Arg::Uimm16(0x1234))),
+        (&[0xca, 0x02, 0x00], Op::RetfImm(2)),
+        (&[0xcb], Op::Retf),
+        (&[0xcc], Op::Int3),
+        (&[0xcd, 0x21], Op::Int(0x21)),
+        (&[0xce], Op::Into),
+        (&[0xcf], Op::Iret),
+        (&[0xd0, 0xe0], Op::Shl(Arg::Reg8(AL), Arg::Uimm8(1))),
+        (&[0xd3, 0xf8], Op::Sar(Arg::Reg16(AX), Arg::Reg8(CL))),
+        (&[0xd1, 0x0f], Op::Ror(bx, Arg::Uimm8(1))),
+        (&[0xd2, 0xd3], Op::Rcl(Arg::Reg8(BL), Arg::Reg8(CL))),
+        (&[0xd0, 0xf0], Op::Setmo(Arg::Reg8(AL), Arg::Uimm8(1))),
+        (&[0xd4, 0x0a], Op::Aam(10)),
+        (&[0xd5, 0x0a], Op::Aad(10)),
+        (&[0xd7], Op::Xlat),
+        (&[0xd9, 0x07], Op::Esc(0x08, bx)),
+        (&[0xe0, 0xfe], Op::Loopnz(-2)),
+        (&[0xe1, 0x10], Op::Loopz(0x10)),
+        (&[0xe2, 0xfe], Op::Loop(-2)),
+        (&[0xe3, 0x00], Op::Jcxz(0)),
+        (&[0xe4, 0x60], Op::In(Arg::Reg8(AL), Arg::Uimm8(0x60))),
+        (&[0xe7, 0x60], Op::Out(Arg::Uimm8(0x60), Arg::Reg16(AX))),
+        (&[0xe8, 0xfd, 0xff], Op::Call(Arg::Imm16(-3))),
+        (&[0xe9, 0x00, 0x80], Op::Jmp(Arg::Imm16(-0x8000))),
+        (&[0xea, 0x5b, 0xe0, 0x00, 0xf0], Op::JmpFar(Arg::Uimm16(0xf000), Arg::Uimm16(0xe05b))),
+        (&[0xeb, 0xfe], Op::Jmp(Arg::Imm8(-2))),
+        (&[0xec], Op::In(Arg::Reg8(AL), Arg::Reg16(DX))),
+        (&[0xef], Op::Out(Arg::Reg16(DX), Arg::Reg16(AX))),
+        (&[0xf4], Op::Hlt),
+        (&[0xf5], Op::Cmc),
+        (&[0xf6, 0xc3, 0x05], Op::Test(Arg::Reg8(BL), Arg::Uimm8(5))),
+        (&[0xf6, 0xd8], Op::Neg(Arg::Reg8(AL))),
+        (&[0xf6, 0x17], Op::Not(bx)),
+        (&[0xf7, 0xe3], Op::Mul(Arg::Reg16(BX))),
+        (&[0xf7, 0xeb], Op::Imul(Arg::Reg16(BX))),
+        (&[0xf7, 0xf3], Op::Div(Arg::Reg16(BX))),
+        (&[0xf7, 0xfb], Op::Idiv(Arg::Reg16(BX))),
+        (&[0xf8], Op::Clc),
+        (&[0xf9], Op::Stc),
+        (&[0xfa], Op::Cli),
+        (&[0xfb], Op::Sti),
+        (&[0xfc], Op::Cld),
+        (&[0xfd], Op::Std),
+        (&[0xfe, 0x07], Op::Inc(bx)),
+        (&[0xff, 0xc8], Op::Dec(Arg::Reg16(AX))),
+        (&[0xff, 0x17], Op::Call(bx)),
+        (&[0xff, 0x1f], Op::CallFarMem(bx)),
+        (&[0xff, 0x27], Op::Jmp(bx)),
+        (&[0xff, 0x2f], Op::JmpFarMem(bx)),
+        (&[0xff, 0x37], Op::Push(bx)),
+    ];
+    for (buf, op) in table {
+        assert_eq!(&decode(buf).op, op, "{:02x?}", buf);
+    }
+
+    // the width of the string operations
+    assert_eq!(decode(&[0xa4]).width, Width::Byte);
+    assert_eq!(decode(&[0xa5]).width, Width::Word);
+
+    // lock and rep are prefixes
+    let inst = decode(&[0xf0, 0xfe, 0x07]);
+    assert!(inst.lock);
+    assert_eq!(inst.op, Op::Inc(bx));
+    assert_eq!(decode(&[0xf3, 0xa5]).rep, Some(Rep::Rep));
+    assert_eq!(decode(&[0xf2, 0xae]).rep, Some(Rep::Repne));
+}
@@ -0,0 +1,369 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// disassembles `bin` with dis8086, returns the text of each instruction
+fn disassemble(name: &str, args: &[&str], bin: &[u8]) -> Vec<String> {
+    let dir = std::env::temp_dir().join(format!("dis8086-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("t.bin");
+    std::fs::write(&file, bin).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_dis8086"))
+        .args(args)
+        .arg(&file)
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(out.status.success());
+    // skips the address and the bytes, in a 16 columns field that 6 bytes
+    // overflow. the labels are alone on their lines
+    String::from_utf8(out.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| match &l[6.min(l.len())..] {
+            _ if l.ends_with(':') => l.to_string(),
+            rest if rest.as_bytes()[16] == b' ' => rest[17..].to_string(),
+            rest => rest[18..].to_string(),
+        })
+        .collect()
+}
+
+// runs dis8086 with `bin` on its standard input, returns its output
+fn dis8086(args: &[&str], bin: &[u8]) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dis8086"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(bin).unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+    let text = String::from_utf8(out.stdout).unwrap();
+    text.lines().map(str::to_string).collect()
+}
+
+#[test]
+fn disassemble_operands() {
+    let bin = [
+        0x8b, 0x00, // mov ax, [bx+si]
+        0x26, 0xc6, 0x46, 0x10, 0x05, // mov byte [es:bp+0x10], 5
+        0xc7, 0x06, 0x34, 0x12, 0x78, 0x56, // mov word [0x1234], 0x5678
+        0x83, 0x47, 0xfe, 0xfd, // add word [bx-2], byte -3
+        0xfe, 0x88, 0x34, 0x12, // dec byte [bx+si+0x1234]
+        0x8d, 0x73, 0xf0, // lea si, [bp+di-0x10]
+        0x36, 0xc4, 0x3f, // les di, [ss:bx]
+        0x2e, 0x8c, 0x06, 0x10, 0x00, // mov [cs:0x10], es
+        0x26, 0xac, // es lodsb
+        0xf3, 0xa7, // repe cmpsw
+        0xd5, 0x0a, // aad
+    ];
+    assert_eq!(
+        disassemble("operands", &[], &bin),
+        [
+            "mov ax, [bx+si]",
+            "mov byte [es:bp+0x10], 0x05",
+            "mov word [0x1234], 0x5678",
+            "add word [bx-0x02], byte -0x03",
+            "dec byte [bx+si+0x1234]",
+            "lea si, [bp+di-0x10]",
+            "les di, [ss:bx]",
+            "mov [cs:0x0010], es",
+            "es lodsb",
+            "repe cmpsw",
+            "aad",
+        ]
+    );
+}
+
+#[test]
+fn disassemble_branches() {
+    let bin = [
+        0xeb, 0xfe, // jmp short 0x0000
+        0x74, 0xfc, // je 0x0000
+        0xe8, 0xf9, 0xff, // call 0x0000
+        0xe9, 0x00, 0x80, // jmp 0x800a: the offset is relative to pc
+        0xff, 0x1f, // call far [bx]
+        0xff, 0x24, // jmp word [si]
+        0x9a, 0x78, 0x56, 0x34, 0x12, // call 0x1234:0x5678
+    ];
+    assert_eq!(
+        disassemble("branches", &[], &bin),
+        [
+            "jmp short 0x0000",
+            "je 0x0000",
+            "call 0x0000",
+            "jmp 0x800A",
+            "call far [bx]",
+            "jmp word [si]",
+            "call 0x1234:0x5678",
+        ]
+    );
+}
+
+const SYNTAX: [u8; 17] = [
+    0x26, 0xc6, 0x46, 0x10, 0x05, // mov byte [es:bp+0x10], 5
+    0x83, 0x47, 0xfe, 0xfd, // add word [bx-2], byte -3
+    0x8b, 0x00, // mov ax, [bx+si]
+    0xff, 0x1f, // call far [bx]
+    0xff, 0xd3, // call bx
+    0xec, // in al, dx
+    0x98, // cbw
+];
+
+#[test]
+fn disassemble_masm() {
+    let text = disassemble("masm", &["-syntax=masm"], &SYNTAX);
+    assert_eq!(
+        text,
+        [
+            "mov byte ptr es:[bp+10h], 05h",
+            "add word ptr [bx-02h], -03h",
+            "mov ax, [bx+si]",
+            "call dword ptr [bx]",
+            "call bx",
+            "in al, dx",
+            "cbw",
+        ]
+    );
+    let text = disassemble("masm-upper", &["-syntax=masm", "-uppercase"], &SYNTAX[..5]);
+    assert_eq!(text, ["MOV BYTE PTR ES:[BP+10H], 05H"]);
+    let bin = [
+        0xa1, 0x34, 0x12, // mov ax, [0x1234]
+        0xc7, 0x06, 0x34, 0x12, 0x01, 0x00, // mov word [0x1234], 1
+        0x26, 0xa0, 0x34, 0x12, // mov al, [es:0x1234]
+        0xcc, // int3
+    ];
+    let text = disassemble("masm-direct", &["-syntax=masm"], &bin);
+    assert_eq!(
+        text,
+        [
+            "mov ax, ds:[1234h]",
+            "mov word ptr ds:[1234h], 0001h",
+            "mov al, es:[1234h]",
+            "int 3",
+        ]
+    );
+}
+
+#[test]
+fn disassemble_att() {
+    let text = disassemble("att", &["-syntax=att"], &SYNTAX);
+    assert_eq!(
+        text,
+        [
+            "movb $0x05,%es:0x10(%bp)",
+            "addw $-0x03,-0x02(%bx)",
+            "mov (%bx,%si),%ax",
+            "lcall *(%bx)",
+            "call *%bx",
+            "in (%dx),%al",
+            "cbtw",
+        ]
+    );
+    let text = disassemble("att-hex", &["-syntax=att", "-hex=h"], &SYNTAX[..5]);
+    assert_eq!(text, ["movb $05h,%es:10h(%bp)"]);
+}
+
+#[test]
+fn disassemble_recursive() {
+    let bin = [
+        0xb8, 0x01, 0x00, // mov ax, 1
+        0xe8, 0x0a, 0x00, // call func
+        0x74, 0x01, // jz .skip
+        0x40, // inc ax
+        0xeb, 0x01, // .skip: jmp .end
+        0xff, // data, not reached
+        0xf4, // .end: hlt
+        0x41, 0x43, 0xc3, // 'AC', 0xc3
+        0x53, // func: push bx
+        0xe2, 0xfd, // loop func
+        0xc3, // ret
+        0x0f, // data
+    ];
+    assert_eq!(
+        disassemble("recursive", &["-recursive"], &bin),
+        [
+            "sub_0000:",
+            "mov ax, 0x0001",
+            "call sub_0010",
+            "je loc_0009",
+            "inc ax",
+            "loc_0009:",
+            "jmp short loc_000C",
+            "db 0xFF",
+            "loc_000C:",
+            "hlt",
+            "db 0x41, 0x43, 0xC3",
+            "sub_0010:",
+            "push bx",
+            "loop sub_0010",
+            "ret",
+            "db 0x0F",
+        ]
+    );
+
+    // another entry point makes the data after hlt code
+    let text = disassemble("entry", &["-entry=0:0D"], &bin);
+    assert_eq!(text[10..14], ["sub_000D:", "inc cx", "inc bx", "ret"]);
+}
+
+#[test]
+fn reassemble_round_trip() {
+    let bin = [
+        0x81, 0xc3, 0x07, 0x00, // add bx, strict word 7
+        0xe9, 0x01, 0x00, // jmp near 0x0008
+        0x90, // nop
+        0x8a, 0x47, 0x00, // mov al, [byte bx+0]
+        0x8a, 0x87, 0x01, 0x00, // mov al, [word bx+1]
+        0x87, 0xc3, // xchg bx, ax: the long form, given as db
+        0xd8, 0xc1, // esc: given as db
+        0x9a, 0x00, 0x90, 0x00, 0xf0, // call 0xF000:0x9000, out of the file
+        0xf4, // hlt
+        0x12, 0x34, // data
+    ];
+    let dir = std::env::temp_dir().join(format!("dis8086-{}-reassemble", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (file, src, out) = (dir.join("t.bin"), dir.join("t.asm"), dir.join("t2.bin"));
+    std::fs::write(&file, bin).unwrap();
+    let dis = Command::new(env!("CARGO_BIN_EXE_dis8086"))
+        .args(["--reassemble"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(dis.status.success());
+    std::fs::write(&src, &dis.stdout).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_as8086"))
+        .arg("-o")
+        .arg(&out)
+        .arg(&src)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let text = String::from_utf8(dis.stdout).unwrap();
+    let again = std::fs::read(&out).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(again, bin, "{}", text);
+    for line in [
+        "jmp near loc_0008",
+        "add bx, strict word 0x0007",
+        "db 0x87, 0xC3 ; xchg bx, ax",
+    ] {
+        assert!(text.contains(line), "{}", text);
+    }
+    // a far target is not relative, it cannot wrap
+    let call = "        call 0xF000:0x9000";
+    assert!(text.lines().any(|l| l == call), "{}", text);
+}
+
+#[test]
+fn disassemble_reset_vector() {
+    // a 64K rom at F000:0000, that starts at the reset vector
+    let mut bin = vec![0xff; 0x10000];
+    bin[0xfff0..0xfff5].copy_from_slice(&[0xea, 0x5b, 0xe0, 0x00, 0xf0]); // jmp 0xF000:0xE05B
+    bin[0xe05b..0xe05f].copy_from_slice(&[0xb0, 0x01, 0xeb, 0xfc]); // mov al, 1; jmp short $-2
+    let args = ["-recursive", "-origin=F000:0", "-output=raw"];
+    let text = dis8086(&args, &bin);
+    let code: Vec<&String> = text.iter().filter(|l| !l.starts_with("db")).collect();
+    assert_eq!(
+        code,
+        [
+            "loc_FE05B:",
+            "mov al, 0x01",
+            "jmp short loc_FE05B",
+            "sub_FFFF0:",
+            "jmp 0xF000:0xE05B",
+        ]
+    );
+
+    // the addresses, as segment:offset
+    let args = ["-origin=F000:0", "-start=FFF0", "-end=FFF5", "-output=hex"];
+    assert_eq!(dis8086(&args, &bin), ["F000:FFF0 ea 5b e0 00 f0"]);
+}
+
+#[test]
+fn disassemble_segment() {
+    // the relative branches wrap around in cs, which need not start on 64K
+    let args = ["-origin=07C0:0", "-output=raw"];
+    assert_eq!(dis8086(&args, &[0xeb, 0xfc]), ["jmp short 0x17BFE"]);
+
+    // 07C0:8400 is 10000, the jump goes back to 07C0:83F0
+    let mut bin = vec![0xff; 0x8402];
+    bin[0..3].copy_from_slice(&[0xe9, 0xfd, 0x83]); // jmp near 0x8400
+    bin[0x83f0] = 0xf4; // hlt
+    bin[0x8400..].copy_from_slice(&[0xeb, 0xee]); // jmp short 0x83F0
+    let args = ["-recursive", "-origin=07C0:0", "-output=raw"];
+    let text = dis8086(&args, &bin);
+    let code: Vec<&String> = text.iter().filter(|l| !l.starts_with("db")).collect();
+    assert_eq!(
+        code,
+        [
+            "sub_7C00:",
+            "jmp loc_10000",
+            "loc_FFF0:",
+            "hlt",
+            "loc_10000:",
+            "jmp short loc_FFF0",
+        ]
+    );
+}
+
+#[test]
+fn disassemble_options() {
+    let bin = [
+        b'H', b'D', b'R', b'!', // a header
+        0xb8, 0x01, 0x00, // mov ax, 1
+        0x40, // inc ax
+        0xeb, 0xfd, // jmp short 0x103
+    ];
+    let args = ["-skip=4", "-start=3", "-origin=100", "-output=json", "-"];
+    assert_eq!(
+        dis8086(&args, &bin),
+        [
+            r#"{"address":259,"bytes":"40","mnemonic":"inc","operands":["ax"],"size":1}"#,
+            r#"{"address":260,"bytes":"ebfd","mnemonic":"jmp","operands":["short 0x0103"],"size":2}"#,
+        ]
+    );
+    let args = ["-skip=4", "-end=4", "-output=raw"];
+    assert_eq!(dis8086(&args, &bin), ["mov ax, 0x0001", "inc ax"]);
+}
+
+#[test]
+fn disassemble_invalid() {
+    let bin = [
+        0x90, // nop
+        0x0f, // not an 8086 opcode
+        0x8e, 0xe0, // mov with a segment register out of range
+        0x90, // nop
+    ];
+    assert_eq!(
+        disassemble("invalid", &[], &bin),
+        ["nop", "db 0x0F", "db 0x8E, 0xE0", "nop"]
+    );
+    assert_eq!(
+        dis8086(&["-output=json", "-"], &bin)[1],
+        r#"{"address":1,"bytes":"0f","mnemonic":"db","operands":["0x0F"],"size":1}"#
+    );
+    let text = disassemble("invalid-masm", &["-syntax=masm"], &bin);
+    assert_eq!(text[1..3], ["db 0Fh", "db 8Eh, 0E0h"]);
+}
+
+#[test]
+fn disassemble_usage() {
+    let text = dis8086(&["-h"], &[]).join("\n");
+    assert!(text.starts_with("Usage: dis8086"), "{}", text);
+    for opt in [
+        "-origin=addr",
+        "-skip=n",
+        "-start=n",
+        "-end=n",
+        "-output=fmt",
+        "-syntax=name",
+        "-uppercase",
+        "-hex=style",
+        "-recursive",
+        "-entry=addr",
+        "--reassemble",
+    ] {
+        assert!(text.contains(opt), "{}", text);
+    }
+}
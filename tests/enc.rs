@@ -0,0 +1,152 @@
+use lib8086::{
+    Arg, Cc, Decoder, EncMode, EncodeError, Encoder, Inst, Invalid, Mem, Op, Reg16, Reg8, Rep, Sreg, Width,
+};
+
+fn decode(bytes: &[u8]) -> Option<Inst> {
+    let mut dec = Decoder::new(bytes.iter().copied());
+    dec.next_i().ok()
+}
+
+fn encode(mode: EncMode, inst: &Inst) -> Vec<u8> {
+    Encoder::new(mode)
+        .encode(inst)
+        .unwrap_or_else(|e| panic!("{:?}: {}", inst, e))
+}
+
+// same instruction, whatever the size of its encoding
+fn same(a: &Inst, b: &Inst) -> bool {
+    (a.lock, a.rep, a.seg, &a.op, a.width) == (b.lock, b.rep, b.seg, &b.op, b.width)
+}
+
+fn inst(op: Op, width: Width) -> Inst {
+    Inst {
+        op,
+        width,
+        ..Default::default()
+    }
+}
+
+// every opcode, with every modrm byte and a few displacement/immediate
+// patterns: what the decoder accepts must encode back (fixed mode) to bytes
+// that decode to the same instruction, and never to more bytes
+#[test]
+fn round_trip_every_opcode() {
+    let tails: [[u8; 4]; 3] = [[0x00, 0x00, 0x00, 0x00], [0x7f, 0x80, 0xff, 0x01], [0x34, 0x12, 0x78, 0x56]];
+    let mut checked = 0;
+    for b0 in 0..=0xffu8 {
+        // prefixes are checked separately
+        if matches!(b0, 0x26 | 0x2e | 0x36 | 0x3e | 0xf0 | 0xf2 | 0xf3) {
+            continue;
+        }
+        for b1 in 0..=0xffu8 {
+            for tail in &tails {
+                let bytes = [b0, b1, tail[0], tail[1], tail[2], tail[3]];
+                let Some(inst) = decode(&bytes) else { continue };
+                if matches!(inst.op, Op::Invalid(_)) {
+                    continue;
+                }
+                let enc = encode(EncMode::Fixed, &inst);
+                assert!(enc.len() <= inst.size as usize, "{:02x?} -> {:02x?}", &bytes[..inst.size as usize], enc);
+                let back = decode(&enc).unwrap();
+                assert!(same(&inst, &back), "{:02x?}: {:?} -> {:02x?}: {:?}", bytes, inst, enc, back);
+                assert_eq!(back.size as usize, enc.len());
+                checked += 1;
+            }
+        }
+    }
+    assert!(checked > 100_000);
+}
+
+// the shortest encoding is never longer, means the same thing and is stable
+#[test]
+fn shortest_is_shorter_and_stable() {
+    for b0 in 0..=0xffu8 {
+        for b1 in 0..=0xffu8 {
+            let bytes = [b0, b1, 0x05, 0x00, 0x80, 0x00];
+            let Some(inst) = decode(&bytes) else { continue };
+            if matches!(inst.op, Op::Invalid(_)) {
+                continue;
+            }
+            let fixed = encode(EncMode::Fixed, &inst);
+            let short = encode(EncMode::Shortest, &inst);
+            assert!(short.len() <= fixed.len(), "{:?}: {:02x?} {:02x?}", inst, short, fixed);
+            let back = decode(&short).unwrap();
+            assert_eq!(encode(EncMode::Shortest, &back), short, "{:?}", inst);
+        }
+    }
+}
+
+#[test]
+fn round_trip_prefixes() {
+    for prefixes in [&[0x26][..], &[0x2e, 0xf3], &[0xf0, 0x36], &[0xf2, 0x3e, 0xf0]] {
+        for body in [&[0xa4][..], &[0xa7], &[0x01, 0x47, 0x10], &[0x8b, 0x86, 0x34, 0x12]] {
+            let bytes = [prefixes, body].concat();
+            let inst = decode(&bytes).unwrap();
+            let enc = encode(EncMode::Fixed, &inst);
+            assert_eq!(enc.len(), bytes.len());
+            assert!(same(&inst, &decode(&enc).unwrap()), "{:02x?}", bytes);
+        }
+    }
+}
+
+#[test]
+fn shortest_choices() {
+    let s = |op, width| encode(EncMode::Shortest, &inst(op, width));
+    let f = |op, width| encode(EncMode::Fixed, &inst(op, width));
+    let bx = Arg::Reg16(Reg16::BX);
+    let ax = Arg::Reg16(Reg16::AX);
+
+    // sign extended imm8
+    assert_eq!(s(Op::Add(bx, Arg::Uimm16(5)), Width::Word), [0x83, 0xc3, 0x05]);
+    assert_eq!(s(Op::Add(bx, Arg::Uimm16(0xfffe)), Width::Word), [0x83, 0xc3, 0xfe]);
+    assert_eq!(f(Op::Add(bx, Arg::Uimm16(5)), Width::Word), [0x81, 0xc3, 0x05, 0x00]);
+    assert_eq!(f(Op::Add(bx, Arg::Imm8(5)), Width::Word), [0x83, 0xc3, 0x05]);
+    assert_eq!(s(Op::Add(bx, Arg::Uimm16(0x80)), Width::Word), [0x81, 0xc3, 0x80, 0x00]);
+
+    // accumulator forms
+    assert_eq!(s(Op::Add(ax, Arg::Uimm16(0x1234)), Width::Word), [0x05, 0x34, 0x12]);
+    assert_eq!(s(Op::Add(ax, Arg::Uimm16(1)), Width::Word), [0x83, 0xc0, 0x01]);
+    assert_eq!(s(Op::Cmp(Arg::Reg8(Reg8::AL), Arg::Uimm8(1)), Width::Byte), [0x3c, 0x01]);
+    assert_eq!(s(Op::Mov(ax, Arg::Mem(Mem::Direct(0x1234))), Width::Word), [0xa1, 0x34, 0x12]);
+    assert_eq!(s(Op::Test(Arg::Reg8(Reg8::AL), Arg::Uimm8(1)), Width::Byte), [0xa8, 0x01]);
+    assert_eq!(s(Op::Xchg(bx, ax), Width::Word), [0x93]);
+    assert_eq!(f(Op::Xchg(bx, ax), Width::Word), [0x87, 0xc3]);
+
+    // displacements
+    let m = |mem| Arg::Mem(mem);
+    assert_eq!(s(Op::Inc(m(Mem::RegOff16(Reg16::BX, 4))), Width::Word), [0xff, 0x47, 0x04]);
+    assert_eq!(f(Op::Inc(m(Mem::RegOff16(Reg16::BX, 4))), Width::Word), [0xff, 0x87, 0x04, 0x00]);
+    assert_eq!(s(Op::Inc(m(Mem::RegOff8(Reg16::BX, 0))), Width::Byte), [0xfe, 0x07]);
+    assert_eq!(s(Op::Inc(m(Mem::Reg(Reg16::BP))), Width::Byte), [0xfe, 0x46, 0x00]);
+    assert_eq!(s(Op::Push(m(Mem::RegReg(Reg16::SI, Reg16::BX))), Width::Word), [0xff, 0x30]);
+
+    // jumps keep their target when shortened
+    assert_eq!(s(Op::Jmp(Arg::Imm16(0x10)), Width::Byte), [0xeb, 0x11]);
+    assert_eq!(s(Op::Jmp(Arg::Imm16(-3)), Width::Byte), [0xeb, 0xfe]);
+    assert_eq!(s(Op::Jmp(Arg::Imm16(127)), Width::Byte), [0xe9, 0x7f, 0x00]);
+    assert_eq!(f(Op::Jmp(Arg::Imm16(0x10)), Width::Byte), [0xe9, 0x10, 0x00]);
+    assert_eq!(s(Op::Jcc(Cc::NO, -2), Width::Byte), [0x71, 0xfe]);
+
+    // short register forms
+    assert_eq!(s(Op::Mov(Arg::Reg8(Reg8::CH), Arg::Uimm8(1)), Width::Byte), [0xb5, 0x01]);
+    assert_eq!(s(Op::Dec(Arg::Reg16(Reg16::DI)), Width::Word), [0x4f]);
+    assert_eq!(s(Op::Pop(Arg::Sreg(Sreg::DS)), Width::Word), [0x1f]);
+}
+
+#[test]
+fn prefixes_and_errors() {
+    let mut i = inst(Op::Movs, Width::Word);
+    i.rep = Some(Rep::Rep);
+    i.seg = Some(Sreg::ES);
+    assert_eq!(encode(EncMode::Shortest, &i), [0x26, 0xf3, 0xa5]);
+
+    let enc = Encoder::default();
+    let mem = Arg::Mem(Mem::Direct(0));
+    let err = |op, width| enc.encode(&inst(op, width)).unwrap_err();
+    assert_eq!(err(Op::Mov(mem, mem), Width::Word), EncodeError::InvalidOperands);
+    assert_eq!(err(Op::Add(Arg::Reg8(Reg8::AL), Arg::Reg16(Reg16::AX)), Width::Byte), EncodeError::InvalidOperands);
+    assert_eq!(err(Op::Mov(Arg::Reg8(Reg8::AL), Arg::Uimm16(0x100)), Width::Byte), EncodeError::OutOfRange);
+    assert_eq!(err(Op::Lea(Arg::Reg16(Reg16::AX), Arg::Reg16(Reg16::BX)), Width::Word), EncodeError::InvalidOperands);
+    assert_eq!(err(Op::Pop(Arg::Sreg(Sreg::CS)), Width::Word), EncodeError::InvalidOperands);
+    assert_eq!(err(Op::Invalid(Invalid::UnexpectedByte(0x0f)), Width::Byte), EncodeError::NotAnInstruction);
+}
@@ -0,0 +1,33 @@
+use lib8086::{Arg, FmtOptions, Inst, Op, Reg16, Syntax, Width};
+
+fn inst(op: Op) -> Inst {
+    Inst {
+        op,
+        width: Width::Word,
+        ..Default::default()
+    }
+}
+
+// a far pointer built by hand, with operands the decoder never gives, is
+// written with its operands as they are
+#[test]
+fn format_far_operands() {
+    let ops = || {
+        [
+            Op::JmpFar(Arg::Uimm16(0xf000), Arg::Uimm16(0xe05b)),
+            Op::JmpFar(Arg::Imm16(0x1234), Arg::Uimm16(0x5678)),
+            Op::CallFar(Arg::Reg16(Reg16::AX), Arg::Reg16(Reg16::BX)),
+        ]
+    };
+    let expect = [
+        (Syntax::Nasm, ["jmp 0xF000:0xE05B", "jmp 0x1234, 0x5678", "call ax, bx"]),
+        (Syntax::Masm, ["jmp 0F000h:0E05Bh", "jmp 1234h, 5678h", "call ax, bx"]),
+        (Syntax::Att, ["ljmp $0xF000,$0xE05B", "ljmp $0x1234,$0x5678", "lcall %ax,%bx"]),
+    ];
+    for (syntax, expect) in expect {
+        let fmt = syntax.formatter(FmtOptions::default());
+        for (op, text) in ops().into_iter().zip(expect) {
+            assert_eq!(fmt.format(0, 0, &inst(op)), text, "{:?}", syntax);
+        }
+    }
+}
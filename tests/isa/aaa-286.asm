@@ -0,0 +1,22 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+_start:
+        MOV     AH, 0x10
+        SAHF
+        MOV     AX, 0x00F9
+        AAA
+        MOV     BX, AX
+
+        ; the 80286 adds 106h to ax: the carry out of al reaches ah
+        MOV     AX, 0x00FA
+        AAA
+        HLT
+
+        EXPECT  __FILE__, __LINE__, BX, 0x010F
+        EXPECT  __FILE__, __LINE__, AX, 0x0200
+        EXPECT  __FILE__, __LINE__, AF, 1
+        EXPECT  __FILE__, __LINE__, CF, 1
+        EXPECT  __FILE__, __LINE__, DONE
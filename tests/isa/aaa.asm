@@ -4,8 +4,25 @@ ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
 %include "./expect.inc"
 
 _start:
+        ; AF set: al + 6 does not carry, the same on the 80286
+        MOV     AH, 0x10
+        SAHF
+        MOV     AX, 0x00F9
+        AAA
+        MOV     BX, AX
+
+        ; the 8086 adds 6 to al alone: the carry out of al is lost
+        MOV     AX, 0x00FA
+        AAA
+        MOV     CX, AX
+
         MOV     AX, 11
         AAA
         HLT
 
+        EXPECT  __FILE__, __LINE__, BX, 0x010F
+        EXPECT  __FILE__, __LINE__, CX, 0x0100
         EXPECT  __FILE__, __LINE__, AX, 0x0101
+        EXPECT  __FILE__, __LINE__, AF, 1
+        EXPECT  __FILE__, __LINE__, CF, 1
+        EXPECT  __FILE__, __LINE__, DONE
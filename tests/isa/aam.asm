@@ -1,11 +1,30 @@
 CPU     8086
 BITS    16
 ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
 
 _start:
-        MOV AX, 11
+        MOV     SP, 0x1000
+        MOV     WORD [0*4], divide
+        MOV     [0*4+2], CS
+
+        MOV     AX, 11
         AAM
-        HLT
+        MOV     BX, AX
+
+        ; a base of 0 is a divide error, ax is left as it is
+        MOV     AX, 0x1234
+        AAM     0
+.next:  HLT
 
-; EXPECT AX == 0x0101
+        EXPECT  __FILE__, __LINE__, BX, 0x0101
+        EXPECT  __FILE__, __LINE__, AX, 0x1234
+        EXPECT  __FILE__, __LINE__, CX, .next
+        EXPECT  __FILE__, __LINE__, SP, 0x1000
+        EXPECT  __FILE__, __LINE__, DONE
 
+; divide error handler: cx is the return address
+divide:
+        MOV     BP, SP
+        MOV     CX, [BP]
+        IRET
@@ -0,0 +1,24 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+_start:
+        MOV     AH, 0x10
+        SAHF
+        MOV     AX, 0x00F9
+        AAS
+        MOV     BX, AX
+
+        ; the 80286 subtracts 6 from ax: al borrows from ah
+        MOV     AH, 0x10
+        SAHF
+        MOV     AX, 0x0205
+        AAS
+        HLT
+
+        EXPECT  __FILE__, __LINE__, BX, 0xFF03
+        EXPECT  __FILE__, __LINE__, AX, 0x000F
+        EXPECT  __FILE__, __LINE__, AF, 1
+        EXPECT  __FILE__, __LINE__, CF, 1
+        EXPECT  __FILE__, __LINE__, DONE
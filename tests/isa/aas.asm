@@ -4,9 +4,29 @@ ORG     0
 %include "expect.inc"
 
 _start:
+        ; AF set: al - 6 does not borrow, the same on the 80286
+        MOV     AH, 0x10
+        SAHF
+        MOV     AX, 0x00F9
+        AAS
+        MOV     BX, AX
+
+        ; the 8086 subtracts 6 from al alone: the borrow is lost
+        MOV     AH, 0x10
+        SAHF
+        MOV     AX, 0x0205
+        AAS
+        MOV     CX, AX
+
+        MOV     AH, 0
+        SAHF
         MOV     AX, 0x15
         AAS
         HLT
 
+        EXPECT  __FILE__, __LINE__, BX, 0xFF03
+        EXPECT  __FILE__, __LINE__, CX, 0x010F
         EXPECT  __FILE__, __LINE__, AX, 0x0005
+        EXPECT  __FILE__, __LINE__, AF, 0
+        EXPECT  __FILE__, __LINE__, CF, 0
         EXPECT  __FILE__, __LINE__, DONE
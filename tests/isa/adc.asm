@@ -1,13 +1,80 @@
 CPU     8086
 BITS    16
-ORG     0
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
 %include "expect.inc"
+%include "flags.inc"
 
 _start:
+        MOV     DI, RESULTS
+
+        ; 0x7f + 0 + cf: signed overflow from the carry in
+        MOV     DX, 0x007F
+        STC
+        ADC     DL, 0x00
+        SAVE    ARITH
+
+        ; 0xff + 0 + cf: carry out, zero, half carry
+        MOV     DX, 0x00FF
+        STC
+        ADC     DL, 0x00
+        SAVE    ARITH
+
+        ; 0x80 + 0xff + cf: carry, no overflow
+        MOV     DX, 0x0080
+        STC
+        ADC     DL, 0xFF
+        SAVE    ARITH
+
+        ; 0x7f + 0x7f + cf: overflow
+        MOV     DX, 0x007F
+        STC
+        ADC     DL, 0x7F
+        SAVE    ARITH
+
+        ; 0xff + 0xff + cf: carry and half carry
+        MOV     DX, 0x00FF
+        STC
+        ADC     DL, 0xFF
+        SAVE    ARITH
+
+        ; 0x7f + 0x80 without carry in
+        MOV     DX, 0x007F
+        CLC
+        ADC     DL, 0x80
+        SAVE    ARITH
+
+        ; 0xffff + 0 + cf: word carry
+        MOV     DX, 0xFFFF
+        STC
+        ADC     DX, 0x0000
+        SAVE    ARITH
+
+        ; 0x7fff + 0 + cf: word overflow
+        MOV     DX, 0x7FFF
+        STC
+        ADC     DX, 0x0000
+        SAVE    ARITH
+
         MOV     AL, 0x05
         STC
         ADC     AL, 0x01
         HLT
 
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0890
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0055
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0091
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x00FF
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0894
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x00FF
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0x00FF
+        EXPECT  __FILE__, __LINE__, MW, 0x0516, 0x0084
+        EXPECT  __FILE__, __LINE__, MW, 0x0518, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x051A, 0x0055
+        EXPECT  __FILE__, __LINE__, MW, 0x051C, 0x8000
+        EXPECT  __FILE__, __LINE__, MW, 0x051E, 0x0894
         EXPECT  __FILE__, __LINE__, AL, 0x07
         EXPECT  __FILE__, __LINE__, DONE
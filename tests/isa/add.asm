@@ -2,41 +2,80 @@ CPU     8086
 BITS    16
 ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
 %include "expect.inc"
+%include "flags.inc"
 
 _start:
-        MOV     SP, 0xfffe
-        MOV     AX, 0
-        MOV     SS, AX
+        MOV     DI, RESULTS
 
-        CALL    .test_r8_r8
-        CALL    .test_m8_r8
-        HLT
+        ; 0x7f + 1: signed overflow, half carry
+        MOV     DX, 0x007F
+        ADD     DL, 0x01
+        SAVE    ARITH
+
+        ; 0xff + 1: carry out, zero
+        MOV     DX, 0x00FF
+        ADD     DL, 0x01
+        SAVE    ARITH
+
+        ; 0x80 + 0x80: carry and overflow
+        MOV     DX, 0x0080
+        ADD     DL, 0x80
+        SAVE    ARITH
+
+        ; 0x0f + 1: half carry only
+        MOV     DX, 0x000F
+        ADD     DL, 0x01
+        SAVE    ARITH
+
+        ; the carry in is ignored
+        MOV     DX, 0x0001
+        STC
+        ADD     DL, 0x01
+        SAVE    ARITH
+
+        ; 0x7fff + 1: word overflow
+        MOV     DX, 0x7FFF
+        ADD     DX, 0x0001
+        SAVE    ARITH
+
+        ; 0xffff + 1: word carry
+        MOV     DX, 0xFFFF
+        ADD     DX, 0x0001
+        SAVE    ARITH
+
+        ; 0x00ff + 1: pf is on the low byte only
+        MOV     DX, 0x00FF
+        ADD     DX, 0x0001
+        SAVE    ARITH
 
-.test_r8_r8:
+        ; the original cases, in registers and in memory
         MOV     CL, 0x01
         MOV     BL, 0x02
         ADD     BL, CL
-        RET
+        MOV     BYTE [.val], 4
+        MOV     AL, 1
+        ADD     [.val], AL
         HLT
 
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0890
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0055
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0845
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x0010
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0010
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x0002
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0x8000
+        EXPECT  __FILE__, __LINE__, MW, 0x0516, 0x0894
+        EXPECT  __FILE__, __LINE__, MW, 0x0518, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x051A, 0x0055
+        EXPECT  __FILE__, __LINE__, MW, 0x051C, 0x0100
+        EXPECT  __FILE__, __LINE__, MW, 0x051E, 0x0014
         EXPECT  __FILE__, __LINE__, BL, 3
+        EXPECT  __FILE__, __LINE__, MB, .val, 5
         EXPECT  __FILE__, __LINE__, DONE
 
-.test_m8_r8_val:
-        DB      4
-
-.test_m8_r8:
-        MOV     BL, 1
-        ADD     [.test_m8_r8_val], BL
-        RET
-        HLT
-
-        EXPECT  __FILE__, __LINE__, MB, .test_m8_r8_val, 5
-        EXPECT  __FILE__, __LINE__, DONE
-
-.todo:
-        MOV AL, 0x05
-        AND AL, 0x01
-        HLT
-
-; EXPECT AX == 0x01
+.val:
+        DB      0
@@ -1,13 +1,38 @@
 CPU     8086
 BITS    16
-ORG     0
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
 %include "expect.inc"
+%include "flags.inc"
 
 _start:
-        MOV AL, 0x05
+        MOV     DI, RESULTS
+
+        ; 0xf0 & 0x0f: zero, cf cleared
+        MOV     DX, 0x00F0
+        STC
+        AND     DL, 0x0F
+        SAVE    LOGIC
+
+        ; 0x81 & 0xff: sign
+        MOV     DX, 0x0081
         STC
-        ADD AL, 0x01
+        AND     DL, 0xFF
+        SAVE    LOGIC
+
+        ; 0x8001 & 0x8000: word sign
+        MOV     DX, 0x8001
+        AND     DX, 0x8000
+        SAVE    LOGIC
+
+        MOV     AL, 0x05
+        AND     AL, 0x01
         HLT
 
-        EXPECT  __FILE__, __LINE__, AX, 0x06
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0081
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0084
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x8000
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0084
+        EXPECT  __FILE__, __LINE__, AL, 0x01
         EXPECT  __FILE__, __LINE__, DONE
@@ -1,13 +1,51 @@
 CPU     8086
 BITS    16
-ORG     0
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
 %include "expect.inc"
+%include "flags.inc"
 
 _start:
+        MOV     DI, RESULTS
+
+        ; 1 - 2: below and less
+        MOV     DX, 0x0001
+        CMP     DL, 0x02
+        SAVE    ARITH
+
+        ; 0x7f - 0xff: greater, but below
+        MOV     DX, 0x007F
+        CMP     DL, 0xFF
+        SAVE    ARITH
+
+        ; 0x80 - 1: overflow
+        MOV     DX, 0x0080
+        CMP     DL, 0x01
+        SAVE    ARITH
+
+        ; 0x1234 - 0x1234: equal
+        MOV     DX, 0x1234
+        CMP     DX, 0x1234
+        SAVE    ARITH
+
+        ; 0x8000 - 1: word overflow
+        MOV     DX, 0x8000
+        CMP     DX, 0x0001
+        SAVE    ARITH
+
         MOV     AL, 0x05
         CMP     AL, 0x05
         HLT
 
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0001
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x007F
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0881
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0810
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x1234
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x8000
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0x0814
         EXPECT  __FILE__, __LINE__, AL, 0x05
         EXPECT  __FILE__, __LINE__, ZF, 1
         EXPECT  __FILE__, __LINE__, DONE
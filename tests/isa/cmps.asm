@@ -0,0 +1,89 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+; the strings are in the code: cs override on the source, es = cs
+_start:
+        MOV     AX, CS
+        MOV     ES, AX
+        MOV     BX, RESULTS
+        CLD
+
+        ; repe stops at the first difference, 'L' - 'P'
+        MOV     SI, s1
+        MOV     DI, s2
+        MOV     CX, 5
+        REPE CS CMPSB
+        SAVE_STR ARITH
+
+        ; or when cx runs out
+        MOV     SI, s1
+        MOV     DI, s2
+        MOV     CX, 3
+        REPE CS CMPSB
+        SAVE_STR ARITH
+
+        ; repne stops at the first match
+        MOV     SI, s1
+        MOV     DI, s3
+        MOV     CX, 5
+        REPNE CS CMPSB
+        SAVE_STR ARITH
+
+        ; cx is 0: nothing is compared, the flags are those of the cmp
+        MOV     AL, 1
+        CMP     AL, 2
+        MOV     SI, s1
+        MOV     DI, s2
+        MOV     CX, 0
+        REPE CS CMPSB
+        SAVE_STR ARITH
+
+        ; backwards, 'LL' - 'LP'
+        STD
+        MOV     SI, s1+2
+        MOV     DI, s2+2
+        MOV     CX, 2
+        REPE CS CMPSW
+        CLD
+        SAVE_STR ARITH
+
+        ; cx does not change without a prefix
+        MOV     SI, s1
+        MOV     DI, s3
+        MOV     CX, 7
+        CS      CMPSB
+        SAVE_STR ARITH
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, s1+4
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, s2+4
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0001
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0085
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, s1+3
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, s2+3
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, s1+4
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, s3+4
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0x0001
+        EXPECT  __FILE__, __LINE__, MW, 0x0516, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x0518, s1
+        EXPECT  __FILE__, __LINE__, MW, 0x051A, s2
+        EXPECT  __FILE__, __LINE__, MW, 0x051C, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x051E, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0520, s1
+        EXPECT  __FILE__, __LINE__, MW, 0x0522, s2
+        EXPECT  __FILE__, __LINE__, MW, 0x0524, 0x0001
+        EXPECT  __FILE__, __LINE__, MW, 0x0526, 0x0085
+        EXPECT  __FILE__, __LINE__, MW, 0x0528, s1+1
+        EXPECT  __FILE__, __LINE__, MW, 0x052A, s3+1
+        EXPECT  __FILE__, __LINE__, MW, 0x052C, 0x0007
+        EXPECT  __FILE__, __LINE__, MW, 0x052E, 0x0091
+        EXPECT  __FILE__, __LINE__, DONE
+
+s1:     DB      'HELLO'
+s2:     DB      'HELPS'
+s3:     DB      'JAMLX'
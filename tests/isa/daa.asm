@@ -0,0 +1,49 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+%define BCD     0x00D5  ; of is undefined
+
+_start:
+        MOV     DI, RESULTS
+
+        ; CF and AF set: 66h is added
+        MOV     AH, 0x11
+        SAHF
+        MOV     AL, 0x00
+        DAA
+        MOV     DL, AL
+        SAVE    BCD
+        MOV     AH, 0x11
+        SAHF
+        MOV     AL, 0x99
+        DAA
+        MOV     DL, AL
+        SAVE    BCD
+
+        ; 15 + 27
+        MOV     AL, 0x15
+        ADD     AL, 0x27
+        DAA
+        MOV     DL, AL
+        SAVE    BCD
+
+        ; 99 + 1
+        MOV     AL, 0x99
+        ADD     AL, 0x01
+        DAA
+        MOV     DL, AL
+        SAVE    BCD
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MB, 0x0500, 0x66
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0015
+        EXPECT  __FILE__, __LINE__, MB, 0x0504, 0xFF
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0095
+        EXPECT  __FILE__, __LINE__, MB, 0x0508, 0x42
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0014
+        EXPECT  __FILE__, __LINE__, MB, 0x050C, 0x00
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0055
+        EXPECT  __FILE__, __LINE__, DONE
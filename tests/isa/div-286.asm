@@ -0,0 +1,60 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+; on the 80286, the return address of a divide error is the faulting
+; instruction: the handler saves it at [si] and skips the 2 byte division
+_start:
+        MOV     SP, 0x1000
+        MOV     WORD [0*4], divide
+        MOV     [0*4+2], CS
+        MOV     DI, 0x500
+        MOV     SI, 0x600
+
+        MOV     AX, 0x1234
+        MOV     BL, 0
+.f1:    DIV     BL
+        STOSW
+
+        MOV     DX, 0xFFFF              ; -32768 / -1
+        MOV     AX, 0x8000
+        MOV     BX, 0xFFFF
+.f2:    IDIV    BX
+
+        ; the most negative quotient fits
+        MOV     AX, 0xFF80              ; -128 / 1
+        MOV     BL, 0x01
+        IDIV    BL
+        STOSW
+        MOV     DX, 0xFFFF              ; -32768 / 1
+        MOV     AX, 0x8000
+        MOV     BX, 0x0001
+        IDIV    BX
+        STOSW
+        MOV     AX, DX
+        STOSW
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x1234
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x8000
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0600, .f1
+        EXPECT  __FILE__, __LINE__, MW, 0x0602, .f2
+        EXPECT  __FILE__, __LINE__, SI, 0x0604
+        EXPECT  __FILE__, __LINE__, SP, 0x1000
+        EXPECT  __FILE__, __LINE__, DONE
+
+; divide error handler
+divide:
+        PUSH    BP
+        MOV     BP, SP
+        PUSH    AX
+        MOV     AX, [BP+2]
+        MOV     [SI], AX
+        ADD     SI, 2
+        ADD     WORD [BP+2], 2
+        POP     AX
+        POP     BP
+        IRET
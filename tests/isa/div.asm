@@ -0,0 +1,100 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+; each case stores the quotient and the remainder, ax then dx. on a divide
+; error, the handler saves the return address at [si]: on the 8086, it is
+; the next instruction
+_start:
+        MOV     SP, 0x1000
+        MOV     WORD [0*4], divide
+        MOV     [0*4+2], CS
+        MOV     DI, RESULTS
+        MOV     SI, 0x600
+
+        MOV     AX, 0x0107              ; 263 / 16
+        MOV     BL, 0x10
+        DIV     BL
+        STOSW
+
+        MOV     DX, 0x0001              ; 65541 / 16
+        MOV     AX, 0x0005
+        MOV     BX, 0x0010
+        DIV     BX
+        STOSW
+        MOV     AX, DX
+        STOSW
+
+        MOV     AX, 0xFFF9              ; -7 / 2
+        MOV     BL, 0x02
+        IDIV    BL
+        STOSW
+
+        MOV     DX, 0xFFFE              ; -100000 / 7
+        MOV     AX, 0x7960
+        MOV     BX, 0x0007
+        IDIV    BX
+        STOSW
+        MOV     AX, DX
+        STOSW
+
+        ; divide by zero: ax is left as it is
+        MOV     AX, 0x1234
+        MOV     BL, 0
+        DIV     BL
+.n1:    STOSW
+
+        ; the quotient does not fit
+        MOV     AX, 0x1000              ; 4096 / 16
+        MOV     BL, 0x10
+        DIV     BL
+.n2:    MOV     DX, 0x0001              ; 65536 / 1
+        MOV     AX, 0
+        MOV     BX, 0x0001
+        DIV     BX
+.n3:    MOV     DX, 0xFFFF              ; -32768 / -1
+        MOV     AX, 0x8000
+        MOV     BX, 0xFFFF
+        IDIV    BX
+.n4:
+
+        ; the 8086 faults on the most negative quotient
+        MOV     AX, 0xFF80              ; -128 / 1
+        MOV     BL, 0x01
+        IDIV    BL
+.n5:    MOV     DX, 0xFFFF              ; -32768 / 1
+        MOV     AX, 0x8000
+        MOV     BX, 0x0001
+        IDIV    BX
+.n6:    HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0710
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x1000
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0005
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0xFFFD
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0xC833
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0xFFFB
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x1234
+        EXPECT  __FILE__, __LINE__, MW, 0x0600, .n1
+        EXPECT  __FILE__, __LINE__, MW, 0x0602, .n2
+        EXPECT  __FILE__, __LINE__, MW, 0x0604, .n3
+        EXPECT  __FILE__, __LINE__, MW, 0x0606, .n4
+        EXPECT  __FILE__, __LINE__, MW, 0x0608, .n5
+        EXPECT  __FILE__, __LINE__, MW, 0x060A, .n6
+        EXPECT  __FILE__, __LINE__, SI, 0x060C
+        EXPECT  __FILE__, __LINE__, SP, 0x1000
+        EXPECT  __FILE__, __LINE__, DONE
+
+; divide error handler
+divide:
+        PUSH    BP
+        MOV     BP, SP
+        PUSH    AX
+        MOV     AX, [BP+2]
+        MOV     [SI], AX
+        ADD     SI, 2
+        POP     AX
+        POP     BP
+        IRET
@@ -0,0 +1,24 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+; no device is mapped: the ports read as 0 and the writes are lost
+_start:
+        MOV     AX, 0x1234
+        OUT     0x80, AL
+        IN      AL, 0x80
+        MOV     BX, AX
+        MOV     DX, 0x03F8
+        MOV     AX, 0x5678
+        OUT     DX, AX
+        IN      AX, DX
+        MOV     CX, 0xFFFF
+        XCHG    AX, CX
+        IN      AL, DX
+        HLT
+
+        EXPECT  __FILE__, __LINE__, BX, 0x1200
+        EXPECT  __FILE__, __LINE__, CX, 0x0000
+        EXPECT  __FILE__, __LINE__, AX, 0xFF00
+        EXPECT  __FILE__, __LINE__, DONE
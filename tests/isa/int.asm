@@ -0,0 +1,70 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+_start:
+        MOV     SP, 0x1000
+        MOV     WORD [0x21*4], int21
+        MOV     [0x21*4+2], CS
+        MOV     WORD [3*4], count       ; int3
+        MOV     [3*4+2], CS
+        MOV     WORD [4*4], count       ; into
+        MOV     [4*4+2], CS
+
+        ; the flags, cs and ip are pushed, IF and TF cleared
+        STI
+        STC
+        INT     0x21
+.back:
+        ; iret restores sp and the flags, even CF which the handler cleared
+        MOV     [0x610], SP
+        PUSHF
+        POP     AX
+        MOV     [0x612], AX
+
+        INT3
+        MOV     AL, 0x7F
+        INTO                            ; OF=0: not taken
+        ADD     AL, 1
+        INTO                            ; OF=1: int 4
+.after_into:
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0600, .back
+        EXPECT  __FILE__, __LINE__, MW, 0x0602, 0xF000
+        EXPECT  __FILE__, __LINE__, MW, 0x0604, 0xF203
+        EXPECT  __FILE__, __LINE__, MW, 0x0606, 0x0FFA
+        EXPECT  __FILE__, __LINE__, MW, 0x0608, 0xF003
+        EXPECT  __FILE__, __LINE__, MW, 0x0610, 0x1000
+        EXPECT  __FILE__, __LINE__, MW, 0x0612, 0xF203
+        EXPECT  __FILE__, __LINE__, MB, 0x0700, 2
+        EXPECT  __FILE__, __LINE__, MW, 0x0702, .after_into
+        EXPECT  __FILE__, __LINE__, SP, 0x1000
+        EXPECT  __FILE__, __LINE__, IF, 1
+        EXPECT  __FILE__, __LINE__, OF, 1
+        EXPECT  __FILE__, __LINE__, DONE
+
+; saves what int pushed, and the flags of the handler
+int21:
+        MOV     BP, SP
+        MOV     AX, [BP]                ; ip
+        MOV     [0x600], AX
+        MOV     AX, [BP+2]              ; cs
+        MOV     [0x602], AX
+        MOV     AX, [BP+4]              ; flags
+        MOV     [0x604], AX
+        MOV     [0x606], SP
+        PUSHF
+        POP     AX
+        MOV     [0x608], AX
+        CLC
+        IRET
+
+; counts the interrupts, and saves the last return address
+count:
+        INC     BYTE [0x700]
+        MOV     BP, SP
+        MOV     AX, [BP]
+        MOV     [0x702], AX
+        IRET
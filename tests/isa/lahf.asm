@@ -0,0 +1,33 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+_start:
+        ; sahf loads SF, ZF, AF, PF and CF from ah, lahf stores them with
+        ; bit 1 set, bits 3 and 5 clear
+        MOV     AH, 0xFF
+        SAHF
+        MOV     AH, 0
+        LAHF
+        MOV     BL, AH
+        MOV     AH, 0x00
+        SAHF
+        MOV     AH, 0xFF
+        LAHF
+        MOV     BH, AH
+
+        ; OF is left as it is
+        MOV     AL, 0x7F
+        ADD     AL, 1
+        MOV     AH, 0x01
+        SAHF
+        HLT
+
+        EXPECT  __FILE__, __LINE__, BL, 0xD7
+        EXPECT  __FILE__, __LINE__, BH, 0x02
+        EXPECT  __FILE__, __LINE__, OF, 1
+        EXPECT  __FILE__, __LINE__, CF, 1
+        EXPECT  __FILE__, __LINE__, SF, 0
+        EXPECT  __FILE__, __LINE__, ZF, 0
+        EXPECT  __FILE__, __LINE__, DONE
@@ -0,0 +1,27 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+_start:
+        ; lea only computes the offset, it wraps at 64K
+        MOV     BX, 0x0100
+        MOV     DI, 0x0020
+        LEA     SI, [BX+DI+0x10]
+        MOV     BX, 0xFFF0
+        LEA     AX, [BX+0x20]
+
+        ; les and lds load a far pointer, the offset first
+        MOV     WORD [0x0500], 0x1234
+        MOV     WORD [0x0502], 0x0060
+        LES     DI, [0x0500]
+        LDS     BX, [0x0500]
+        HLT
+
+        EXPECT  __FILE__, __LINE__, SI, 0x0130
+        EXPECT  __FILE__, __LINE__, AX, 0x0010
+        EXPECT  __FILE__, __LINE__, DI, 0x1234
+        EXPECT  __FILE__, __LINE__, ES, 0x0060
+        EXPECT  __FILE__, __LINE__, BX, 0x1234
+        EXPECT  __FILE__, __LINE__, DS, 0x0060
+        EXPECT  __FILE__, __LINE__, DONE
@@ -0,0 +1,73 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+; the tables are in the code. lea moves si without changing ZF
+_start:
+        MOV     DI, 0x0500
+
+        MOV     BX, 0
+        MOV     CX, 3
+.loop:  INC     BX
+        LOOP    .loop
+        MOV     [DI], BX
+        MOV     [DI+2], CX
+        ADD     DI, 4
+
+        ; loopz stops when ZF is cleared
+        MOV     SI, zeros
+        MOV     CX, 5
+.z1:    CMP     BYTE [CS:SI], 0
+        LEA     SI, [SI+1]
+        LOOPZ   .z1
+        MOV     [DI], SI
+        MOV     [DI+2], CX
+        ADD     DI, 4
+
+        ; or when cx runs out
+        MOV     SI, zeros
+        MOV     CX, 2
+.z2:    CMP     BYTE [CS:SI], 0
+        LEA     SI, [SI+1]
+        LOOPZ   .z2
+        MOV     [DI], SI
+        MOV     [DI+2], CX
+        ADD     DI, 4
+
+        ; loopnz stops when ZF is set
+        MOV     SI, ones
+        MOV     CX, 5
+.nz:    CMP     BYTE [CS:SI], 0
+        LEA     SI, [SI+1]
+        LOOPNZ  .nz
+        MOV     [DI], SI
+        MOV     [DI+2], CX
+        ADD     DI, 4
+
+        ; jcxz jumps when cx is 0, and does not change it
+        MOV     BX, 0
+        MOV     CX, 1
+        JCXZ    .j1
+        OR      BX, 1
+.j1:    MOV     CX, 0
+        JCXZ    .j2
+        OR      BX, 2
+.j2:    MOV     [DI], BX
+        MOV     [DI+2], CX
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0003
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, zeros+3
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0002
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, zeros+2
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, ones+3
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0002
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x0001
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0x0000
+        EXPECT  __FILE__, __LINE__, DONE
+
+zeros:  DB      0, 0, 1, 0, 0
+ones:   DB      1, 1, 0, 1, 1
@@ -0,0 +1,112 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+_start:
+        MOV     SP, 0x1000
+        MOV     WORD [1*4], step
+        MOV     [1*4+2], CS
+        MOV     BX, RESULTS
+        MOV     AX, 0x0080              ; es:0 is at 0x800
+        MOV     ES, AX
+        CLD
+
+        ; from the code, with a segment override
+        MOV     SI, pattern
+        MOV     DI, 0
+        MOV     CX, 4
+        REP CS  MOVSB
+        SAVE_STR 0
+
+        ; cx is 0: nothing is copied
+        MOV     SI, 0x0800
+        MOV     DI, 0x0020
+        MOV     CX, 0
+        REP     MOVSW
+        SAVE_STR 0
+
+        ; backwards
+        STD
+        MOV     SI, 0x0802
+        MOV     DI, 0x0012
+        MOV     CX, 2
+        REP     MOVSW
+        CLD
+        SAVE_STR 0
+
+        ; cx does not change without a prefix
+        MOV     SI, 0x0800
+        MOV     DI, 0x0018
+        MOV     CX, 5
+        MOVSB
+        SAVE_STR 0
+
+        ; stos, to fill 0x600 with 'x'
+        MOV     AX, 0x0060
+        MOV     ES, AX
+        MOV     DI, 0
+        MOV     CX, 4
+        MOV     AL, 'x'
+        REP     STOSB
+
+        ; interrupted after each iteration by the single-step trap, the
+        ; 8086 resumes at the rep prefix and forgets the es override: the
+        ; first byte comes from es:0, the others from ds:1
+        MOV     AX, 0x0080
+        MOV     ES, AX
+        MOV     AX, 0x0060
+        MOV     DS, AX
+        MOV     SI, 0
+        MOV     DI, 0x0020
+        MOV     CX, 4
+        PUSHF
+        POP     AX
+        OR      AX, 0x0100
+        PUSH    AX
+        POPF
+        ES
+        REP     MOVSB
+        PUSHF
+        POP     AX
+        AND     AX, 0xFEFF
+        PUSH    AX
+        POPF
+        MOV     AX, 0
+        MOV     DS, AX
+        SAVE_STR 0
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0800, 'AB'
+        EXPECT  __FILE__, __LINE__, MW, 0x0802, 'CD'
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, pattern+4
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0004
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x0800
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0020
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0810, 'AB'
+        EXPECT  __FILE__, __LINE__, MW, 0x0812, 'CD'
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x07FE
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0x000E
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0x0000
+        EXPECT  __FILE__, __LINE__, MB, 0x0818, 'A'
+        EXPECT  __FILE__, __LINE__, MW, 0x0518, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x051A, 0x0019
+        EXPECT  __FILE__, __LINE__, MW, 0x051C, 0x0005
+        EXPECT  __FILE__, __LINE__, MW, 0x0600, 'xx'
+        EXPECT  __FILE__, __LINE__, MW, 0x0602, 'xx'
+        EXPECT  __FILE__, __LINE__, MW, 0x0820, 'Ax'
+        EXPECT  __FILE__, __LINE__, MW, 0x0822, 'xx'
+        EXPECT  __FILE__, __LINE__, MW, 0x0520, 0x0004
+        EXPECT  __FILE__, __LINE__, MW, 0x0522, 0x0024
+        EXPECT  __FILE__, __LINE__, MW, 0x0524, 0x0000
+        EXPECT  __FILE__, __LINE__, DONE
+
+pattern:
+        DB      'ABCD'
+
+; single-step handler
+step:
+        IRET
@@ -0,0 +1,120 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+; each case stores ax, then dx and the flags: 6 bytes. CF and OF are set
+; when the high half of the product is needed
+_start:
+        MOV     DI, RESULTS
+
+        ; mul
+        XOR     DX, DX
+        MOV     AX, 0x0010
+        MOV     BL, 0x0F
+        MUL     BL
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0x0080
+        MOV     BL, 0x02
+        MUL     BL
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0x1234
+        MOV     BX, 0x0100
+        MUL     BX
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0xFFFF
+        MOV     BX, 0x0001
+        MUL     BX
+        STOSW
+        SAVE    CO
+
+        ; imul: the high half is needed unless it is the sign extension of
+        ; the low half
+        XOR     DX, DX
+        MOV     AX, 0x00FE              ; -2 * 3
+        MOV     BL, 0x03
+        IMUL    BL
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0x0040              ; 64 * 2
+        MOV     BL, 0x02
+        IMUL    BL
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0x0080              ; -128 * 1
+        MOV     BL, 0x01
+        IMUL    BL
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0x0080              ; -128 * -1
+        MOV     BL, 0xFF
+        IMUL    BL
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0xFFFF              ; -1 * -1
+        MOV     BX, 0xFFFF
+        IMUL    BX
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0x4000              ; 16384 * 2
+        MOV     BX, 0x0002
+        IMUL    BX
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0x8000              ; -32768 * -1
+        MOV     BX, 0xFFFF
+        IMUL    BX
+        STOSW
+        SAVE    CO
+
+        MOV     AX, 0x0100              ; 256 * -256
+        MOV     BX, 0xFF00
+        IMUL    BX
+        STOSW
+        SAVE    CO
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x00F0
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0100
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x3400
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0012
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0xFFFF
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0516, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0518, 0xFFFA
+        EXPECT  __FILE__, __LINE__, MW, 0x051C, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x051E, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x0522, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x0524, 0xFF80
+        EXPECT  __FILE__, __LINE__, MW, 0x0528, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x052A, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x052E, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x0530, 0x0001
+        EXPECT  __FILE__, __LINE__, MW, 0x0532, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0534, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0536, 0x8000
+        EXPECT  __FILE__, __LINE__, MW, 0x0538, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x053A, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x053C, 0x8000
+        EXPECT  __FILE__, __LINE__, MW, 0x053E, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0540, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x0542, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0544, 0xFFFF
+        EXPECT  __FILE__, __LINE__, MW, 0x0546, 0x0801
+        EXPECT  __FILE__, __LINE__, DONE
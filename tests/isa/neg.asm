@@ -0,0 +1,55 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+_start:
+        MOV     DI, RESULTS
+
+        ; neg 0: no carry
+        MOV     DX, 0x0000
+        STC
+        NEG     DL
+        SAVE    ARITH
+
+        ; neg 1
+        MOV     DX, 0x0001
+        NEG     DL
+        SAVE    ARITH
+
+        ; neg 0x80: overflow
+        MOV     DX, 0x0080
+        NEG     DL
+        SAVE    ARITH
+
+        ; neg 0x10: no half borrow
+        MOV     DX, 0x0010
+        NEG     DL
+        SAVE    ARITH
+
+        ; neg 0x8000: word overflow
+        MOV     DX, 0x8000
+        NEG     DX
+        SAVE    ARITH
+
+        ; neg 0x0100: carry
+        MOV     DX, 0x0100
+        NEG     DX
+        SAVE    ARITH
+
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x00FF
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0881
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x00F0
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0085
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x8000
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0x0885
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0xFF00
+        EXPECT  __FILE__, __LINE__, MW, 0x0516, 0x0085
+        EXPECT  __FILE__, __LINE__, DONE
@@ -0,0 +1,35 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+_start:
+        MOV     DI, RESULTS
+
+        ; 0x80 | 0x01: sign, parity
+        MOV     DX, 0x0080
+        STC
+        OR      DL, 0x01
+        SAVE    LOGIC
+
+        ; 0 | 0: zero
+        MOV     DX, 0x0000
+        OR      DL, 0x00
+        SAVE    LOGIC
+
+        ; 0x0100 | 0x0001: word, odd parity
+        MOV     DX, 0x0100
+        STC
+        OR      DX, 0x0001
+        SAVE    LOGIC
+
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0081
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0084
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x0101
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0000
+        EXPECT  __FILE__, __LINE__, DONE
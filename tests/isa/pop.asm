@@ -11,7 +11,26 @@ _start:
         MOV     BX, 1
         PUSH    BX
         POP     AX
+
+        ; pop [mem] takes the word off the stack, then writes it: here over
+        ; the next word, which is popped again
+        MOV     DI, 0x2222
+        PUSH    DI
+        MOV     DI, 0x1111
+        PUSH    DI
+        MOV     BX, SP
+        POP     WORD [BX+2]
+        MOV     CX, SP
+        POP     DX
+
+        ; pop sp loads the popped value
+        MOV     DI, 0x1234
+        PUSH    DI
+        POP     SP
         HLT
 
         EXPECT  __FILE__, __LINE__, AX, 1
+        EXPECT  __FILE__, __LINE__, CX, 0xFFFC
+        EXPECT  __FILE__, __LINE__, DX, 0x1111
+        EXPECT  __FILE__, __LINE__, SP, 0x1234
         EXPECT  __FILE__, __LINE__, DONE
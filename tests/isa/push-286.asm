@@ -0,0 +1,18 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+_start:
+        MOV     SP, 0xfffe
+        MOV     AX, 0
+        MOV     SS, AX
+
+        ; the 80286 pushes sp as it was before the instruction
+        PUSH    SP
+        POP     BX
+        HLT
+
+        EXPECT  __FILE__, __LINE__, BX, 0xFFFE
+        EXPECT  __FILE__, __LINE__, SP, 0xFFFE
+        EXPECT  __FILE__, __LINE__, DONE
@@ -11,7 +11,25 @@ _start:
         MOV     BX, 1
         PUSH    BX
         POP     AX
+
+        ; the 8086 pushes sp once decremented
+        PUSH    SP
+        POP     BX
+        MOV     CX, SP
+        PUSH    SS
+        PUSH    CS
+        POP     DX
+        POP     SI
+        MOV     [0x0500], SP
+        PUSH    WORD [0x0500]
+        POP     DI
         HLT
 
         EXPECT  __FILE__, __LINE__, AX, 1
+        EXPECT  __FILE__, __LINE__, BX, 0xFFFC
+        EXPECT  __FILE__, __LINE__, CX, 0xFFFE
+        EXPECT  __FILE__, __LINE__, DX, 0xF000
+        EXPECT  __FILE__, __LINE__, SI, 0x0000
+        EXPECT  __FILE__, __LINE__, DI, 0xFFFE
+        EXPECT  __FILE__, __LINE__, SP, 0xFFFE
         EXPECT  __FILE__, __LINE__, DONE
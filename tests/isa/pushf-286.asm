@@ -0,0 +1,23 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+; on the 80286 in real mode, pushf clears bits 12-15
+_start:
+        MOV     SP, 0x1000
+        MOV     AX, 0
+        PUSH    AX
+        POPF
+        PUSHF
+        POP     BX
+        MOV     AX, 0xFEFF
+        PUSH    AX
+        POPF
+        PUSHF
+        POP     CX
+        HLT
+
+        EXPECT  __FILE__, __LINE__, BX, 0x0002
+        EXPECT  __FILE__, __LINE__, CX, 0x0ED7
+        EXPECT  __FILE__, __LINE__, DONE
@@ -0,0 +1,30 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+; on the 8086, pushf sets bits 12-15 and bit 1. popf only changes the
+; defined flags. 0xFEFF leaves TF clear, to not single-step
+_start:
+        MOV     SP, 0x1000
+        MOV     AX, 0
+        PUSH    AX
+        POPF
+        PUSHF
+        POP     BX
+        MOV     AX, 0xFEFF
+        PUSH    AX
+        POPF
+        PUSHF
+        POP     CX
+        MOV     AX, 0x08D5
+        PUSH    AX
+        POPF
+        PUSHF
+        POP     DX
+        HLT
+
+        EXPECT  __FILE__, __LINE__, BX, 0xF002
+        EXPECT  __FILE__, __LINE__, CX, 0xFED7
+        EXPECT  __FILE__, __LINE__, DX, 0xF8D7
+        EXPECT  __FILE__, __LINE__, DONE
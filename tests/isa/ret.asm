@@ -0,0 +1,44 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+; ret n and retf n pop the return address, then drop n bytes of arguments
+_start:
+        MOV     SP, 0x1000
+        MOV     AX, 0x1111
+        PUSH    AX
+        PUSH    AX
+        CALL    sub_near
+.n:     MOV     [0x0504], SP
+
+        PUSH    AX
+        PUSH    AX
+        CALL    0xF000:sub_far
+.f:     MOV     [0x050A], SP
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0FFA
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, .n
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x1000
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0FF8
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, .f
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x1000
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0xF000
+        EXPECT  __FILE__, __LINE__, DONE
+
+sub_near:
+        MOV     BP, SP
+        MOV     [0x0500], SP
+        MOV     BX, [BP]
+        MOV     [0x0502], BX
+        RET     4
+
+sub_far:
+        MOV     BP, SP
+        MOV     [0x0506], SP
+        MOV     BX, [BP]
+        MOV     [0x0508], BX
+        MOV     BX, [BP+2]
+        MOV     [0x050C], BX
+        RETF    4
@@ -2,12 +2,79 @@ CPU     8086
 BITS    16
 ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
 %include "expect.inc"
+%include "flags.inc"
 
 _start:
-        MOV AL, 0x05
+        MOV     DI, RESULTS
+
+        ; 0x80 - 0 - cf: signed overflow from the carry in
+        MOV     DX, 0x0080
+        STC
+        SBB     DL, 0x00
+        SAVE    ARITH
+
+        ; 0 - 0 - cf: borrow
+        MOV     DX, 0x0000
+        STC
+        SBB     DL, 0x00
+        SAVE    ARITH
+
+        ; 0x7f - 0xff - cf: borrow, no overflow
+        MOV     DX, 0x007F
+        STC
+        SBB     DL, 0xFF
+        SAVE    ARITH
+
+        ; 0xff - 0xff - cf: borrow and half borrow
+        MOV     DX, 0x00FF
+        STC
+        SBB     DL, 0xFF
+        SAVE    ARITH
+
+        ; 0x80 - 0x7f - cf: zero
+        MOV     DX, 0x0080
+        STC
+        SBB     DL, 0x7F
+        SAVE    ARITH
+
+        ; 0x80 - 0x01 without carry in
+        MOV     DX, 0x0080
+        CLC
+        SBB     DL, 0x01
+        SAVE    ARITH
+
+        ; 0 - 0 - cf: word borrow
+        MOV     DX, 0x0000
+        STC
+        SBB     DX, 0x0000
+        SAVE    ARITH
+
+        ; 0x8000 - 0 - cf: word overflow
+        MOV     DX, 0x8000
+        STC
+        SBB     DX, 0x0000
+        SAVE    ARITH
+
+        MOV     AL, 0x05
         STC
-        SBB AL, 0x01
+        SBB     AL, 0x01
         HLT
 
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x007F
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0810
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x00FF
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x007F
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0011
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x00FF
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0x0854
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0x007F
+        EXPECT  __FILE__, __LINE__, MW, 0x0516, 0x0810
+        EXPECT  __FILE__, __LINE__, MW, 0x0518, 0xFFFF
+        EXPECT  __FILE__, __LINE__, MW, 0x051A, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x051C, 0x7FFF
+        EXPECT  __FILE__, __LINE__, MW, 0x051E, 0x0814
         EXPECT  __FILE__, __LINE__, AL, 0x03
         EXPECT  __FILE__, __LINE__, DONE
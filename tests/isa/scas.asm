@@ -0,0 +1,83 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+; the strings are in the code: es = cs
+_start:
+        MOV     AX, CS
+        MOV     ES, AX
+        MOV     BX, RESULTS
+        MOV     SI, 0
+        CLD
+
+        ; repne stops at the first match
+        MOV     AL, 'L'
+        MOV     DI, str
+        MOV     CX, 5
+        REPNE   SCASB
+        SAVE_STR ARITH
+
+        ; or when cx runs out, 'Z' - 'O'
+        MOV     AL, 'Z'
+        MOV     DI, str
+        MOV     CX, 5
+        REPNE   SCASB
+        SAVE_STR ARITH
+
+        ; repe stops at the first difference, 'H' - 'A'
+        MOV     AL, 'H'
+        MOV     DI, hhh
+        MOV     CX, 6
+        REPE    SCASB
+        SAVE_STR ARITH
+
+        ; cx is 0: nothing is compared, the flags are those of the cmp
+        MOV     AL, 1
+        CMP     AL, 2
+        MOV     DI, str
+        MOV     CX, 0
+        REPNE   SCASB
+        SAVE_STR ARITH
+
+        ; backwards, words
+        STD
+        MOV     AX, 'HE'
+        MOV     DI, str+2
+        MOV     CX, 3
+        REPNE   SCASW
+        CLD
+        SAVE_STR ARITH
+
+        ; cx does not change without a prefix
+        MOV     AL, 'H'
+        MOV     DI, str
+        MOV     CX, 7
+        SCASB
+        SAVE_STR ARITH
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, str+3
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0002
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, str+5
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0010
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, hhh+4
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0x0002
+        EXPECT  __FILE__, __LINE__, MW, 0x0516, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x051A, str
+        EXPECT  __FILE__, __LINE__, MW, 0x051C, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x051E, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0522, str-2
+        EXPECT  __FILE__, __LINE__, MW, 0x0524, 0x0001
+        EXPECT  __FILE__, __LINE__, MW, 0x0526, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x052A, str+1
+        EXPECT  __FILE__, __LINE__, MW, 0x052C, 0x0007
+        EXPECT  __FILE__, __LINE__, MW, 0x052E, 0x0044
+        EXPECT  __FILE__, __LINE__, DONE
+
+str:    DB      'HELLO'
+hhh:    DB      'HHHAHH'
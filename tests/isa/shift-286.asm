@@ -0,0 +1,37 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+_start:
+        MOV     DI, RESULTS
+
+        ; the 80286 masks the count to 5 bits: 0x20 is 0, the flags are
+        ; those of the cmp
+        MOV     AL, 1
+        CMP     AL, 2
+        MOV     DX, 0xFFFF
+        MOV     CL, 0x20
+        SHL     DX, CL
+        SAVE    ARITH
+
+        ; and 0x21 is 1
+        MOV     DX, 0xFFFF
+        MOV     CL, 0x21
+        SHL     DX, CL
+        SAVE    LOGIC
+
+        ; d0 /6 is shl
+        MOV     DL, 0x81
+        DB      0xD0, 0xF2
+        SAVE    LOGIC
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0xFFFF
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0xFFFE
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0081
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0xFF02
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0801
+        EXPECT  __FILE__, __LINE__, DONE
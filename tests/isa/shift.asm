@@ -0,0 +1,125 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+; AF is undefined after the shifts, the rotates only change CF and OF
+_start:
+        MOV     DI, RESULTS
+        MOV     CL, 2
+
+        ; OF is set when the sign changes, for a count of 1
+        MOV     DL, 0x81
+        SHL     DL, 1
+        SAVE    LOGIC
+        MOV     DL, 0x60
+        SHL     DL, 1
+        SAVE    LOGIC
+
+        ; for more than 1, the 8086 leaves it as set by the last step
+        MOV     DL, 0x60
+        SHL     DL, CL
+        SAVE    LOGIC
+
+        MOV     DL, 0x81                ; of: the sign before the shift
+        SHR     DL, 1
+        SAVE    LOGIC
+        MOV     DL, 0x81
+        SAR     DL, 1
+        SAVE    LOGIC
+
+        MOV     DL, 0x81
+        ROL     DL, 1
+        SAVE    CO
+        MOV     DL, 0x01
+        ROR     DL, 1
+        SAVE    CO
+
+        ; rcl and rcr rotate through CF
+        STC
+        MOV     DL, 0x00
+        RCL     DL, 1
+        SAVE    CO
+        CLC
+        MOV     DL, 0x80
+        RCL     DL, 1
+        SAVE    CO
+        STC
+        MOV     DL, 0x01
+        RCR     DL, 1
+        SAVE    CO
+        STC                             ; 17 bits: back where it started
+        MOV     DX, 0x1234
+        MOV     CL, 17
+        RCR     DX, CL
+        SAVE    CO
+
+        ; a count of 0 changes nothing, the flags are those of the cmp
+        MOV     AL, 1
+        CMP     AL, 2
+        MOV     DX, 0x0081
+        MOV     CL, 0
+        SHL     DL, CL
+        SAVE    ARITH
+        MOV     AL, 1
+        CMP     AL, 2
+        MOV     DX, 0x0081
+        RCR     DL, CL
+        SAVE    ARITH
+
+        ; the 8086 does not mask the count
+        MOV     DX, 0xFFFF
+        MOV     CL, 0x20
+        SHL     DX, CL
+        SAVE    SZPC
+        MOV     DX, 0xFFFF
+        MOV     CL, 0x21
+        SHR     DX, CL
+        SAVE    SZPC
+
+        ; setmo (d0 /6, d1 /6) sets the operand to all ones, flags as for an
+        ; or
+        MOV     DL, 0x00
+        DB      0xD0, 0xF2              ; setmo dl
+        SAVE    LOGIC
+        MOV     DX, 0x0000
+        DB      0xD1, 0xF2              ; setmo dx
+        SAVE    LOGIC
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0002
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x00C0
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0884
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0081
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x0040
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x00C0
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0x0085
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0x0003
+        EXPECT  __FILE__, __LINE__, MW, 0x0516, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x0518, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x051A, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x051C, 0x0001
+        EXPECT  __FILE__, __LINE__, MW, 0x051E, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0520, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0522, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x0524, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x0526, 0x0801
+        EXPECT  __FILE__, __LINE__, MW, 0x0528, 0x1234
+        EXPECT  __FILE__, __LINE__, MW, 0x052A, 0x0001
+        EXPECT  __FILE__, __LINE__, MW, 0x052C, 0x0081
+        EXPECT  __FILE__, __LINE__, MW, 0x052E, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0530, 0x0081
+        EXPECT  __FILE__, __LINE__, MW, 0x0532, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0534, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0536, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x0538, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x053A, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x053C, 0x00FF
+        EXPECT  __FILE__, __LINE__, MW, 0x053E, 0x0084
+        EXPECT  __FILE__, __LINE__, MW, 0x0540, 0xFFFF
+        EXPECT  __FILE__, __LINE__, MW, 0x0542, 0x0084
+        EXPECT  __FILE__, __LINE__, DONE
@@ -1,13 +1,66 @@
 CPU     8086
 BITS    16
-ORG     0
-%include "./expect.inc"
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
 
 _start:
+        MOV     DI, RESULTS
+
+        ; 0x80 - 1: signed overflow, half borrow
+        MOV     DX, 0x0080
+        SUB     DL, 0x01
+        SAVE    ARITH
+
+        ; 0 - 1: borrow
+        MOV     DX, 0x0000
+        SUB     DL, 0x01
+        SAVE    ARITH
+
+        ; 5 - 5: zero
+        MOV     DX, 0x0005
+        SUB     DL, 0x05
+        SAVE    ARITH
+
+        ; 0x7f - 0xff: overflow and borrow
+        MOV     DX, 0x007F
+        SUB     DL, 0xFF
+        SAVE    ARITH
+
+        ; the carry in is ignored
+        MOV     DX, 0x0005
+        STC
+        SUB     DL, 0x01
+        SAVE    ARITH
+
+        ; 0x8000 - 1: word overflow
+        MOV     DX, 0x8000
+        SUB     DX, 0x0001
+        SAVE    ARITH
+
+        ; 0 - 1: word borrow
+        MOV     DX, 0x0000
+        SUB     DX, 0x0001
+        SAVE    ARITH
+
         MOV     AL, 0x05
         STC
         SUB     AL, 0x01
         HLT
 
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x007F
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0810
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x00FF
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0095
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x050C, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x050E, 0x0881
+        EXPECT  __FILE__, __LINE__, MW, 0x0510, 0x0004
+        EXPECT  __FILE__, __LINE__, MW, 0x0512, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0514, 0x7FFF
+        EXPECT  __FILE__, __LINE__, MW, 0x0516, 0x0814
+        EXPECT  __FILE__, __LINE__, MW, 0x0518, 0xFFFF
+        EXPECT  __FILE__, __LINE__, MW, 0x051A, 0x0095
         EXPECT  __FILE__, __LINE__, AL, 0x04
         EXPECT  __FILE__, __LINE__, DONE
@@ -0,0 +1,35 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+%include "flags.inc"
+
+_start:
+        MOV     DI, RESULTS
+
+        ; 0x80 & 0x80: sign, dl unchanged
+        MOV     DX, 0x0080
+        STC
+        TEST    DL, 0x80
+        SAVE    LOGIC
+
+        ; 0x0f & 0xf0: zero
+        MOV     DX, 0x000F
+        TEST    DL, 0xF0
+        SAVE    LOGIC
+
+        ; 0x8000 & 0x8001: word sign
+        MOV     DX, 0x8000
+        STC
+        TEST    DX, 0x8001
+        SAVE    LOGIC
+
+        HLT
+
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0080
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x000F
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x8000
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0084
+        EXPECT  __FILE__, __LINE__, DONE
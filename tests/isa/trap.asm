@@ -0,0 +1,54 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+_start:
+        MOV     SP, 0x1000
+        MOV     WORD [1*4], step
+        MOV     [1*4+2], CS
+        MOV     SI, 0x600               ; where step saves the return addresses
+        MOV     AX, SS
+
+        ; popf sets TF: the trap is taken after each instruction that starts
+        ; with TF set, so not after popf itself
+        PUSHF
+        POP     BX
+        OR      BX, 0x0100
+        PUSH    BX
+        POPF
+        NOP
+.t1:    NOP
+.t2:    MOV     SS, AX                  ; no trap until the next instruction
+        NOP
+.t3:    PUSHF
+.t4:    POP     BX
+.t5:    AND     BX, 0xFEFF
+.t6:    PUSH    BX
+.t7:    POPF                            ; clears TF, after a last trap
+.t8:    NOP
+        HLT
+
+        EXPECT  __FILE__, __LINE__, SI, 0x0610
+        EXPECT  __FILE__, __LINE__, MW, 0x0600, .t1
+        EXPECT  __FILE__, __LINE__, MW, 0x0602, .t2
+        EXPECT  __FILE__, __LINE__, MW, 0x0604, .t3
+        EXPECT  __FILE__, __LINE__, MW, 0x0606, .t4
+        EXPECT  __FILE__, __LINE__, MW, 0x0608, .t5
+        EXPECT  __FILE__, __LINE__, MW, 0x060A, .t6
+        EXPECT  __FILE__, __LINE__, MW, 0x060C, .t7
+        EXPECT  __FILE__, __LINE__, MW, 0x060E, .t8
+        EXPECT  __FILE__, __LINE__, TF, 0
+        EXPECT  __FILE__, __LINE__, DONE
+
+; single-step handler: TF is clear here, iret sets it again
+step:
+        PUSH    BP
+        PUSH    AX
+        MOV     BP, SP
+        MOV     AX, [BP+4]
+        MOV     [SI], AX
+        ADD     SI, 2
+        POP     AX
+        POP     BP
+        IRET
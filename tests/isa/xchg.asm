@@ -0,0 +1,22 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+_start:
+        MOV     AX, 0x1234
+        MOV     BX, 0x5678
+        XCHG    AX, BX
+        MOV     CX, 0xAABB
+        XCHG    CH, CL
+        MOV     WORD [0x0500], 0x1111
+        MOV     DX, 0x2222
+        XCHG    [0x0500], DX
+        HLT
+
+        EXPECT  __FILE__, __LINE__, AX, 0x5678
+        EXPECT  __FILE__, __LINE__, BX, 0x1234
+        EXPECT  __FILE__, __LINE__, CX, 0xBBAA
+        EXPECT  __FILE__, __LINE__, DX, 0x1111
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x2222
+        EXPECT  __FILE__, __LINE__, DONE
@@ -0,0 +1,22 @@
+CPU     8086
+BITS    16
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
+%include "expect.inc"
+
+_start:
+        ; al = [bx + al], in ds or the segment given
+        MOV     BYTE [0x0705], 0x42
+        MOV     BX, 0x0700
+        MOV     AX, 0x1205
+        XLATB
+        MOV     CL, AL
+        MOV     BX, table
+        MOV     AL, 2
+        CS      XLATB
+        HLT
+
+        EXPECT  __FILE__, __LINE__, CL, 0x42
+        EXPECT  __FILE__, __LINE__, AX, 0x12CC
+        EXPECT  __FILE__, __LINE__, DONE
+
+table:  DB      0xAA, 0xBB, 0xCC
@@ -1,12 +1,38 @@
 CPU     8086
 BITS    16
-ORG     0
+ORG     0       ; !!! hey: in fact, we start at 0xf000:0x0000
 %include "expect.inc"
+%include "flags.inc"
 
 _start:
+        MOV     DI, RESULTS
+
+        ; 0xff ^ 0x0f: sign
+        MOV     DX, 0x00FF
+        STC
+        XOR     DL, 0x0F
+        SAVE    LOGIC
+
+        ; 0x55 ^ 0x55: zero
+        MOV     DX, 0x0055
+        XOR     DL, 0x55
+        SAVE    LOGIC
+
+        ; 0xffff ^ 0x7ffe: word
+        MOV     DX, 0xFFFF
+        STC
+        XOR     DX, 0x7FFE
+        SAVE    LOGIC
+
         MOV     AL, 0x05
         XOR     AL, 0x01
         HLT
 
+        EXPECT  __FILE__, __LINE__, MW, 0x0500, 0x00F0
+        EXPECT  __FILE__, __LINE__, MW, 0x0502, 0x0084
+        EXPECT  __FILE__, __LINE__, MW, 0x0504, 0x0000
+        EXPECT  __FILE__, __LINE__, MW, 0x0506, 0x0044
+        EXPECT  __FILE__, __LINE__, MW, 0x0508, 0x8001
+        EXPECT  __FILE__, __LINE__, MW, 0x050A, 0x0080
         EXPECT  __FILE__, __LINE__, AX, 0x04
         EXPECT  __FILE__, __LINE__, DONE